@@ -0,0 +1,176 @@
+//! Agreement algorithms: strategies that choose an interval from the intervals of the sources, and
+//! judge every source against it. Code written against `AgreementAlgorithm` runs with either
+//! algorithm of the crate, chosen at runtime, e.g. by `by_name` from a configuration.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::averaging::FaultTolerantAverage;
+use crate::intersection::{self, Intersection};
+use crate::midpoint::WelchLynch;
+use crate::pipeline::Select;
+use crate::verdict::{self, source_intervals};
+use crate::{Interval, MarzulloError, SourceBound, SourceVerdict, Verdict};
+
+/// The chosen interval, and the verdict of every source against it, ordered by source.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Agreement {
+    pub interval: Interval,
+    pub verdicts: Vec<SourceVerdict>,
+}
+
+/// A strategy that chooses an interval from the intervals of the sources.
+pub trait AgreementAlgorithm {
+    /// The name of the algorithm, as `by_name` accepts it.
+    fn name(&self) -> &'static str;
+
+    /// Returns the chosen interval, or `None` if there are no sources at all.
+    fn interval(&self, source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError>;
+
+    /// Returns the verdict of every source against `interval`, ordered by source. By default, the
+    /// truechimers are the sources whose intervals contain it.
+    fn verdicts(&self, interval: &Interval, source_bounds: &[SourceBound]) -> Vec<SourceVerdict> {
+        interval.verdicts(source_bounds)
+    }
+
+    /// Returns the chosen interval with the verdicts, or `None` if there are no sources at all.
+    fn agree(&self, source_bounds: &[SourceBound]) -> Result<Option<Agreement>, MarzulloError> {
+        Ok(self.interval(source_bounds)?.map(|interval| Agreement {
+            verdicts: self.verdicts(&interval, source_bounds),
+            interval,
+        }))
+    }
+}
+
+/// Marzullo's algorithm, as `Interval::try_from_source_bounds`: the smallest interval consistent
+/// with the most sources.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct Marzullo;
+
+impl AgreementAlgorithm for Marzullo {
+    fn name(&self) -> &'static str {
+        "marzullo"
+    }
+
+    fn interval(&self, source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError> {
+        Interval::try_from_source_bounds(source_bounds.to_vec())
+    }
+}
+
+impl Select for Marzullo {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        Interval::try_from_source_bounds(source_bounds)
+    }
+}
+
+/// The intersection algorithm of NTP. As in NTP, the truechimers are the sources whose intervals
+/// intersect the chosen interval, which may be wider than some of them.
+impl AgreementAlgorithm for Intersection {
+    fn name(&self) -> &'static str {
+        "intersection"
+    }
+
+    fn interval(&self, source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError> {
+        intersection::intersect(source_bounds)
+    }
+
+    fn verdicts(&self, interval: &Interval, source_bounds: &[SourceBound]) -> Vec<SourceVerdict> {
+        intersecting(interval, source_bounds)
+    }
+}
+
+/// Returns the verdict of every source, ordered by source, where the truechimers are the sources
+/// whose intervals intersect `interval`.
+pub(crate) fn intersecting(
+    interval: &Interval,
+    source_bounds: &[SourceBound],
+) -> Vec<SourceVerdict> {
+    source_intervals(source_bounds)
+        .into_iter()
+        .map(|(source, lower, upper)| SourceVerdict {
+            source,
+            lower,
+            upper,
+            verdict: if lower <= interval.upper_bound() && upper >= interval.lower_bound() {
+                Verdict::Truechimer
+            } else {
+                Verdict::Falseticker
+            },
+            distance: verdict::distance(&lower, &upper, interval),
+        })
+        .collect()
+}
+
+/// An algorithm chosen at runtime, as the select stage of a `Pipeline`.
+impl Select for Box<dyn AgreementAlgorithm> {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        self.interval(&source_bounds)
+    }
+}
+
+/// Returns the algorithm of the crate with the given name, or `None` if there is none.
+pub fn by_name(name: &str) -> Option<Box<dyn AgreementAlgorithm>> {
+    match name {
+        "marzullo" => Some(Box::new(Marzullo)),
+        "intersection" => Some(Box::new(Intersection)),
+        "welch-lynch" => Some(Box::new(WelchLynch::default())),
+        "lamport-melliar-smith" => Some(Box::new(FaultTolerantAverage::default())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+    use crate::Pipeline;
+
+    #[test]
+    fn test_agreement_algorithms() {
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 0, 20), (1, 8, 16), (2, 9, 11), (3, 13, 14)]);
+        let truechimers = |algorithm: &dyn AgreementAlgorithm| {
+            let agreement = algorithm.agree(&source_bounds).unwrap().unwrap();
+            let interval = (
+                agreement.interval.lower_bound(),
+                agreement.interval.upper_bound(),
+            );
+            let sources: Vec<u8> = agreement
+                .verdicts
+                .iter()
+                .filter(|verdict| verdict.verdict == Verdict::Truechimer)
+                .map(|verdict| verdict.source)
+                .collect();
+            (interval, sources)
+        };
+        assert_eq!(
+            truechimers(by_name("marzullo").unwrap().as_ref()),
+            ((13, 14), Vec::from([0, 1, 3]))
+        );
+        // Source 2 intersects the wider interval, though it does not contain it:
+        assert_eq!(
+            truechimers(&Intersection),
+            ((9, 14), Vec::from([0, 1, 2, 3]))
+        );
+        assert!(by_name("median").is_none());
+        for name in [
+            "marzullo",
+            "intersection",
+            "welch-lynch",
+            "lamport-melliar-smith",
+        ] {
+            assert_eq!(by_name(name).unwrap().name(), name);
+        }
+
+        let mut pipeline = Pipeline::new().with_selector(by_name("intersection").unwrap());
+        let outcome = pipeline.run([]).unwrap();
+        assert_eq!(outcome.interval, None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Alarms on the symptoms operators page on: the midpoint of the chosen interval jumping away from
+//! that of the previous selection, or the width of the interval exploding. An `AnomalyDetector` is
+//! an `Observer`, so it raises alarms for every selection of the `Engine` it is registered with.
+
+use alloc::vec::Vec;
+
+use crate::{Interval, MarzulloError, Observer};
+
+/// An anomaly of a selection against the previous one.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alarm {
+    /// The midpoint of the interval moved by more than `max_jump`.
+    OffsetJump { previous: i64, current: i64 },
+    /// The width of the interval grew by more than `max_width_factor`.
+    WidthExplosion { previous: u64, current: u64 },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct AlarmThresholds {
+    /// The largest move of the midpoint between two selections, in nanoseconds.
+    pub max_jump: u64,
+    /// The largest factor by which the width may grow between two selections. A zero width
+    /// counts as 1ns.
+    pub max_width_factor: u64,
+}
+
+/// Compares every chosen interval with the previous one, and calls `on_alarm` for every anomaly.
+pub struct AnomalyDetector<F> {
+    thresholds: AlarmThresholds,
+    previous: Option<(i64, u64)>,
+    on_alarm: F,
+}
+
+impl<F: FnMut(&Alarm)> AnomalyDetector<F> {
+    pub fn new(thresholds: AlarmThresholds, on_alarm: F) -> AnomalyDetector<F> {
+        AnomalyDetector {
+            thresholds,
+            previous: None,
+            on_alarm,
+        }
+    }
+
+    /// Compares `interval` with the previous interval, which it then replaces, and returns the
+    /// anomalies after calling `on_alarm` for each.
+    pub fn check(&mut self, interval: &Interval) -> Vec<Alarm> {
+        let width = interval.width();
+        let midpoint = (interval.lower_bound() as i128 + width as i128 / 2) as i64;
+        let mut alarms = Vec::new();
+        if let Some((previous, previous_width)) = self.previous.replace((midpoint, width)) {
+            if previous.abs_diff(midpoint) > self.thresholds.max_jump {
+                alarms.push(Alarm::OffsetJump {
+                    previous,
+                    current: midpoint,
+                });
+            }
+            if width as u128
+                > previous_width.max(1) as u128 * self.thresholds.max_width_factor as u128
+            {
+                alarms.push(Alarm::WidthExplosion {
+                    previous: previous_width,
+                    current: width,
+                });
+            }
+        }
+        for alarm in &alarms {
+            (self.on_alarm)(alarm);
+        }
+        alarms
+    }
+}
+
+impl<F: FnMut(&Alarm)> Observer for AnomalyDetector<F> {
+    fn on_result(&mut self, result: &Result<Option<Interval>, MarzulloError>) {
+        if let Ok(Some(interval)) = result {
+            self.check(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Engine, SourceBound};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_anomaly_detector() {
+        let alarms = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+        let recorded = Rc::clone(&alarms);
+        engine.add_observer(AnomalyDetector::new(
+            AlarmThresholds {
+                max_jump: 100,
+                max_width_factor: 4,
+            },
+            move |alarm: &Alarm| recorded.borrow_mut().push(*alarm),
+        ));
+
+        let select = |engine: &mut Engine, lower, upper| {
+            engine
+                .select(Vec::from(SourceBound::pair(0, lower, upper)))
+                .unwrap();
+        };
+        select(&mut engine, 0, 10);
+        select(&mut engine, 50, 60);
+        assert!(alarms.borrow().is_empty());
+        select(&mut engine, 200, 210);
+        select(&mut engine, 180, 240);
+        assert_eq!(
+            *alarms.borrow(),
+            [
+                Alarm::OffsetJump {
+                    previous: 55,
+                    current: 205,
+                },
+                Alarm::WidthExplosion {
+                    previous: 10,
+                    current: 60,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! The 13 relations of Allen's interval algebra, e.g. to classify how a rejected source relates to
+//! the chosen interval. Intervals are closed, so an interval meets another when the upper bound of
+//! one is the lower bound of the other; a single value within an interval starts, finishes, or is
+//! during it instead.
+
+use core::cmp::Ordering;
+
+use alloc::vec::Vec;
+
+use crate::value::Value;
+use crate::verdict::source_intervals;
+use crate::{Interval, SourceBound};
+
+/// How an interval `a` relates to an interval `b`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Relation {
+    /// `a` ends before `b` starts.
+    Before,
+    /// `a` ends where `b` starts.
+    Meets,
+    /// `a` starts before `b`, and ends within it.
+    Overlaps,
+    /// `a` starts before `b`, and ends with it.
+    FinishedBy,
+    /// `a` starts before `b`, and ends after it.
+    Contains,
+    /// `a` starts with `b`, and ends within it.
+    Starts,
+    Equals,
+    /// `a` starts with `b`, and ends after it.
+    StartedBy,
+    /// `a` starts and ends within `b`.
+    During,
+    /// `a` starts within `b`, and ends with it.
+    Finishes,
+    /// `a` starts within `b`, and ends after it.
+    OverlappedBy,
+    /// `a` starts where `b` ends.
+    MetBy,
+    /// `a` starts after `b` ends.
+    After,
+}
+
+impl Relation {
+    /// Returns how `b` relates to `a`.
+    pub fn inverse(self) -> Relation {
+        match self {
+            Relation::Before => Relation::After,
+            Relation::Meets => Relation::MetBy,
+            Relation::Overlaps => Relation::OverlappedBy,
+            Relation::FinishedBy => Relation::Finishes,
+            Relation::Contains => Relation::During,
+            Relation::Starts => Relation::StartedBy,
+            Relation::Equals => Relation::Equals,
+            Relation::StartedBy => Relation::Starts,
+            Relation::During => Relation::Contains,
+            Relation::Finishes => Relation::FinishedBy,
+            Relation::OverlappedBy => Relation::Overlaps,
+            Relation::MetBy => Relation::Meets,
+            Relation::After => Relation::Before,
+        }
+    }
+
+    /// Returns whether the intervals have no value in common.
+    pub fn is_disjoint(self) -> bool {
+        matches!(self, Relation::Before | Relation::After)
+    }
+
+    /// Returns whether every value of `a` is in `b`.
+    pub fn is_within(self) -> bool {
+        matches!(
+            self,
+            Relation::Starts | Relation::Equals | Relation::During | Relation::Finishes
+        )
+    }
+}
+
+/// Returns how `[a_lower, a_upper]` relates to `[b_lower, b_upper]`, or `None` if a lower bound
+/// is greater than its upper bound.
+pub fn relate<T: Ord>(a_lower: &T, a_upper: &T, b_lower: &T, b_upper: &T) -> Option<Relation> {
+    if a_lower > a_upper || b_lower > b_upper {
+        return None;
+    }
+    if a_upper < b_lower {
+        return Some(Relation::Before);
+    }
+    if a_lower > b_upper {
+        return Some(Relation::After);
+    }
+    // A single value at the end of the other interval is within it rather than meeting it:
+    if a_upper == b_lower && a_lower < a_upper && b_lower < b_upper {
+        return Some(Relation::Meets);
+    }
+    if a_lower == b_upper && a_lower < a_upper && b_lower < b_upper {
+        return Some(Relation::MetBy);
+    }
+    let relation = match (a_lower.cmp(b_lower), a_upper.cmp(b_upper)) {
+        (Ordering::Less, Ordering::Less) => Relation::Overlaps,
+        (Ordering::Less, Ordering::Equal) => Relation::FinishedBy,
+        (Ordering::Less, Ordering::Greater) => Relation::Contains,
+        (Ordering::Equal, Ordering::Less) => Relation::Starts,
+        (Ordering::Equal, Ordering::Equal) => Relation::Equals,
+        (Ordering::Equal, Ordering::Greater) => Relation::StartedBy,
+        (Ordering::Greater, Ordering::Less) => Relation::During,
+        (Ordering::Greater, Ordering::Equal) => Relation::Finishes,
+        (Ordering::Greater, Ordering::Greater) => Relation::OverlappedBy,
+    };
+    Some(relation)
+}
+
+impl<T: Value> Interval<T> {
+    /// Returns how this interval relates to `other`.
+    pub fn relation(&self, other: &Interval<T>) -> Relation {
+        relate(
+            &self.lower_bound,
+            &self.upper_bound,
+            &other.lower_bound,
+            &other.upper_bound,
+        )
+        .expect("the bounds of an interval are ordered")
+    }
+
+    /// Returns how the interval of every source with both bounds relates to this interval, by
+    /// source. Sources whose lower bound is greater than their upper bound are skipped.
+    pub fn source_relations(&self, source_bounds: &[SourceBound<T>]) -> Vec<(u8, Relation)> {
+        source_intervals(source_bounds)
+            .into_iter()
+            .filter_map(|(source, lower, upper)| {
+                let relation = relate(&lower, &upper, &self.lower_bound, &self.upper_bound)?;
+                Some((source, relation))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_relate() {
+        let b = (10, 20);
+        let cases = [
+            ((0, 5), Relation::Before),
+            ((0, 10), Relation::Meets),
+            ((0, 15), Relation::Overlaps),
+            ((0, 20), Relation::FinishedBy),
+            ((0, 25), Relation::Contains),
+            ((10, 15), Relation::Starts),
+            ((10, 20), Relation::Equals),
+            ((10, 25), Relation::StartedBy),
+            ((12, 18), Relation::During),
+            ((15, 20), Relation::Finishes),
+            ((15, 25), Relation::OverlappedBy),
+            ((20, 25), Relation::MetBy),
+            ((21, 25), Relation::After),
+            // Single values at either end of `b`:
+            ((10, 10), Relation::Starts),
+            ((20, 20), Relation::Finishes),
+        ];
+        for ((lower, upper), relation) in cases {
+            assert_eq!(relate(&lower, &upper, &b.0, &b.1), Some(relation));
+            assert_eq!(relate(&b.0, &b.1, &lower, &upper), Some(relation.inverse()));
+        }
+        assert_eq!(relate(&15, &12, &b.0, &b.1), None);
+    }
+
+    #[test]
+    fn test_source_relations() {
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 8, 12), (1, 9, 13), (2, 10, 14), (3, 40, 60)]);
+        let interval = Interval::try_from_source_bounds(source_bounds.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.relation(&interval), Relation::Equals);
+        assert_eq!(
+            interval.source_relations(&source_bounds),
+            [
+                (0, Relation::FinishedBy),
+                (1, Relation::Contains),
+                (2, Relation::StartedBy),
+                (3, Relation::After),
+            ]
+        );
+
+        // A source with inverted bounds relates to nothing, rather than panicking:
+        let mut source_bounds = source_bounds;
+        source_bounds.extend(SourceBound::pair(4, 30, 20));
+        let relations = interval.source_relations(&source_bounds);
+        assert_eq!(relations.len(), 4);
+        assert!(relations.iter().all(|&(source, _)| source != 4));
+    }
+}
@@ -0,0 +1,124 @@
+//! Arithmetic on the chosen interval, for post-processing it without taking it apart, e.g. adding
+//! a known path delay. Every operation is checked, and keeps the counts of the sources.
+
+use crate::interop::ConversionError;
+use crate::Interval;
+
+macro_rules! impl_arithmetic_for_integers {
+    ($($ty:ty => $width:ty, $add:ident, $sub:ident);*) => {
+        $(
+            impl Interval<$ty> {
+                fn with_bounds(&self, lower_bound: $ty, upper_bound: $ty) -> Interval<$ty> {
+                    Interval {
+                        lower_bound,
+                        upper_bound,
+                        sources_true: self.sources_true,
+                        sources_false: self.sources_false,
+                    }
+                }
+
+                /// Returns the interval moved by `offset`, or `OutOfRange` if a bound overflows.
+                pub fn shift(&self, offset: $ty) -> Result<Interval<$ty>, ConversionError> {
+                    let lower = self.lower_bound.checked_add(offset);
+                    let upper = self.upper_bound.checked_add(offset);
+                    match (lower, upper) {
+                        (Some(lower), Some(upper)) => Ok(self.with_bounds(lower, upper)),
+                        _ => Err(ConversionError::OutOfRange),
+                    }
+                }
+
+                /// Returns the interval with both bounds multiplied by `factor`, swapped if it is
+                /// negative, or `OutOfRange` if a bound overflows.
+                pub fn scale(&self, factor: $ty) -> Result<Interval<$ty>, ConversionError> {
+                    let lower = self.lower_bound.checked_mul(factor);
+                    let upper = self.upper_bound.checked_mul(factor);
+                    match (lower, upper) {
+                        (Some(lower), Some(upper)) => {
+                            Ok(self.with_bounds(lower.min(upper), lower.max(upper)))
+                        }
+                        _ => Err(ConversionError::OutOfRange),
+                    }
+                }
+
+                /// Returns the interval widened by `margin` on both sides, or `OutOfRange` if a
+                /// bound overflows.
+                pub fn expand(&self, margin: $width) -> Result<Interval<$ty>, ConversionError> {
+                    let lower = self.lower_bound.$sub(margin);
+                    let upper = self.upper_bound.$add(margin);
+                    match (lower, upper) {
+                        (Some(lower), Some(upper)) => Ok(self.with_bounds(lower, upper)),
+                        _ => Err(ConversionError::OutOfRange),
+                    }
+                }
+
+                /// Returns the interval narrowed by `margin` on both sides, or `NegativeDuration`
+                /// if it is narrower than twice the margin.
+                pub fn contract(&self, margin: $width) -> Result<Interval<$ty>, ConversionError> {
+                    if self.width() / 2 < margin {
+                        return Err(ConversionError::NegativeDuration);
+                    }
+                    // Neither bound can overflow, as both stay within the interval:
+                    let lower = self.lower_bound.$add(margin).expect("within the interval");
+                    let upper = self.upper_bound.$sub(margin).expect("within the interval");
+                    Ok(self.with_bounds(lower, upper))
+                }
+
+                /// Returns the part of the interval within `[min, max]`, or `OutOfRange` if there
+                /// is none.
+                pub fn clamp(&self, min: $ty, max: $ty) -> Result<Interval<$ty>, ConversionError> {
+                    let lower = self.lower_bound.max(min);
+                    let upper = self.upper_bound.min(max);
+                    if lower > upper {
+                        return Err(ConversionError::OutOfRange);
+                    }
+                    Ok(self.with_bounds(lower, upper))
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic_for_integers!(
+    i8 => u8, checked_add_unsigned, checked_sub_unsigned;
+    i16 => u16, checked_add_unsigned, checked_sub_unsigned;
+    i32 => u32, checked_add_unsigned, checked_sub_unsigned;
+    i64 => u64, checked_add_unsigned, checked_sub_unsigned;
+    i128 => u128, checked_add_unsigned, checked_sub_unsigned;
+    isize => usize, checked_add_unsigned, checked_sub_unsigned;
+    u8 => u8, checked_add, checked_sub;
+    u16 => u16, checked_add, checked_sub;
+    u32 => u32, checked_add, checked_sub;
+    u64 => u64, checked_add, checked_sub;
+    u128 => u128, checked_add, checked_sub;
+    usize => usize, checked_add, checked_sub
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_interval_arithmetic() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 10, 20));
+        source_bounds.extend(SourceBound::pair(1, -5, 50));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        let bounds = |interval: Interval| (interval.lower_bound(), interval.upper_bound());
+
+        let shifted = interval.shift(-100).unwrap();
+        assert_eq!(bounds(shifted.clone()), (-90, -80));
+        assert_eq!(shifted.sources_true(), 2);
+        assert_eq!(bounds(interval.scale(-3).unwrap()), (-60, -30));
+        assert_eq!(bounds(interval.expand(5).unwrap()), (5, 25));
+        assert_eq!(bounds(interval.contract(5).unwrap()), (15, 15));
+        assert_eq!(interval.contract(6), Err(ConversionError::NegativeDuration));
+        assert_eq!(bounds(interval.clamp(0, 12).unwrap()), (10, 12));
+        assert_eq!(interval.clamp(30, 40), Err(ConversionError::OutOfRange));
+        assert_eq!(interval.shift(i64::MAX), Err(ConversionError::OutOfRange));
+        assert_eq!(interval.expand(u64::MAX), Err(ConversionError::OutOfRange));
+    }
+}
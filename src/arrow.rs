@@ -0,0 +1,208 @@
+//! Bulk selection over Arrow record batches, for fleet-wide agreement analysis in dataframe
+//! pipelines.
+//!
+//! The input batch has a `group` column of any type, and `lower` and `upper` columns of `Int64`.
+//! Every row is a source of its group, and a null bound is an unbounded side of the source. The
+//! result batch has one row per group, in order of first appearance, with the columns `group`,
+//! `lower_bound`, `upper_bound` (null where unbounded), `sources_true`, and `sources_false`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::Int64Type;
+use arrow_array::{
+    Array, ArrayRef, Int64Array, PrimitiveArray, RecordBatch, UInt64Array, UInt8Array,
+};
+use arrow_row::{RowConverter, SortField};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+use crate::{Interval, MarzulloError, SourceBound};
+
+#[derive(Debug)]
+pub enum BatchError {
+    /// The batch is missing a column or a column has the wrong type, or an Arrow kernel failed.
+    Arrow(ArrowError),
+    /// A group has more than 255 sources. The row is the first row past the limit.
+    TooManySources { row: usize },
+    /// The algorithm rejected the bounds of a group. The row is the first row of the group, and
+    /// the source of a bound is its position within the group.
+    Marzullo { row: usize, error: MarzulloError },
+}
+
+impl core::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            BatchError::Arrow(error) => write!(f, "{}", error),
+            BatchError::TooManySources { row } => {
+                write!(f, "row {}: group has more than 255 sources", row)
+            }
+            BatchError::Marzullo { row, error } => write!(f, "group at row {}: {}", row, error),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<ArrowError> for BatchError {
+    fn from(error: ArrowError) -> BatchError {
+        BatchError::Arrow(error)
+    }
+}
+
+fn column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef, ArrowError> {
+    batch
+        .column_by_name(name)
+        .ok_or_else(|| ArrowError::SchemaError(format!("missing column {:?}", name)))
+}
+
+fn bounds_column<'a>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a PrimitiveArray<Int64Type>, ArrowError> {
+    column(batch, name)?
+        .as_primitive_opt::<Int64Type>()
+        .ok_or_else(|| ArrowError::SchemaError(format!("column {:?} should be Int64", name)))
+}
+
+/// Runs the algorithm over the sources of every group in `batch`.
+pub fn select_by_group(batch: &RecordBatch) -> Result<RecordBatch, BatchError> {
+    let group = column(batch, "group")?;
+    let lower = bounds_column(batch, "lower")?;
+    let upper = bounds_column(batch, "upper")?;
+
+    // Groups are keyed by their row encoding, which supports any column type.
+    let converter = RowConverter::new(vec![SortField::new(group.data_type().clone())])?;
+    let rows = converter.convert_columns(&[Arc::clone(group)])?;
+    let mut indexes = HashMap::new();
+    let mut groups: Vec<(usize, Vec<SourceBound>)> = Vec::new();
+    for row in 0..batch.num_rows() {
+        let index = *indexes.entry(rows.row(row)).or_insert_with(|| {
+            groups.push((row, Vec::new()));
+            groups.len() - 1
+        });
+        let source_bounds = &mut groups[index].1;
+        // Up to 255 sources, which is as many as `sources_true` can count:
+        let source = u8::try_from(source_bounds.len() / 2)
+            .ok()
+            .filter(|&source| source < u8::MAX)
+            .ok_or(BatchError::TooManySources { row })?;
+        source_bounds.extend(SourceBound::pair_optional(
+            source,
+            lower.is_valid(row).then(|| lower.value(row)),
+            upper.is_valid(row).then(|| upper.value(row)),
+        ));
+    }
+
+    let mut first_rows = Vec::with_capacity(groups.len());
+    let mut intervals = Vec::with_capacity(groups.len());
+    for (row, source_bounds) in groups {
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .map_err(|error| BatchError::Marzullo { row, error })?
+            .expect("every group has at least one source");
+        first_rows.push(row as u64);
+        intervals.push(interval);
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("group", group.data_type().clone(), group.is_nullable()),
+        Field::new("lower_bound", DataType::Int64, true),
+        Field::new("upper_bound", DataType::Int64, true),
+        Field::new("sources_true", DataType::UInt8, false),
+        Field::new("sources_false", DataType::UInt8, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        arrow_select::take::take(group, &UInt64Array::from(first_rows), None)?,
+        Arc::new(
+            intervals
+                .iter()
+                .map(|ivl| (!ivl.is_lower_unbounded()).then(|| ivl.lower_bound()))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            intervals
+                .iter()
+                .map(|ivl| (!ivl.is_upper_unbounded()).then(|| ivl.upper_bound()))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(UInt8Array::from_iter_values(
+            intervals.iter().map(Interval::sources_true),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            intervals.iter().map(Interval::sources_false),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::StringArray;
+
+    fn batch(groups: Vec<&str>, lowers: Vec<Option<i64>>, uppers: Vec<Option<i64>>) -> RecordBatch {
+        RecordBatch::try_from_iter([
+            ("group", Arc::new(StringArray::from(groups)) as ArrayRef),
+            ("lower", Arc::new(Int64Array::from(lowers)) as ArrayRef),
+            ("upper", Arc::new(Int64Array::from(uppers)) as ArrayRef),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_select_by_group() {
+        let input = batch(
+            vec!["eu", "us", "eu", "us", "eu"],
+            vec![Some(11), Some(1), Some(10), None, Some(8)],
+            vec![Some(13), Some(4), Some(12), Some(3), Some(12)],
+        );
+        let output = select_by_group(&input).unwrap();
+        assert_eq!(output.num_rows(), 2);
+        assert_eq!(
+            output.column(0).as_string::<i32>(),
+            &StringArray::from(vec!["eu", "us"])
+        );
+        assert_eq!(
+            output.column(1).as_primitive::<Int64Type>(),
+            &Int64Array::from(vec![11, 1])
+        );
+        assert_eq!(
+            output.column(2).as_primitive::<Int64Type>(),
+            &Int64Array::from(vec![12, 3])
+        );
+        assert_eq!(
+            output.column(3).as_any().downcast_ref::<UInt8Array>(),
+            Some(&UInt8Array::from(vec![3, 2]))
+        );
+
+        // Unbounded results are null:
+        let output = select_by_group(&batch(vec!["eu"], vec![Some(1)], vec![None])).unwrap();
+        assert!(output.column(2).is_null(0));
+    }
+
+    #[test]
+    fn test_select_by_group_errors() {
+        let input = RecordBatch::try_from_iter([(
+            "group",
+            Arc::new(Int64Array::from(vec![0])) as ArrayRef,
+        )])
+        .unwrap();
+        assert!(matches!(
+            select_by_group(&input),
+            Err(BatchError::Arrow(ArrowError::SchemaError(_)))
+        ));
+
+        let input = batch(vec!["eu"; 255], vec![Some(0); 255], vec![Some(1); 255]);
+        let output = select_by_group(&input).unwrap();
+        assert_eq!(
+            output.column(3).as_any().downcast_ref::<UInt8Array>(),
+            Some(&UInt8Array::from(vec![255]))
+        );
+        let input = batch(vec!["eu"; 256], vec![Some(0); 256], vec![Some(1); 256]);
+        assert!(matches!(
+            select_by_group(&input),
+            Err(BatchError::TooManySources { row: 255 })
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+//! Fault-tolerant averaging, as in the interactive convergence algorithm of Lamport and
+//! Melliar-Smith: readings that deviate from the local clock by more than `δ` are ignored, and the
+//! rest are averaged.
+//!
+//! If the correct clocks lie within `δ` of the local clock, they are all averaged, and each of `f`
+//! faulty clocks can only be averaged where it lies within `δ` of the local clock too, whatever it
+//! reports. Of `n` readings, the average then lies within `2δf / (n - f)` of the average of the
+//! correct readings, which is below `δ` for `n > 3f`: the bound under which repeated rounds
+//! converge despite Byzantine faults.
+
+use alloc::vec::Vec;
+
+use crate::agreement::AgreementAlgorithm;
+use crate::discipline::STEP_LIMIT;
+use crate::pipeline::Select;
+use crate::verdict::{self, source_intervals, Verdict};
+use crate::{Interval, MarzulloError, SourceBound, SourceVerdict};
+
+/// The fault-tolerant average as an agreement algorithm and the select stage of a `Pipeline`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FaultTolerantAverage {
+    delta: u64,
+    reference: i64,
+}
+
+/// Ignores the sources that deviate from the local clock by more than `STEP_LIMIT`, beyond which
+/// the local clock would be stepped anyway.
+impl Default for FaultTolerantAverage {
+    fn default() -> FaultTolerantAverage {
+        FaultTolerantAverage::new(STEP_LIMIT)
+    }
+}
+
+impl FaultTolerantAverage {
+    /// Ignores the sources whose midpoints deviate from the local clock, at an offset of zero, by
+    /// more than `delta`.
+    pub fn new(delta: u64) -> FaultTolerantAverage {
+        FaultTolerantAverage {
+            delta,
+            reference: 0,
+        }
+    }
+
+    /// Measures the deviations from this offset instead, e.g. the last agreed offset.
+    pub fn with_reference(mut self, reference: i64) -> FaultTolerantAverage {
+        self.reference = reference;
+        self
+    }
+
+    /// Whether the source with the interval `[lower, upper]` is averaged.
+    fn accepts(&self, lower: i64, upper: i64) -> bool {
+        let midpoint = (lower as i128 + upper as i128).div_euclid(2) as i64;
+        midpoint.abs_diff(self.reference) <= self.delta
+    }
+}
+
+/// Returns the mean of `values`, rounded toward negative infinity.
+fn mean(values: impl Iterator<Item = i64>) -> i64 {
+    let (sum, count) = values.fold((0_i128, 0_i128), |(sum, count), value| {
+        (sum + value as i128, count + 1)
+    });
+    sum.div_euclid(count) as i64
+}
+
+impl AgreementAlgorithm for FaultTolerantAverage {
+    fn name(&self) -> &'static str {
+        "lamport-melliar-smith"
+    }
+
+    /// Returns the means of the lower and of the upper bounds of the sources that are averaged, or
+    /// `MarzulloError::NoMajority` if none is. Of the interval, `sources_true` is the number of
+    /// sources that are averaged.
+    fn interval(&self, source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError> {
+        let sources = source_intervals(source_bounds);
+        if sources.is_empty() {
+            return Ok(None);
+        }
+        let accepted: Vec<(i64, i64)> = sources
+            .iter()
+            .map(|&(_, lower, upper)| (lower, upper))
+            .filter(|&(lower, upper)| self.accepts(lower, upper))
+            .collect();
+        if accepted.is_empty() {
+            return Err(MarzulloError::NoMajority {
+                sources: sources.len(),
+            });
+        }
+        Ok(Some(Interval {
+            lower_bound: mean(accepted.iter().map(|&(lower, _)| lower)),
+            upper_bound: mean(accepted.iter().map(|&(_, upper)| upper)),
+            sources_true: accepted.len() as u8,
+            sources_false: (sources.len() - accepted.len()) as u8,
+        }))
+    }
+
+    /// The truechimers are the sources that are averaged.
+    fn verdicts(&self, interval: &Interval, source_bounds: &[SourceBound]) -> Vec<SourceVerdict> {
+        source_intervals(source_bounds)
+            .into_iter()
+            .map(|(source, lower, upper)| SourceVerdict {
+                source,
+                lower,
+                upper,
+                verdict: if self.accepts(lower, upper) {
+                    Verdict::Truechimer
+                } else {
+                    Verdict::Falseticker
+                },
+                distance: verdict::distance(&lower, &upper, interval),
+            })
+            .collect()
+    }
+}
+
+impl Select for FaultTolerantAverage {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        self.interval(&source_bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(midpoints: &[i64]) -> Vec<SourceBound> {
+        midpoints
+            .iter()
+            .enumerate()
+            .flat_map(|(source, &midpoint)| {
+                SourceBound::pair(source as u8, midpoint - 10, midpoint + 10)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fault_tolerant_average() {
+        const DELTA: i64 = 100;
+        let average = FaultTolerantAverage::new(DELTA as u64);
+        assert_eq!(average.interval(&[]), Ok(None));
+
+        // Three correct clocks within δ, averaging 20, and a faulty clock far off, which is
+        // ignored:
+        let agreement = average
+            .agree(&bounds(&[0, 20, 40, 5_000]))
+            .unwrap()
+            .unwrap();
+        let interval = agreement.interval;
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (10, 30));
+        assert_eq!((interval.sources_true(), interval.sources_false()), (3, 1));
+        assert_eq!(agreement.verdicts[3].verdict, Verdict::Falseticker);
+
+        // The faulty clock pulls the most from just within δ, by at most 2δf / (n - f):
+        for faulty in [-DELTA, DELTA] {
+            let interval = average.interval(&bounds(&[0, 20, 40, faulty])).unwrap();
+            let midpoint = interval.unwrap().lower_bound() + 10;
+            assert!(midpoint.abs_diff(20) as i64 <= 2 * DELTA / 3);
+        }
+
+        assert_eq!(
+            average.with_reference(1_000).interval(&bounds(&[0, 20])),
+            Err(MarzulloError::NoMajority { sources: 2 })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The Berkeley algorithm, for a closed group of clocks without an external time source: a
+//! coordinator samples the offset of every participant from its own clock, discards the outliers,
+//! and averages the rest with its own, at an offset of zero. Rather than a time, every participant,
+//! and the coordinator itself, is then sent the correction that brings it to the average, so that
+//! the delay of the message does not matter.
+//!
+//! The outliers are the falsetickers of an `AgreementAlgorithm` over the samples, Marzullo's
+//! algorithm by default.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::agreement::{AgreementAlgorithm, Marzullo};
+use crate::source::Sample;
+use crate::{MarzulloError, Verdict};
+
+/// The correction of one participant.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Correction {
+    pub participant: u8,
+    /// The adjustment to apply to the clock of the participant, in nanoseconds.
+    pub correction: i64,
+    /// Whether the participant was left out of the average, as an outlier or because the bounds of
+    /// its sample could not be computed. It is corrected all the same.
+    pub outlier: bool,
+}
+
+/// The outcome of one round of the Berkeley algorithm.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Round {
+    /// The average offset from the clock of the coordinator, in nanoseconds, which is the
+    /// correction of the coordinator itself.
+    pub average: i64,
+    /// The correction of every participant, ordered by participant.
+    pub corrections: Vec<Correction>,
+}
+
+/// The coordinator of a group of clocks.
+pub struct Coordinator {
+    algorithm: Box<dyn AgreementAlgorithm>,
+}
+
+impl Default for Coordinator {
+    fn default() -> Coordinator {
+        Coordinator {
+            algorithm: Box::new(Marzullo),
+        }
+    }
+}
+
+impl Coordinator {
+    pub fn new() -> Coordinator {
+        Coordinator::default()
+    }
+
+    /// Discards the falsetickers of `algorithm` rather than of Marzullo's algorithm.
+    pub fn with_algorithm<A: AgreementAlgorithm + 'static>(mut self, algorithm: A) -> Coordinator {
+        self.algorithm = Box::new(algorithm);
+        self
+    }
+
+    /// Returns the corrections for the latest sample of every participant, by its index, of the
+    /// participant's clock against that of the coordinator.
+    pub fn round(
+        &self,
+        samples: impl IntoIterator<Item = (u8, Sample)>,
+    ) -> Result<Round, MarzulloError> {
+        let mut samples: Vec<(u8, Sample)> = samples.into_iter().collect();
+        samples.sort_unstable_by_key(|&(participant, _)| participant);
+        let source_bounds: Vec<_> = samples
+            .iter()
+            .filter_map(|&(participant, sample)| sample.bounds(participant).ok())
+            .flatten()
+            .collect();
+        let truechimers: Vec<u8> = match self.algorithm.agree(&source_bounds)? {
+            Some(agreement) => agreement
+                .verdicts
+                .iter()
+                .filter(|verdict| verdict.verdict == Verdict::Truechimer)
+                .map(|verdict| verdict.source)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // The coordinator counts with an offset of zero:
+        let (sum, count) = samples
+            .iter()
+            .filter(|(participant, _)| truechimers.contains(participant))
+            .fold((0_i128, 1_i128), |(sum, count), (_, sample)| {
+                (sum + sample.offset as i128, count + 1)
+            });
+        let average = sum.div_euclid(count) as i64;
+        Ok(Round {
+            average,
+            corrections: samples
+                .iter()
+                .map(|&(participant, sample)| Correction {
+                    participant,
+                    correction: average.saturating_sub(sample.offset),
+                    outlier: !truechimers.contains(&participant),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(offset: i64) -> Sample {
+        Sample {
+            local_time: 0,
+            offset,
+            uncertainty: 10,
+        }
+    }
+
+    #[test]
+    fn test_berkeley_round() {
+        assert_eq!(Coordinator::new().round([]), Ok(Round::default()));
+        // Two participants 25 ahead and 15 ahead of the coordinator, and one far off:
+        let round = Coordinator::new()
+            .round([(2, sample(9_000)), (0, sample(25)), (1, sample(15))])
+            .unwrap();
+        assert_eq!(round.average, 13);
+        assert_eq!(
+            round.corrections,
+            [
+                Correction {
+                    participant: 0,
+                    correction: -12,
+                    outlier: false,
+                },
+                Correction {
+                    participant: 1,
+                    correction: -2,
+                    outlier: false,
+                },
+                Correction {
+                    participant: 2,
+                    correction: -8_987,
+                    outlier: true,
+                },
+            ]
+        );
+    }
+}
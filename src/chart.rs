@@ -0,0 +1,122 @@
+//! An ASCII chart of the source intervals and the chosen interval, for debugging a selection from a
+//! terminal or a test failure message:
+//!
+//! ```text
+//! 0 |   [=====]      | 11..=13 truechimer
+//! 1 |[=====]         | 10..=12 truechimer
+//! 2 |            [==]| 14..=15 falseticker
+//!   |   ^^^^         | 11..=12
+//! ```
+//!
+//! Unbounded sides are drawn as `<` and `>` at the edges of the chart.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::scale::{bounded_values, Scale};
+use crate::{Report, SourceBound, Verdict};
+
+fn write_range(row: &mut String, lower: i64, upper: i64) {
+    if lower != SourceBound::UNBOUNDED_LOWER {
+        let _ = write!(row, "{}", lower);
+    }
+    row.push_str("..");
+    if upper != SourceBound::UNBOUNDED_UPPER {
+        let _ = write!(row, "={}", upper);
+    }
+}
+
+impl Report<i64> {
+    /// Renders the sources, one row per source, and the chosen interval on `columns` columns.
+    pub fn chart(&self, columns: usize) -> String {
+        let columns = columns.max(2);
+        let Some(interval) = &self.interval else {
+            return String::from("no sources\n");
+        };
+
+        let scale = Scale::new(&bounded_values(self));
+        let column = |value| scale.column(value, columns);
+        let label_width = self
+            .verdicts
+            .iter()
+            .map(|verdict| verdict.source.checked_ilog10().unwrap_or(0) as usize + 1)
+            .max()
+            .unwrap_or(1);
+
+        let mut chart = String::new();
+        for verdict in &self.verdicts {
+            let (start, end) = (column(verdict.lower), column(verdict.upper));
+            let _ = write!(chart, "{:>1$} |", verdict.source, label_width);
+            for column in 0..columns {
+                chart.push(match column {
+                    _ if column < start || column > end => ' ',
+                    _ if column == start && verdict.lower == SourceBound::UNBOUNDED_LOWER => '<',
+                    _ if column == end && verdict.upper == SourceBound::UNBOUNDED_UPPER => '>',
+                    _ if start == end => '|',
+                    _ if column == start => '[',
+                    _ if column == end => ']',
+                    _ => '=',
+                });
+            }
+            chart.push_str("| ");
+            write_range(&mut chart, verdict.lower, verdict.upper);
+            chart.push_str(match verdict.verdict {
+                Verdict::Truechimer => " truechimer\n",
+                Verdict::Falseticker => " falseticker\n",
+            });
+        }
+
+        let (start, end) = (column(interval.lower_bound), column(interval.upper_bound));
+        let _ = write!(chart, "{:>1$} |", "", label_width);
+        for column in 0..columns {
+            chart.push(if (start..=end).contains(&column) {
+                '^'
+            } else {
+                ' '
+            });
+        }
+        chart.push_str("| ");
+        write_range(&mut chart, interval.lower_bound, interval.upper_bound);
+        chart.push('\n');
+        chart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_chart() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        source_bounds.extend(SourceBound::pair(2, 14, 15));
+        let report = Report::try_from_source_bounds(source_bounds).unwrap();
+        assert_eq!(
+            report.chart(16),
+            "\
+0 |   [=====]      | 11..=13 truechimer
+1 |[=====]         | 10..=12 truechimer
+2 |            [==]| 14..=15 falseticker
+  |   ^^^^         | 11..=12
+"
+        );
+
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, Some(4)));
+        source_bounds.extend(SourceBound::pair(1, 0, 6));
+        source_bounds.extend(SourceBound::pair_optional(10, Some(2), None));
+        let report = Report::try_from_source_bounds(source_bounds).unwrap();
+        assert_eq!(
+            report.chart(7),
+            concat!(
+                " 0 |<===]  | ..=4 truechimer\n",
+                " 1 |[=====]| 0..=6 truechimer\n",
+                "10 |  [===>| 2.. truechimer\n",
+                "   |  ^^^  | 2..=4\n",
+            )
+        );
+    }
+}
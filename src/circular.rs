@@ -0,0 +1,185 @@
+//! Agreement over circular domains, such as sequence numbers and hardware counters that wrap
+//! around modulo `2^32` or `2^64`. An interval whose lower bound lies above its upper bound wraps
+//! around: it runs from its lower bound up through the largest value, and on from zero to its
+//! upper bound. An interval cannot cover the whole circle, as `[x, x]` is the single value `x`.
+//!
+//! The chosen interval is the smallest one consistent with the most sources, as for Marzullo's
+//! algorithm, and may wrap around itself. It always starts at the lower bound of a source, so every
+//! lower bound is tried, which takes `O(m²)` time for `m` sources.
+
+use alloc::vec::Vec;
+
+use crate::verdict::source_intervals;
+use crate::{MarzulloError, SourceBound, Value};
+
+/// An unsigned integer type on a circle of its own size.
+pub trait Circular: Value + Copy {
+    /// The distance from `from` up to `to`, going around the circle if `to` is below `from`.
+    fn distance(from: Self, to: Self) -> Self;
+}
+
+macro_rules! impl_circular {
+    ($($ty:ty),*) => {
+        $(
+            impl Circular for $ty {
+                fn distance(from: Self, to: Self) -> Self {
+                    to.wrapping_sub(from)
+                }
+            }
+        )*
+    };
+}
+
+impl_circular!(u8, u16, u32, u64, u128, usize);
+
+/// An interval on a circle, which wraps around if its lower bound lies above its upper bound.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CircularInterval<T> {
+    lower_bound: T,
+    upper_bound: T,
+    sources_true: u8,
+    sources_false: u8,
+}
+
+impl<T: Circular> CircularInterval<T> {
+    pub fn lower_bound(&self) -> T {
+        self.lower_bound
+    }
+
+    pub fn upper_bound(&self) -> T {
+        self.upper_bound
+    }
+
+    /// The number of sources whose intervals contain the interval.
+    pub fn sources_true(&self) -> u8 {
+        self.sources_true
+    }
+
+    /// The number of sources whose intervals do not contain the interval.
+    pub fn sources_false(&self) -> u8 {
+        self.sources_false
+    }
+
+    /// The distance from the lower bound up to the upper bound, around the circle.
+    pub fn width(&self) -> T {
+        T::distance(self.lower_bound, self.upper_bound)
+    }
+
+    /// Whether the interval runs through the largest value and on from zero.
+    pub fn wraps(&self) -> bool {
+        self.lower_bound > self.upper_bound
+    }
+
+    /// Whether the interval contains `value`.
+    pub fn contains(&self, value: T) -> bool {
+        T::distance(self.lower_bound, value) <= self.width()
+    }
+}
+
+/// Returns the smallest interval on the circle consistent with the most sources, or `None` if there
+/// are no sources at all. Sources missing either bound are skipped. Of intervals of the same width
+/// and support, the one with the lowest lower bound is chosen. Fails with
+/// `MarzulloError::TooManySources` for 256 sources, which the interval cannot count.
+pub fn select_circular<T: Circular>(
+    source_bounds: &[SourceBound<T>],
+) -> Result<Option<CircularInterval<T>>, MarzulloError<T>> {
+    let sources: Vec<CircularInterval<T>> = source_intervals(source_bounds)
+        .into_iter()
+        .map(|(_, lower_bound, upper_bound)| CircularInterval {
+            lower_bound,
+            upper_bound,
+            sources_true: 0,
+            sources_false: 0,
+        })
+        .collect();
+    let count = u8::try_from(sources.len()).map_err(|_| MarzulloError::TooManySources {
+        sources: sources.len(),
+    })?;
+
+    let mut best: Option<CircularInterval<T>> = None;
+    for start in &sources {
+        // Every source that contains the start contains what follows it up to its upper bound, so
+        // that the interval ends at the nearest of their upper bounds:
+        let start = start.lower_bound;
+        let consistent: Vec<&CircularInterval<T>> = sources
+            .iter()
+            .filter(|source| source.contains(start))
+            .collect();
+        let width = consistent
+            .iter()
+            .map(|source| T::distance(start, source.upper_bound))
+            .min()
+            .expect("the source of the start contains it");
+        let upper_bound = consistent
+            .iter()
+            .map(|source| source.upper_bound)
+            .find(|&upper| T::distance(start, upper) == width)
+            .expect("the nearest upper bound is that of a source");
+        let candidate = CircularInterval {
+            lower_bound: start,
+            upper_bound,
+            sources_true: consistent.len() as u8,
+            sources_false: count - consistent.len() as u8,
+        };
+        let better = match &best {
+            None => true,
+            Some(best) => (best.sources_true, candidate.width(), candidate.lower_bound)
+                .cmp(&(candidate.sources_true, best.width(), best.lower_bound))
+                .is_lt(),
+        };
+        if better {
+            best = Some(candidate);
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_select_circular() {
+        assert_eq!(select_circular::<u32>(&[]), Ok(None));
+        // Three sources around the wrap point of a 32-bit counter, and one elsewhere:
+        let source_bounds: Vec<SourceBound<u32>> = bounds(&[
+            (0, u32::MAX - 10, 5),
+            (1, u32::MAX - 3, 20),
+            (2, u32::MAX - 20, 2),
+            (3, 1_000, 2_000),
+        ]);
+        let interval = select_circular(&source_bounds).unwrap().unwrap();
+        assert_eq!(
+            (interval.lower_bound(), interval.upper_bound()),
+            (u32::MAX - 3, 2)
+        );
+        assert!(interval.wraps());
+        assert_eq!(interval.width(), 6);
+        assert_eq!((interval.sources_true(), interval.sources_false()), (3, 1));
+        assert!(interval.contains(0) && !interval.contains(3));
+
+        // Without wrapping, it is the interval of Marzullo's algorithm:
+        let source_bounds: Vec<SourceBound<u64>> = bounds(&[(0, 8, 12), (1, 11, 13), (2, 14, 15)]);
+        let interval = select_circular(&source_bounds).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (11, 12));
+
+        // Up to 255 sources, as many as the interval can count:
+        let source_bounds: Vec<SourceBound<u8>> = (0..=u8::MAX)
+            .flat_map(|source| SourceBound::pair(source, 0, 1))
+            .collect();
+        assert_eq!(
+            select_circular(&source_bounds[..510])
+                .unwrap()
+                .unwrap()
+                .sources_true(),
+            255
+        );
+        assert_eq!(
+            select_circular(&source_bounds),
+            Err(MarzulloError::TooManySources { sources: 256 })
+        );
+    }
+}
@@ -0,0 +1,535 @@
+//! A cluster clock, ported from TigerBeetle's `clock.zig`. Every replica of a cluster pings the
+//! others, and learns the offset of their realtime clocks from the pongs. The clock accumulates the
+//! best sample of every source in a window, and once a majority of the sources (including itself)
+//! agree within a tolerance, the window becomes the active epoch and a new window starts learning.
+//!
+//! The offsets are relative to the realtime clock at the start of the window, advanced by the
+//! monotonic clock, so that jumps of the realtime clock during a window do not distort them.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Interval, SourceBound};
+
+/// The maximum offset between the realtime clock and the cluster time, in nanoseconds, beyond which
+/// the clock is considered faulty.
+pub const OFFSET_TOLERANCE_MAX: u64 = 10_000_000_000;
+/// The maximum age of an epoch. Clocks drift, so an epoch is only trusted for so long.
+pub const EPOCH_MAX: u64 = 60_000_000_000;
+/// The minimum duration of a window, so that it has time to collect accurate samples.
+pub const WINDOW_MIN: u64 = 2_000_000_000;
+/// The maximum duration of a window, after which it is discarded for lack of agreement.
+pub const WINDOW_MAX: u64 = 20_000_000_000;
+/// The change of the realtime clock against the monotonic clock beyond which the realtime clock is
+/// considered stepped. Slewing, at 500ppm at most, moves the clocks apart far less between checks a
+/// few seconds apart.
+pub const STEP_THRESHOLD: u64 = 10_000_000;
+
+/// The monotonic and realtime clocks of the local machine, in nanoseconds.
+pub trait Time {
+    /// Returns a time that never goes backwards, from an arbitrary origin.
+    fn monotonic(&self) -> u64;
+
+    /// Returns the time since the Unix epoch, which may jump.
+    fn realtime(&self) -> i64;
+}
+
+/// The clocks of the operating system.
+#[cfg(feature = "std")]
+pub struct StdTime {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for StdTime {
+    fn default() -> StdTime {
+        StdTime {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Time for StdTime {
+    fn monotonic(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+
+    fn realtime(&self) -> i64 {
+        system_realtime()
+    }
+}
+
+#[cfg(feature = "std")]
+fn system_realtime() -> i64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i64,
+        Err(error) => -(error.duration().as_nanos() as i64),
+    }
+}
+
+/// The monotonic clock of `quanta`, which reads the TSC where it can: a few nanoseconds per reading,
+/// at the resolution of the processor.
+#[cfg(feature = "quanta")]
+#[derive(Debug, Clone)]
+pub struct QuantaTime {
+    clock: quanta::Clock,
+    start: quanta::Instant,
+}
+
+#[cfg(feature = "quanta")]
+impl Default for QuantaTime {
+    fn default() -> QuantaTime {
+        QuantaTime::new(quanta::Clock::new())
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl QuantaTime {
+    /// Uses `clock`, e.g. a mocked clock from `quanta::Clock::mock`.
+    pub fn new(clock: quanta::Clock) -> QuantaTime {
+        QuantaTime {
+            start: clock.now(),
+            clock,
+        }
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl Time for QuantaTime {
+    fn monotonic(&self) -> u64 {
+        (self.clock.now() - self.start).as_nanos() as u64
+    }
+
+    fn realtime(&self) -> i64 {
+        system_realtime()
+    }
+}
+
+/// The coarse monotonic clock of `coarsetime`, which is cheaper to read than `Instant` but only
+/// advances every millisecond or so, depending on the platform.
+#[cfg(feature = "coarsetime")]
+#[derive(Debug, Clone, Copy)]
+pub struct CoarseTime {
+    start: coarsetime::Instant,
+}
+
+#[cfg(feature = "coarsetime")]
+impl Default for CoarseTime {
+    fn default() -> CoarseTime {
+        CoarseTime {
+            start: coarsetime::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "coarsetime")]
+impl Time for CoarseTime {
+    fn monotonic(&self) -> u64 {
+        coarsetime::Instant::now()
+            .duration_since(self.start)
+            .as_nanos()
+    }
+
+    fn realtime(&self) -> i64 {
+        system_realtime()
+    }
+}
+
+/// Detects steps of the realtime clock against the monotonic clock, such as a manual clock set or
+/// the migration of a virtual machine, after which samples taken against the realtime clock before
+/// the step are no longer comparable with those after it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct StepDetector {
+    threshold: u64,
+    /// The realtime minus the monotonic time, at the last check.
+    difference: Option<i128>,
+}
+
+impl Default for StepDetector {
+    fn default() -> StepDetector {
+        StepDetector::new(STEP_THRESHOLD)
+    }
+}
+
+impl StepDetector {
+    pub fn new(threshold: u64) -> StepDetector {
+        StepDetector {
+            threshold,
+            difference: None,
+        }
+    }
+
+    /// Checks the clocks, and returns by how much the realtime clock stepped since the last check,
+    /// if it stepped by more than the threshold.
+    pub fn check<M: Time + ?Sized>(&mut self, time: &M) -> Option<i64> {
+        let difference = time.realtime() as i128 - time.monotonic() as i128;
+        let step = difference - self.difference.replace(difference)?;
+        if step.unsigned_abs() > self.threshold as u128 {
+            Some(step.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Measurement {
+    /// The realtime of the source minus the realtime of the window, when the pong was received.
+    offset: i64,
+    /// Half the round trip, the maximum error of `offset` in either direction.
+    one_way_delay: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Epoch {
+    /// The best measurement of every source, by index.
+    sources: Vec<Option<Measurement>>,
+    /// The monotonic and realtime clocks at the start of the epoch.
+    monotonic: u64,
+    realtime: i64,
+    synchronized: Option<Interval>,
+    /// Whether any measurement was taken from another source.
+    learned: bool,
+}
+
+impl Epoch {
+    fn new<M: Time>(time: &M, sources: usize, source: u8) -> Epoch {
+        let mut epoch = Epoch {
+            sources: vec![None; sources],
+            monotonic: 0,
+            realtime: 0,
+            synchronized: None,
+            learned: false,
+        };
+        epoch.reset(time, source);
+        epoch
+    }
+
+    fn reset<M: Time>(&mut self, time: &M, source: u8) {
+        self.sources.fill(None);
+        // The local clock agrees with itself:
+        self.sources[source as usize] = Some(Measurement {
+            offset: 0,
+            one_way_delay: 0,
+        });
+        self.monotonic = time.monotonic();
+        self.realtime = time.realtime();
+        self.synchronized = None;
+        self.learned = false;
+    }
+
+    fn elapsed<M: Time>(&self, time: &M) -> u64 {
+        time.monotonic() - self.monotonic
+    }
+
+    fn sources_sampled(&self) -> usize {
+        self.sources
+            .iter()
+            .filter(|sample| sample.is_some())
+            .count()
+    }
+
+    fn source_bounds(&self, tolerance: u64) -> Vec<SourceBound> {
+        let mut source_bounds = Vec::with_capacity(self.sources.len() * 2);
+        for (source, sample) in self.sources.iter().enumerate() {
+            if let Some(sample) = sample {
+                let error = sample
+                    .one_way_delay
+                    .saturating_add(tolerance)
+                    .min(i64::MAX as u64);
+                source_bounds.extend(SourceBound::pair(
+                    source as u8,
+                    sample.offset.saturating_sub(error as i64),
+                    sample.offset.saturating_add(error as i64),
+                ));
+            }
+        }
+        source_bounds
+    }
+}
+
+/// Why a window was discarded without synchronizing.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SynchronizationFailure {
+    /// No more than half of the sources were sampled.
+    Partitioned,
+    /// Enough sources were sampled, but no majority of them agreed.
+    NoAgreement,
+    /// The realtime clock stepped by this many nanoseconds, so that the window started over.
+    LocalStep { step: i64 },
+}
+
+pub struct ClockTracker<M> {
+    time: M,
+    /// The index of the local clock among the sources.
+    source: u8,
+    /// The epoch of the last synchronization, whose interval is in use.
+    epoch: Epoch,
+    /// The epoch that is learning from new samples, until it synchronizes.
+    window: Epoch,
+    steps: StepDetector,
+}
+
+impl<M: Time> ClockTracker<M> {
+    /// Creates the clock of the source `source` of a cluster of `sources` clocks.
+    ///
+    /// # Panics
+    ///
+    /// If `sources` is more than 256, or `source` is not less than `sources`.
+    pub fn new(time: M, sources: usize, source: u8) -> ClockTracker<M> {
+        assert!(sources <= 256, "at most 256 sources");
+        assert!((source as usize) < sources, "source out of range");
+        let epoch = Epoch::new(&time, sources, source);
+        let window = Epoch::new(&time, sources, source);
+        let mut steps = StepDetector::default();
+        steps.check(&time);
+        ClockTracker {
+            time,
+            source,
+            epoch,
+            window,
+            steps,
+        }
+    }
+
+    pub fn time(&self) -> &M {
+        &self.time
+    }
+
+    /// Learns from a pong of `source` with its realtime clock, to a ping sent and received at the
+    /// given monotonic times. Pings sent before the current window started are ignored, and only
+    /// the sample with the least delay is kept for every source.
+    pub fn learn(
+        &mut self,
+        source: u8,
+        monotonic_sent: u64,
+        realtime_remote: i64,
+        monotonic_received: u64,
+    ) {
+        if source == self.source || source as usize >= self.window.sources.len() {
+            return;
+        }
+        if monotonic_sent < self.window.monotonic || monotonic_received < monotonic_sent {
+            return;
+        }
+        // The pong was sent some time during the round trip, so assume it was halfway:
+        let one_way_delay = (monotonic_received - monotonic_sent) / 2;
+        let realtime_received = self
+            .window
+            .realtime
+            .saturating_add((monotonic_received - self.window.monotonic) as i64);
+        let offset = realtime_remote
+            .saturating_add(one_way_delay as i64)
+            .saturating_sub(realtime_received);
+        let sample = &mut self.window.sources[source as usize];
+        if let Some(best) = sample {
+            if best.one_way_delay <= one_way_delay {
+                return;
+            }
+        }
+        *sample = Some(Measurement {
+            offset,
+            one_way_delay,
+        });
+        self.window.learned = true;
+    }
+
+    /// Synchronizes the window if it can, expires windows and epochs that are too old, and returns
+    /// why a window was discarded, if it was. If the realtime clock stepped, the window starts over,
+    /// so that samples from before and after the step are not mixed.
+    pub fn tick(&mut self) -> Option<SynchronizationFailure> {
+        if let Some(step) = self.steps.check(&self.time) {
+            self.window.reset(&self.time, self.source);
+            return Some(SynchronizationFailure::LocalStep { step });
+        }
+        let failure = self.synchronize();
+        if self.epoch.elapsed(&self.time) >= EPOCH_MAX {
+            self.epoch.reset(&self.time, self.source);
+        }
+        failure
+    }
+
+    fn synchronize(&mut self) -> Option<SynchronizationFailure> {
+        let elapsed = self.window.elapsed(&self.time);
+        if elapsed < WINDOW_MIN {
+            return None;
+        }
+        if elapsed >= WINDOW_MAX {
+            let failure = if self.window.sources_sampled() <= self.window.sources.len() / 2 {
+                SynchronizationFailure::Partitioned
+            } else {
+                SynchronizationFailure::NoAgreement
+            };
+            self.window.reset(&self.time, self.source);
+            return Some(failure);
+        }
+        if !self.window.learned {
+            return None;
+        }
+
+        // Starting with the largest tolerance, reduce the tolerance for as long as a majority
+        // agrees: a tighter interval is preferred, even if fewer sources agree on it.
+        let mut tolerance = OFFSET_TOLERANCE_MAX;
+        loop {
+            let interval = Interval::try_from_source_bounds(self.window.source_bounds(tolerance))
+                .ok()
+                .flatten();
+            match interval {
+                Some(interval)
+                    if interval.sources_true() as usize > self.window.sources.len() / 2 =>
+                {
+                    self.window.synchronized = Some(interval);
+                }
+                _ => break,
+            }
+            if tolerance == 0 {
+                break;
+            }
+            tolerance /= 2;
+        }
+        // Otherwise, wait for more accurate samples, or for the window to expire:
+        if self.window.synchronized.is_some() {
+            core::mem::swap(&mut self.epoch, &mut self.window);
+            self.window.reset(&self.time, self.source);
+        }
+        None
+    }
+
+    /// Returns the offset window of the active epoch, relative to the realtime clock at the start of
+    /// the epoch advanced by the monotonic clock.
+    pub fn synchronized(&self) -> Option<&Interval> {
+        self.epoch.synchronized.as_ref()
+    }
+
+    /// Returns the realtime clock, clamped to the cluster time of the active epoch, or `None` if the
+    /// clock is not synchronized.
+    pub fn realtime_synchronized(&self) -> Option<i64> {
+        let interval = self.epoch.synchronized.as_ref()?;
+        let realtime = self
+            .epoch
+            .realtime
+            .saturating_add(self.epoch.elapsed(&self.time) as i64);
+        Some(self.time.realtime().clamp(
+            realtime.saturating_add(interval.lower_bound()),
+            realtime.saturating_add(interval.upper_bound()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    const REALTIME: i64 = 1_700_000_000_000_000_000;
+    const MILLISECOND: u64 = 1_000_000;
+
+    #[derive(Default)]
+    struct TestTime {
+        monotonic: Cell<u64>,
+        step: Cell<i64>,
+    }
+
+    impl Time for TestTime {
+        fn monotonic(&self) -> u64 {
+            self.monotonic.get()
+        }
+
+        fn realtime(&self) -> i64 {
+            REALTIME + self.monotonic.get() as i64 + self.step.get()
+        }
+    }
+
+    #[cfg(all(feature = "quanta", feature = "coarsetime"))]
+    #[test]
+    fn test_monotonic_clocks() {
+        let (clock, mock) = quanta::Clock::mock();
+        let time = QuantaTime::new(clock);
+        mock.increment(5 * MILLISECOND);
+        assert_eq!(time.monotonic(), 5 * MILLISECOND);
+        assert!(time.realtime() > REALTIME);
+
+        let time = CoarseTime::default();
+        let start = time.monotonic();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(time.monotonic() - start >= 10 * MILLISECOND);
+    }
+
+    #[test]
+    fn test_clock_synchronizes() {
+        let mut clock = ClockTracker::new(TestTime::default(), 3, 0);
+        clock.time().monotonic.set(200 * MILLISECOND);
+        // 10ms ahead, with a one-way delay of 5ms:
+        clock.learn(
+            1,
+            190 * MILLISECOND,
+            REALTIME + 205 * MILLISECOND as i64,
+            200 * MILLISECOND,
+        );
+        // A falseticker, 200ms ahead:
+        clock.learn(
+            2,
+            190 * MILLISECOND,
+            REALTIME + 395 * MILLISECOND as i64,
+            200 * MILLISECOND,
+        );
+        assert_eq!(clock.tick(), None);
+        assert_eq!(clock.synchronized(), None);
+
+        clock.time().monotonic.set(WINDOW_MIN);
+        assert_eq!(clock.tick(), None);
+        let interval = clock.synchronized().unwrap();
+        assert_eq!(interval.sources_true(), 2);
+        // With the tolerance halved 11 times, the last time the local clock and source 1 agree:
+        assert_eq!(
+            (interval.lower_bound(), interval.upper_bound()),
+            (117_188, 4_882_812)
+        );
+        assert_eq!(
+            clock.realtime_synchronized(),
+            Some(REALTIME + WINDOW_MIN as i64 + 117_188)
+        );
+
+        // The window that started with the epoch learned nothing since:
+        clock.time().monotonic.set(EPOCH_MAX);
+        assert_eq!(clock.tick(), Some(SynchronizationFailure::Partitioned));
+        assert_eq!(clock.synchronized(), None);
+    }
+
+    #[test]
+    fn test_local_step() {
+        let mut clock = ClockTracker::new(TestTime::default(), 2, 0);
+        clock.time().monotonic.set(200 * MILLISECOND);
+        clock.learn(
+            1,
+            190 * MILLISECOND,
+            REALTIME + 200 * MILLISECOND as i64,
+            200 * MILLISECOND,
+        );
+        // Slewing is not a step:
+        clock.time().step.set(STEP_THRESHOLD as i64);
+        assert_eq!(clock.tick(), None);
+        clock.time().step.set(1_000 * MILLISECOND as i64);
+        assert_eq!(
+            clock.tick(),
+            Some(SynchronizationFailure::LocalStep {
+                step: 1_000 * MILLISECOND as i64 - STEP_THRESHOLD as i64
+            })
+        );
+        // The sample from before the step was discarded with the window:
+        clock.time().monotonic.set(200 * MILLISECOND + WINDOW_MIN);
+        assert_eq!(clock.tick(), None);
+        assert_eq!(clock.synchronized(), None);
+    }
+
+    #[test]
+    fn test_clock_partitioned() {
+        let mut clock = ClockTracker::new(TestTime::default(), 3, 0);
+        clock.time().monotonic.set(WINDOW_MAX);
+        assert_eq!(clock.tick(), Some(SynchronizationFailure::Partitioned));
+        assert_eq!(clock.realtime_synchronized(), None);
+    }
+}
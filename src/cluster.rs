@@ -0,0 +1,156 @@
+//! The cluster algorithm of NTP (RFC 5905, section 11.2.2), which prunes the truechimers of a
+//! selection down to those whose offsets agree most closely. The selection jitter of a survivor is
+//! the root mean square of the differences between its offset and those of the other survivors.
+//! The survivor with the largest selection jitter is discarded, one at a time, until that jitter
+//! is no larger than the smallest jitter of any survivor's own samples (pruning further would not
+//! improve the estimate), or only `min_survivors` are left.
+
+use alloc::vec::Vec;
+
+use crate::source::{FilteredSample, Root};
+
+/// The number of survivors below which none are discarded, by default.
+pub const MIN_SURVIVORS: usize = 3;
+
+/// A source that survived the selection.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Candidate {
+    pub source: u8,
+    pub offset: i64,
+    /// The jitter of the source's own samples, as of its clock filter.
+    pub jitter: i64,
+    /// The synchronization distance: the maximum error of `offset` from the reference clock, by
+    /// which survivors are ranked, the smallest first.
+    pub distance: i64,
+    pub root: Root,
+    /// The signed distance of the source's interval from the chosen interval, as
+    /// `SourceVerdict::distance`, or `None` before an interval was chosen.
+    pub interval_distance: Option<i64>,
+}
+
+impl Candidate {
+    /// A candidate from the filtered sample of a source, at the synchronization distance of the
+    /// sample's uncertainty plus that of `root`.
+    pub fn from_filtered(source: u8, filtered: &FilteredSample, root: Root) -> Candidate {
+        Candidate {
+            source,
+            offset: filtered.sample.offset,
+            jitter: filtered.jitter,
+            distance: filtered.sample.uncertainty.saturating_add(root.distance()),
+            root,
+            interval_distance: None,
+        }
+    }
+}
+
+/// A survivor discarded by the cluster algorithm.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Discard {
+    pub candidate: Candidate,
+    /// The selection jitter of the candidate when it was discarded.
+    pub selection_jitter: i64,
+}
+
+/// The outcome of the cluster algorithm.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clustering {
+    /// The survivors, by their distance, the smallest first.
+    pub survivors: Vec<Candidate>,
+    /// The discarded candidates, in the order they were discarded.
+    pub discarded: Vec<Discard>,
+}
+
+/// Returns the selection jitter of `survivors[index]`.
+fn selection_jitter(survivors: &[Candidate], index: usize) -> i64 {
+    if survivors.len() < 2 {
+        return 0;
+    }
+    let offset = survivors[index].offset;
+    let squares: u128 = survivors
+        .iter()
+        .map(|other| (other.offset.abs_diff(offset) as u128).pow(2))
+        .sum();
+    i64::try_from((squares / (survivors.len() - 1) as u128).isqrt()).unwrap_or(i64::MAX)
+}
+
+/// Prunes the candidates, which should be the truechimers of a selection, keeping at least
+/// `min_survivors` of them.
+pub fn cluster(mut candidates: Vec<Candidate>, min_survivors: usize) -> Clustering {
+    candidates.sort_by_key(|candidate| (candidate.distance, candidate.source));
+    let mut discarded = Vec::new();
+    while candidates.len() > min_survivors.max(1) {
+        let (index, jitter) = (0..candidates.len())
+            .map(|index| (index, selection_jitter(&candidates, index)))
+            // The last of the largest, so that among equals the more distant one is discarded:
+            .max_by_key(|&(_, jitter)| jitter)
+            .expect("there are candidates");
+        let min_jitter = candidates
+            .iter()
+            .map(|candidate| candidate.jitter)
+            .min()
+            .expect("there are candidates");
+        if jitter <= min_jitter {
+            break;
+        }
+        discarded.push(Discard {
+            candidate: candidates.remove(index),
+            selection_jitter: jitter,
+        });
+    }
+    Clustering {
+        survivors: candidates,
+        discarded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(source: u8, offset: i64, jitter: i64, distance: i64) -> Candidate {
+        Candidate {
+            source,
+            offset,
+            jitter,
+            distance,
+            root: Root::default(),
+            interval_distance: None,
+        }
+    }
+
+    #[test]
+    fn test_cluster() {
+        let candidates = Vec::from([
+            candidate(0, 1_000, 200, 400),
+            candidate(1, 1_100, 200, 300),
+            candidate(2, 900, 200, 200),
+            candidate(3, 5_000, 200, 100),
+            candidate(4, -3_500, 200, 500),
+        ]);
+        let clustering = cluster(candidates.clone(), MIN_SURVIVORS);
+        let sources = |survivors: &[Candidate]| -> Vec<u8> {
+            survivors.iter().map(|survivor| survivor.source).collect()
+        };
+        assert_eq!(sources(&clustering.survivors), [2, 1, 0]);
+        let discarded: Vec<_> = clustering
+            .discarded
+            .iter()
+            .map(|discard| discard.candidate.source)
+            .collect();
+        assert_eq!(discarded, [4, 3]);
+        assert!(
+            clustering.discarded[0].selection_jitter > clustering.discarded[1].selection_jitter
+        );
+
+        // Survivors that agree within their own jitter are all kept:
+        let clustering = cluster(candidates[..3].to_vec(), 1);
+        assert!(clustering.discarded.is_empty());
+        let clustering = cluster(candidates, 4);
+        assert_eq!(sources(&clustering.survivors), [3, 2, 1, 0]);
+    }
+}
@@ -0,0 +1,91 @@
+//! The combine algorithm of NTP (RFC 5905, section 11.2.3), which averages the offsets of the
+//! survivors of the cluster algorithm into a single estimate, weighting every survivor by the
+//! inverse of its distance: a survivor half as far off counts twice as much.
+
+use crate::cluster::Candidate;
+
+/// The weight of a survivor at a distance of 1ns. Weights are integers, so that distances beyond
+/// this still weigh 1.
+const WEIGHT_SCALE: u128 = 1 << 40;
+
+/// The offset by which to adjust the local clock, "adjust by `offset` ± `error`".
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Estimate {
+    /// The weighted mean of the offsets of the survivors.
+    pub offset: i64,
+    /// The weighted root mean square of the differences between the offsets of the survivors
+    /// and `offset`.
+    pub jitter: i64,
+    /// The smallest distance of any survivor.
+    pub distance: i64,
+}
+
+impl Estimate {
+    /// Returns the maximum error of `offset` in either direction: the distance of the closest
+    /// survivor, plus the jitter between the survivors.
+    pub fn error(&self) -> i64 {
+        self.distance.saturating_add(self.jitter)
+    }
+}
+
+fn weight(candidate: &Candidate) -> u128 {
+    (WEIGHT_SCALE / candidate.distance.max(1) as u128).max(1)
+}
+
+/// Combines the survivors into an estimate, or returns `None` if there are none.
+pub fn combine(survivors: &[Candidate]) -> Option<Estimate> {
+    let distance = survivors.iter().map(|survivor| survivor.distance).min()?;
+    let total: u128 = survivors.iter().map(weight).sum();
+    let weighted: i128 = survivors
+        .iter()
+        .map(|survivor| survivor.offset as i128 * weight(survivor) as i128)
+        .sum();
+    // Rounded to the nearest nanosecond:
+    let offset = (weighted + total as i128 / 2).div_euclid(total as i128) as i64;
+    let squares = survivors.iter().fold(0_u128, |squares, survivor| {
+        let square = (survivor.offset.abs_diff(offset) as u128).pow(2);
+        squares.saturating_add(square.saturating_mul(weight(survivor)) / total)
+    });
+    Some(Estimate {
+        offset,
+        jitter: i64::try_from(squares.isqrt()).unwrap_or(i64::MAX),
+        distance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Root;
+
+    fn candidate(source: u8, offset: i64, distance: i64) -> Candidate {
+        Candidate {
+            source,
+            offset,
+            jitter: 0,
+            distance,
+            root: Root::default(),
+            interval_distance: None,
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        assert_eq!(combine(&[]), None);
+        let estimate = combine(&[candidate(0, 1_000, 100), candidate(1, 4_000, 200)]).unwrap();
+        assert_eq!(estimate.offset, 2_000);
+        // sqrt((2 * 1000² + 2000²) / 3):
+        assert_eq!(estimate.jitter, 1_414);
+        assert_eq!(estimate.error(), 1_514);
+        assert_eq!(
+            combine(&[candidate(0, -50, 0)]).unwrap(),
+            Estimate {
+                offset: -50,
+                jitter: 0,
+                distance: 0,
+            }
+        );
+    }
+}
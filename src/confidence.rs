@@ -0,0 +1,192 @@
+//! Fusion of sources that bound the value at several confidence levels, e.g. `[9, 11]` at 68% and
+//! `[8, 12]` at 95%, rather than by a single interval they are certain of.
+//!
+//! The sources are taken as a mixture: the aggregate confidence of an interval is the mean, over
+//! the sources, of the highest confidence level of a source whose interval it contains, or zero
+//! if it contains none of them. It grows as the interval widens, and the fused interval is the
+//! tightest one that reaches a requested confidence. A requested confidence below 1 leaves room
+//! for outliers, as their intervals need not be covered at all.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The interval of a source at one confidence level.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level {
+    pub lower: i64,
+    pub upper: i64,
+    /// The probability that the value lies within the interval, in `(0, 1]`.
+    pub confidence: f64,
+}
+
+/// The nested intervals of one source, each containing those of lower confidence.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfidenceSource {
+    pub source: u8,
+    pub levels: Vec<Level>,
+}
+
+/// An interval, and its aggregate confidence.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfidenceInterval {
+    pub lower: i64,
+    pub upper: i64,
+    pub confidence: f64,
+}
+
+/// Why the levels of a source are invalid.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConfidenceError {
+    /// A confidence level is not within `(0, 1]`, or an interval is empty.
+    InvalidLevel { source: u8, level: Level },
+    /// An interval does not contain that of a lower confidence level.
+    NotNested { source: u8 },
+}
+
+impl core::fmt::Display for ConfidenceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ConfidenceError::InvalidLevel { source, level } => write!(
+                f,
+                "source {} has an invalid level: [{}, {}] at {}",
+                source, level.lower, level.upper, level.confidence
+            ),
+            ConfidenceError::NotNested { source } => {
+                write!(f, "the intervals of source {} are not nested", source)
+            }
+        }
+    }
+}
+
+impl core::error::Error for ConfidenceError {}
+
+/// Checks that the levels of a source are valid and nested.
+fn validate(source: &ConfidenceSource) -> Result<(), ConfidenceError> {
+    let mut levels: Vec<Level> = source.levels.clone();
+    for &level in &levels {
+        if !(level.confidence > 0.0 && level.confidence <= 1.0) || level.lower > level.upper {
+            return Err(ConfidenceError::InvalidLevel {
+                source: source.source,
+                level,
+            });
+        }
+    }
+    levels.sort_by(|a, b| a.confidence.total_cmp(&b.confidence));
+    for pair in levels.windows(2) {
+        if pair[1].lower > pair[0].lower || pair[1].upper < pair[0].upper {
+            return Err(ConfidenceError::NotNested {
+                source: source.source,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns the tightest interval whose aggregate confidence reaches `confidence`, or `None` if no
+/// interval does, e.g. if there are no sources. Of intervals of the same width, the one with the
+/// higher confidence is chosen, and then the lower one.
+pub fn tightest(
+    sources: &[ConfidenceSource],
+    confidence: f64,
+) -> Result<Option<ConfidenceInterval>, ConfidenceError> {
+    for source in sources {
+        validate(source)?;
+    }
+    // Every level by its upper bound, with the index of its source:
+    let mut levels: Vec<(usize, Level)> = sources
+        .iter()
+        .enumerate()
+        .flat_map(|(index, source)| source.levels.iter().map(move |&level| (index, level)))
+        .collect();
+    levels.sort_by_key(|&(_, level)| level.upper);
+    let mut lowers: Vec<i64> = levels.iter().map(|&(_, level)| level.lower).collect();
+    lowers.sort_unstable();
+    lowers.dedup();
+
+    let count = sources.len() as f64;
+    let mut best: Option<ConfidenceInterval> = None;
+    for &lower in &lowers {
+        // Widen the upper bound over the levels above `lower`, until the confidence is reached:
+        let mut highest = vec![0.0; sources.len()];
+        let mut sum = 0.0;
+        for &(index, level) in &levels {
+            if level.lower < lower || level.confidence <= highest[index] {
+                continue;
+            }
+            sum += level.confidence - highest[index];
+            highest[index] = level.confidence;
+            if sum / count < confidence {
+                continue;
+            }
+            let candidate = ConfidenceInterval {
+                lower,
+                upper: level.upper,
+                confidence: sum / count,
+            };
+            let width = |interval: &ConfidenceInterval| interval.upper.abs_diff(interval.lower);
+            let better = match &best {
+                None => true,
+                Some(best) => {
+                    width(&candidate) < width(best)
+                        || (width(&candidate) == width(best)
+                            && candidate.confidence > best.confidence)
+                }
+            };
+            if better {
+                best = Some(candidate);
+            }
+            break;
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(source: u8, levels: &[(i64, i64, f64)]) -> ConfidenceSource {
+        ConfidenceSource {
+            source,
+            levels: levels
+                .iter()
+                .map(|&(lower, upper, confidence)| Level {
+                    lower,
+                    upper,
+                    confidence,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_tightest() {
+        assert_eq!(tightest(&[], 0.5), Ok(None));
+        let sources = [
+            source(0, &[(9, 11, 0.68), (8, 12, 0.95)]),
+            source(1, &[(10, 12, 0.68), (9, 14, 0.95)]),
+            source(2, &[(10, 11, 0.68), (7, 13, 0.95)]),
+            source(3, &[(50, 52, 0.68), (45, 60, 0.95)]),
+        ];
+        // All three agreeing sources at 68%, while the outlier is never covered:
+        let interval = tightest(&sources, 0.5).unwrap().unwrap();
+        assert_eq!((interval.lower, interval.upper), (9, 12));
+        assert!((interval.confidence - 0.51).abs() < 1e-9);
+        // At 95% for all three, the interval spans their widest:
+        let interval = tightest(&sources, 0.7).unwrap().unwrap();
+        assert_eq!((interval.lower, interval.upper), (7, 14));
+        // Beyond what the agreeing sources can reach, the outlier must be covered too:
+        let interval = tightest(&sources, 0.8).unwrap().unwrap();
+        assert_eq!((interval.lower, interval.upper), (8, 52));
+        assert_eq!(tightest(&sources, 0.99), Ok(None));
+
+        assert_eq!(
+            tightest(&[source(0, &[(9, 11, 0.95), (8, 12, 0.68)])], 0.5),
+            Err(ConfidenceError::NotNested { source: 0 })
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Reading source bounds from CSV, for offline analysis of exported clock data.
+//!
+//! Every record is either an interval, `source,lower,upper`, or a single bound,
+//! `source,value,bound_type`, where the bound type is `lower` or `upper` (case-insensitive). Both
+//! kinds of record can be mixed, and a header row starting with `source` is skipped. Fields are
+//! trimmed, and lines starting with `#` are comments.
+
+use std::io;
+use std::str::FromStr;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::{validate_source_bounds, BoundType, SourceBound, ValidationIssue, Value};
+
+#[derive(Debug)]
+pub enum CsvError<T = i64> {
+    /// The input is not well-formed CSV, or could not be read.
+    Csv(::csv::Error),
+    /// A record does not have three fields.
+    FieldCount { line: u64, count: usize },
+    /// A field could not be parsed.
+    InvalidField {
+        line: u64,
+        field: &'static str,
+        text: String,
+    },
+    /// The bounds were parsed but do not form a valid bound set. Every issue carries the line of
+    /// the record it points at.
+    Invalid(Vec<(u64, ValidationIssue<T>)>),
+}
+
+impl<T: Value> core::fmt::Display for CsvError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            CsvError::Csv(error) => write!(f, "{}", error),
+            CsvError::FieldCount { line, count } => {
+                write!(f, "line {}: expected 3 fields, got {}", line, count)
+            }
+            CsvError::InvalidField { line, field, text } => {
+                write!(f, "line {}: invalid {} {:?}", line, field, text)
+            }
+            CsvError::Invalid(issues) => {
+                for (i, (line, issue)) in issues.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "line {}: {}", line, issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T: Value> std::error::Error for CsvError<T> {}
+
+impl<T> From<::csv::Error> for CsvError<T> {
+    fn from(error: ::csv::Error) -> CsvError<T> {
+        CsvError::Csv(error)
+    }
+}
+
+fn parse_field<F: FromStr, T>(
+    line: u64,
+    field: &'static str,
+    text: &str,
+) -> Result<F, CsvError<T>> {
+    text.parse().map_err(|_| CsvError::InvalidField {
+        line,
+        field,
+        text: text.to_string(),
+    })
+}
+
+fn parse_bound_type(text: &str) -> Option<BoundType> {
+    if text.eq_ignore_ascii_case("lower") {
+        Some(BoundType::Lower)
+    } else if text.eq_ignore_ascii_case("upper") {
+        Some(BoundType::Upper)
+    } else {
+        None
+    }
+}
+
+/// Reads a bound set from CSV and validates it with `validate_source_bounds`.
+pub fn read_source_bounds<T, R>(reader: R) -> Result<Vec<SourceBound<T>>, CsvError<T>>
+where
+    T: Value + FromStr,
+    R: io::Read,
+{
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(::csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(reader);
+
+    // The bounds, and the line each bound was read from:
+    let mut source_bounds = Vec::new();
+    let mut lines = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        if record.len() != 3 {
+            return Err(CsvError::FieldCount {
+                line,
+                count: record.len(),
+            });
+        }
+        if i == 0 && record[0].eq_ignore_ascii_case("source") {
+            continue;
+        }
+
+        let source: u8 = parse_field(line, "source", &record[0])?;
+        match parse_bound_type(&record[2]) {
+            Some(bound_type) => {
+                let value = parse_field(line, "value", &record[1])?;
+                source_bounds.push(SourceBound::new(source, value, bound_type));
+                lines.push(line);
+            }
+            None => {
+                let lower = parse_field(line, "lower bound", &record[1])?;
+                let upper = parse_field(line, "upper bound", &record[2])?;
+                source_bounds.extend(SourceBound::pair(source, lower, upper));
+                lines.extend([line, line]);
+            }
+        }
+    }
+
+    validate_source_bounds(&source_bounds).map_err(|issues| {
+        let line_of = |source: u8, bound_type: Option<BoundType>, occurrence: usize| {
+            source_bounds
+                .iter()
+                .zip(&lines)
+                .filter(|(bound, _)| {
+                    bound.source == source && bound_type.is_none_or(|ty| bound.bound_type == ty)
+                })
+                .nth(occurrence)
+                .map_or(0, |(_, &line)| line)
+        };
+        CsvError::Invalid(
+            issues
+                .into_iter()
+                .filter_map(|issue| {
+                    let line = match issue {
+                        // Implied by a missing or duplicate bound of some source.
+                        ValidationIssue::OddBoundCount { .. } => return None,
+                        ValidationIssue::MissingLowerBound { source }
+                        | ValidationIssue::MissingUpperBound { source } => line_of(source, None, 0),
+                        ValidationIssue::DuplicateBound { source, bound_type } => {
+                            line_of(source, Some(bound_type), 1)
+                        }
+                        ValidationIssue::InvertedBounds { source, .. } => {
+                            line_of(source, Some(BoundType::Lower), 0)
+                        }
+                    };
+                    Some((line, issue))
+                })
+                .collect(),
+        )
+    })?;
+    Ok(source_bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interval;
+
+    #[test]
+    fn test_read_source_bounds() {
+        let text = "\
+source, lower, upper
+0, 11, 13
+1, 10, 12
+# a source exported bound by bound:
+2, 8, lower
+2, 12, Upper
+";
+        let source_bounds: Vec<SourceBound> = read_source_bounds(text.as_bytes()).unwrap();
+        assert_eq!(source_bounds.len(), 6);
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), 11);
+        assert_eq!(interval.upper_bound(), 12);
+        assert_eq!(interval.sources_true(), 3);
+    }
+
+    #[test]
+    fn test_read_source_bounds_errors_point_at_lines() {
+        let error = read_source_bounds::<i64, _>("0,11,13\n1,x,12\n".as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: invalid lower bound \"x\"");
+
+        let error = read_source_bounds::<i64, _>("0,11\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, CsvError::FieldCount { line: 1, count: 2 }));
+
+        let text = "0,11,13\n1,12,lower\n2,14,10\n0,13,upper\n";
+        match read_source_bounds::<i64, _>(text.as_bytes()).unwrap_err() {
+            CsvError::Invalid(issues) => assert_eq!(
+                issues,
+                vec![
+                    (
+                        4,
+                        ValidationIssue::DuplicateBound {
+                            source: 0,
+                            bound_type: BoundType::Upper
+                        }
+                    ),
+                    (2, ValidationIssue::MissingUpperBound { source: 1 }),
+                    (
+                        3,
+                        ValidationIssue::InvertedBounds {
+                            source: 2,
+                            lower: 14,
+                            upper: 10
+                        }
+                    ),
+                ]
+            ),
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! The clock of Linux, adjusted through `clock_adjtime(2)`: the system clock, or a PTP hardware
+//! clock by its dynamic clock id.
+//!
+//! The kernel corrects the frequency by up to 500ppm, in units of 2⁻¹⁶ppm, and additionally by the
+//! length of a tick, in microseconds, for about 100ppm per microsecond at 100 ticks per second.
+//! Corrections are split between both, so that the clock can be disciplined by up to 10%.
+
+use std::io;
+
+use super::slew::{self, FrequencySlew, Slewing};
+use super::{ClockAdjust, Steering};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Splits a correction in parts per million into the change of the tick, in microseconds, and the
+/// remaining frequency correction in units of 2⁻¹⁶ppm, for a nominal tick of `tick` microseconds.
+fn split_frequency(ppm: f64, tick: i64) -> (i64, i64) {
+    let tick_ppm = 1e6 / tick as f64;
+    let ticks = (ppm / tick_ppm).round();
+    let frequency = (ppm - ticks * tick_ppm) * 65_536.0;
+    (ticks as i64, frequency.round() as i64)
+}
+
+fn timex() -> libc::timex {
+    // SAFETY: `struct timex` is plain old data, for which all zeroes are valid.
+    unsafe { core::mem::zeroed() }
+}
+
+/// A `ClockAdjust` through `clock_adjtime`. Steps are applied with `ADJ_SETOFFSET`, and slews by
+/// the frequency until `poll` finds them over.
+#[derive(Debug)]
+pub struct Adjtimex {
+    clock: libc::clockid_t,
+    /// The nominal length of a tick, in microseconds.
+    tick: i64,
+    slewing: Slewing,
+}
+
+impl Default for Adjtimex {
+    fn default() -> Adjtimex {
+        Adjtimex::new()
+    }
+}
+
+impl Adjtimex {
+    /// Adjusts the system clock, `CLOCK_REALTIME`.
+    pub fn new() -> Adjtimex {
+        Adjtimex::with_clock(libc::CLOCK_REALTIME)
+    }
+
+    /// Adjusts the given clock, e.g. the dynamic clock of a PTP hardware clock.
+    pub fn with_clock(clock: libc::clockid_t) -> Adjtimex {
+        // SAFETY: `sysconf` has no preconditions.
+        let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        Adjtimex {
+            clock,
+            tick: 1_000_000 / if hz > 0 { hz as i64 } else { 100 },
+            slewing: Slewing::default(),
+        }
+    }
+
+    fn adjtime(&self, timex: &mut libc::timex) -> io::Result<libc::c_int> {
+        // SAFETY: `timex` is a valid, exclusive `struct timex` for the duration of the call.
+        let state = unsafe { libc::clock_adjtime(self.clock, timex) };
+        if state < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(state)
+    }
+
+    fn read(&self) -> io::Result<libc::timex> {
+        let mut timex = timex();
+        self.adjtime(&mut timex)?;
+        Ok(timex)
+    }
+
+    /// Returns the frequency correction the kernel currently applies, in parts per million, e.g.
+    /// to start a `DisciplineLoop` from where a previous daemon left off.
+    // `c_long` is only 64 bits on some platforms.
+    #[allow(clippy::useless_conversion)]
+    pub fn frequency(&self) -> io::Result<f64> {
+        let timex = self.read()?;
+        let ticks = i64::from(timex.tick) - self.tick;
+        Ok(ticks as f64 * 1e6 / self.tick as f64 + timex.freq as f64 / 65_536.0)
+    }
+
+    /// Restores the frequency once the slew in progress is over, and returns whether one is still
+    /// in progress, e.g. before every update of the loop.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        slew::poll(self)
+    }
+}
+
+impl FrequencySlew for Adjtimex {
+    fn slewing(&mut self) -> &mut Slewing {
+        &mut self.slewing
+    }
+
+    fn adjust_frequency(&self, ppm: f64) -> io::Result<()> {
+        let (ticks, frequency) = split_frequency(ppm, self.tick);
+        let mut timex = timex();
+        timex.modes = libc::ADJ_FREQUENCY | libc::ADJ_TICK;
+        timex.tick = (self.tick + ticks) as _;
+        timex.freq = frequency as _;
+        self.adjtime(&mut timex)?;
+        Ok(())
+    }
+
+    fn step(&self, delta: i64) -> io::Result<()> {
+        let mut timex = timex();
+        timex.modes = libc::ADJ_SETOFFSET | libc::ADJ_NANO;
+        // With `ADJ_NANO`, the microseconds are nanoseconds, which must not be negative:
+        timex.time.tv_sec = delta.div_euclid(NANOS_PER_SECOND) as _;
+        timex.time.tv_usec = delta.rem_euclid(NANOS_PER_SECOND) as _;
+        self.adjtime(&mut timex)?;
+        Ok(())
+    }
+}
+
+impl ClockAdjust for Adjtimex {
+    type Error = io::Error;
+
+    fn steer(&mut self, steering: &Steering) -> io::Result<()> {
+        slew::steer(self, steering)
+    }
+
+    fn set_frequency(&mut self, ppm: f64) -> io::Result<()> {
+        slew::set_frequency(self, ppm)
+    }
+
+    fn set_error(&mut self, estimated: u64, maximum: u64) -> io::Result<()> {
+        let mut timex = self.read()?;
+        timex.modes = libc::ADJ_ESTERROR | libc::ADJ_MAXERROR | libc::ADJ_STATUS;
+        timex.esterror = (estimated / 1_000).min(libc::c_long::MAX as u64) as _;
+        timex.maxerror = (maximum / 1_000).min(libc::c_long::MAX as u64) as _;
+        timex.status &= !libc::STA_UNSYNC;
+        self.adjtime(&mut timex)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_frequency() {
+        assert_eq!(split_frequency(0.0, 10_000), (0, 0));
+        assert_eq!(split_frequency(12.5, 10_000), (0, 819_200));
+        // Beyond 50ppm at 100Hz, the tick corrects by 100ppm per microsecond:
+        assert_eq!(split_frequency(-730.0, 10_000), (-7, -30 * 65_536));
+        assert_eq!(split_frequency(60.0, 10_000), (1, -40 * 65_536));
+    }
+}
@@ -0,0 +1,141 @@
+//! The system clock of macOS, slewed through `adjtime(2)`, stepped through `clock_settime`, and
+//! corrected in frequency through `ntp_adjtime`.
+//!
+//! All of them require the process to run as root, and are denied to sandboxed processes whatever
+//! their user, so `Adjtime::new` checks for the privilege up front rather than failing on the
+//! first adjustment. The kernel slews an `adjtime` offset out by itself, at its own rate.
+
+use std::io;
+
+use super::{ClockAdjust, Steering};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Returns whether the process may adjust the system clock: it runs as root, and `adjtime` may be
+/// queried, which the sandbox denies.
+pub fn is_privileged() -> bool {
+    // SAFETY: `geteuid` has no preconditions.
+    if unsafe { libc::geteuid() } != 0 {
+        return false;
+    }
+    let mut pending = libc::timeval {
+        tv_sec: 0,
+        tv_usec: 0,
+    };
+    // SAFETY: a null delta only queries the pending adjustment into `pending`.
+    unsafe { libc::adjtime(core::ptr::null(), &mut pending) == 0 }
+}
+
+/// Returns the `adjtime` delta for an offset in nanoseconds, with non-negative microseconds.
+fn timeval(offset: i64) -> libc::timeval {
+    let micros = offset / 1_000;
+    libc::timeval {
+        tv_sec: micros.div_euclid(1_000_000) as _,
+        tv_usec: micros.rem_euclid(1_000_000) as _,
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn timex() -> libc::timex {
+    // SAFETY: `struct timex` is plain old data, for which all zeroes are valid.
+    unsafe { core::mem::zeroed() }
+}
+
+/// A `ClockAdjust` through `adjtime`, `clock_settime`, and `ntp_adjtime`. A slew is handed to
+/// `adjtime` as the offset it makes up for.
+#[derive(Debug)]
+pub struct Adjtime {
+    _private: (),
+}
+
+impl Adjtime {
+    /// Returns an error of kind `PermissionDenied` unless `is_privileged`.
+    pub fn new() -> io::Result<Adjtime> {
+        if !is_privileged() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "adjusting the clock requires root, outside of the sandbox",
+            ));
+        }
+        Ok(Adjtime { _private: () })
+    }
+
+    fn adjtime(&self, offset: i64) -> io::Result<()> {
+        let delta = timeval(offset);
+        // SAFETY: `delta` is a valid `timeval`, and the old delta may be null.
+        check(unsafe { libc::adjtime(&delta, core::ptr::null_mut()) })
+    }
+
+    // `time_t` and `c_long` are only 64 bits on some platforms.
+    #[allow(clippy::useless_conversion)]
+    fn step(&self, delta: i64) -> io::Result<()> {
+        // A pending slew would carry on past the step:
+        self.adjtime(0)?;
+        let mut now = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `now` is a valid, exclusive `timespec` for the duration of the call.
+        check(unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut now) })?;
+        let time = i64::from(now.tv_sec)
+            .saturating_mul(NANOS_PER_SECOND)
+            .saturating_add(i64::from(now.tv_nsec))
+            .saturating_add(delta);
+        let time = libc::timespec {
+            tv_sec: time.div_euclid(NANOS_PER_SECOND) as _,
+            tv_nsec: time.rem_euclid(NANOS_PER_SECOND) as _,
+        };
+        // SAFETY: `time` is a valid `timespec`.
+        check(unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &time) })
+    }
+}
+
+impl ClockAdjust for Adjtime {
+    type Error = io::Error;
+
+    fn steer(&mut self, steering: &Steering) -> io::Result<()> {
+        match *steering {
+            Steering::Slew { ppm, duration } => self.adjtime((ppm * duration as f64 / 1e6) as i64),
+            Steering::Step { delta } => self.step(delta),
+            Steering::Hold => Ok(()),
+        }
+    }
+
+    fn set_frequency(&mut self, ppm: f64) -> io::Result<()> {
+        let mut timex = timex();
+        timex.modes = libc::MOD_FREQUENCY;
+        timex.freq = (ppm * 65_536.0) as _;
+        // SAFETY: `timex` is a valid, exclusive `struct timex` for the duration of the call.
+        check(unsafe { libc::ntp_adjtime(&mut timex) })
+    }
+
+    fn set_error(&mut self, estimated: u64, maximum: u64) -> io::Result<()> {
+        let mut timex = timex();
+        // SAFETY: `timex` is a valid, exclusive `struct timex` for the duration of the calls.
+        check(unsafe { libc::ntp_adjtime(&mut timex) })?;
+        timex.modes = libc::MOD_ESTERROR | libc::MOD_MAXERROR | libc::MOD_STATUS;
+        timex.esterror = (estimated / 1_000).min(libc::c_long::MAX as u64) as _;
+        timex.maxerror = (maximum / 1_000).min(libc::c_long::MAX as u64) as _;
+        timex.status &= !libc::STA_UNSYNC;
+        check(unsafe { libc::ntp_adjtime(&mut timex) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeval() {
+        let delta = timeval(1_500_000_000);
+        assert_eq!((delta.tv_sec, delta.tv_usec), (1, 500_000));
+        let delta = timeval(-2_500);
+        assert_eq!((delta.tv_sec, delta.tv_usec), (-1, 999_998));
+    }
+}
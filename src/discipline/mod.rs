@@ -0,0 +1,360 @@
+//! A clock discipline loop in the style of ntpd (RFC 5905, appendix A.5.5.6), which turns the
+//! offsets estimated by a `Pipeline` into a continuous frequency correction for the local clock.
+//!
+//! In the phase-locked mode, the loop slews out the offset over the time constant, and integrates
+//! the offset into the frequency. An offset large enough to be dominated by the frequency error
+//! rather than by noise switches the loop to the frequency-locked mode, which measures the
+//! frequency error directly from the drift of the offset between updates, and locks much faster.
+//!
+//! A `Steerer` decides whether to step the local clock at all, or to slew it, and a `ClockAdjust`
+//! applies both to the clock of the operating system.
+
+#[cfg(all(target_os = "linux", feature = "linux"))]
+pub mod linux;
+#[cfg(all(target_os = "macos", feature = "macos"))]
+pub mod macos;
+#[cfg(any(
+    all(target_os = "linux", feature = "linux"),
+    all(windows, feature = "windows")
+))]
+mod slew;
+#[cfg(all(windows, feature = "windows"))]
+pub mod windows;
+
+use crate::combine::Estimate;
+use crate::Interval;
+
+/// The time constant of the loop, in seconds: the phase-locked loop slews out an offset over about
+/// this long, and responds to frequency errors about four times more slowly.
+pub const TIME_CONSTANT: f64 = 64.0;
+/// The offset beyond which the loop switches to the frequency-locked mode, in nanoseconds. It
+/// returns to the phase-locked mode below half of this.
+pub const FLL_THRESHOLD: u64 = 1_000_000;
+/// The largest frequency correction, in parts per million, as for the kernel of Linux.
+pub const MAX_FREQUENCY: f64 = 500.0;
+
+/// The offset beyond which to step the local clock rather than slew it, in nanoseconds, as in
+/// ntpd.
+pub const STEP_LIMIT: u64 = 128_000_000;
+/// How long the offset must stay beyond `STEP_LIMIT` before the clock is stepped, in nanoseconds,
+/// so that a single outlier does not step it.
+pub const STEPOUT: u64 = 300_000_000_000;
+
+/// The fraction of the measured frequency error that the frequency-locked loop corrects per update.
+const FLL_GAIN: f64 = 0.25;
+
+const NANOS_PER_SECOND: f64 = 1e9;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct LoopConfig {
+    /// The time constant, in seconds.
+    pub time_constant: f64,
+    /// The offset beyond which to lock the frequency rather than the phase, in nanoseconds.
+    pub fll_threshold: u64,
+    /// The largest correction, in parts per million.
+    pub max_frequency: f64,
+}
+
+impl Default for LoopConfig {
+    fn default() -> LoopConfig {
+        LoopConfig {
+            time_constant: TIME_CONSTANT,
+            fll_threshold: FLL_THRESHOLD,
+            max_frequency: MAX_FREQUENCY,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LoopMode {
+    /// Phase-locked: the offset is slewed out, and integrated into the frequency.
+    Pll,
+    /// Frequency-locked: the drift of the offset is corrected in the frequency.
+    Fll,
+}
+
+/// The correction of the local clock after an update.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correction {
+    /// The frequency by which to speed up the local clock until the next update, in parts per
+    /// million: the learned frequency error plus the rate at which the offset is slewed out.
+    pub frequency: f64,
+    /// The learned frequency error of the local clock, in parts per million.
+    pub frequency_error: f64,
+    pub mode: LoopMode,
+}
+
+/// The phase/frequency-locked loop. The local clock is assumed to run at the returned frequency
+/// between updates, so that every offset is measured against the disciplined clock.
+#[derive(Debug, Clone)]
+pub struct DisciplineLoop {
+    config: LoopConfig,
+    mode: LoopMode,
+    /// The learned frequency error, in nanoseconds per second.
+    frequency: f64,
+    /// The local time and offset of the last update, in nanoseconds, and the rate at which the
+    /// offset was being slewed out since, in nanoseconds per second.
+    last: Option<(i64, i64, f64)>,
+}
+
+impl Default for DisciplineLoop {
+    fn default() -> DisciplineLoop {
+        DisciplineLoop::new(LoopConfig::default())
+    }
+}
+
+impl DisciplineLoop {
+    /// # Panics
+    ///
+    /// Panics if the time constant is not positive.
+    pub fn new(config: LoopConfig) -> DisciplineLoop {
+        assert!(config.time_constant > 0.0);
+        DisciplineLoop {
+            config,
+            mode: LoopMode::Pll,
+            frequency: 0.0,
+            last: None,
+        }
+    }
+
+    /// Starts the loop from a frequency error learned earlier, e.g. from a drift file or by
+    /// `ClockFilter::frequency`, in parts per million.
+    pub fn with_frequency(mut self, ppm: f64) -> Self {
+        self.frequency = self.clamp(ppm * 1_000.0);
+        self
+    }
+
+    pub fn mode(&self) -> LoopMode {
+        self.mode
+    }
+
+    /// Updates the loop with the offset of the reference time from the local clock at the local
+    /// time `time`, both in nanoseconds, and returns the correction until the next update.
+    pub fn update(&mut self, time: i64, offset: i64) -> Correction {
+        let magnitude = offset.unsigned_abs();
+        self.mode = match self.mode {
+            LoopMode::Pll if magnitude > self.config.fll_threshold => LoopMode::Fll,
+            LoopMode::Fll if magnitude <= self.config.fll_threshold / 2 => LoopMode::Pll,
+            mode => mode,
+        };
+
+        let tau = self.config.time_constant;
+        if let Some((last_time, last_offset, slew)) = self.last {
+            let mu = (time as f64 - last_time as f64) / NANOS_PER_SECOND;
+            if mu > 0.0 {
+                let error = match self.mode {
+                    LoopMode::Pll => offset as f64 * mu / (16.0 * tau * tau),
+                    // The slew of the last offset was meant to cancel it out, so whatever drift
+                    // remains is a frequency error:
+                    LoopMode::Fll => {
+                        let drift = offset as f64 - last_offset as f64 + slew * mu;
+                        FLL_GAIN * drift / mu
+                    }
+                };
+                self.frequency = self.clamp(self.frequency + error);
+            }
+        }
+        let frequency = self.clamp(self.frequency + offset as f64 / tau);
+        self.last = Some((time, offset, frequency - self.frequency));
+
+        Correction {
+            frequency: frequency / 1_000.0,
+            frequency_error: self.frequency / 1_000.0,
+            mode: self.mode,
+        }
+    }
+
+    /// Updates the loop with the offset of an estimate of the pipeline.
+    pub fn update_estimate(&mut self, time: i64, estimate: &Estimate) -> Correction {
+        self.update(time, estimate.offset)
+    }
+
+    /// Forgets the last offset but keeps the learned frequency, e.g. after the local clock was
+    /// stepped to the reference time.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.mode = LoopMode::Pll;
+    }
+
+    fn clamp(&self, frequency: f64) -> f64 {
+        let max = self.config.max_frequency * 1_000.0;
+        frequency.clamp(-max, max)
+    }
+}
+
+/// How to steer the local clock.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Steering {
+    /// Speed up the local clock by `ppm` parts per million, or slow it down if negative, for
+    /// `duration` nanoseconds.
+    Slew { ppm: f64, duration: u64 },
+    /// Set the local clock forward by `delta` nanoseconds, or back if negative.
+    Step { delta: i64 },
+    /// Leave the local clock alone.
+    Hold,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SteeringPolicy {
+    /// The offset beyond which to step, in nanoseconds.
+    pub step_limit: u64,
+    /// How long the offset must stay beyond `step_limit` before stepping, in nanoseconds.
+    pub stepout: u64,
+    /// The rate at which to slew, in parts per million.
+    pub max_slew: f64,
+}
+
+impl Default for SteeringPolicy {
+    fn default() -> SteeringPolicy {
+        SteeringPolicy {
+            step_limit: STEP_LIMIT,
+            stepout: STEPOUT,
+            max_slew: MAX_FREQUENCY,
+        }
+    }
+}
+
+/// Decides how to steer the local clock towards the chosen intervals: not at all while the interval
+/// contains the local time, by a slew of the midpoint at the largest rate while it is within the
+/// step limit, and by a step once the midpoint stayed beyond it for the stepout.
+#[derive(Debug, Clone, Default)]
+pub struct Steerer {
+    policy: SteeringPolicy,
+    /// The local time at which the offset first exceeded the step limit.
+    beyond_since: Option<i64>,
+}
+
+impl Steerer {
+    /// # Panics
+    ///
+    /// Panics if the slew rate is not positive.
+    pub fn new(policy: SteeringPolicy) -> Steerer {
+        assert!(policy.max_slew > 0.0);
+        Steerer {
+            policy,
+            beyond_since: None,
+        }
+    }
+
+    /// Decides how to steer the local clock for the interval chosen at the local time `time`.
+    pub fn steer(&mut self, time: i64, interval: &Interval) -> Steering {
+        let offset = (interval.lower_bound() as i128 + interval.width() as i128 / 2) as i64;
+        if interval.lower_bound() <= 0 && 0 <= interval.upper_bound() {
+            self.beyond_since = None;
+            return Steering::Hold;
+        }
+        if offset.unsigned_abs() > self.policy.step_limit {
+            let since = *self.beyond_since.get_or_insert(time);
+            if time.saturating_sub(since).unsigned_abs() < self.policy.stepout {
+                return Steering::Hold;
+            }
+            self.beyond_since = None;
+            return Steering::Step { delta: offset };
+        }
+        self.beyond_since = None;
+        let ppm = self.policy.max_slew;
+        Steering::Slew {
+            ppm: if offset < 0 { -ppm } else { ppm },
+            duration: (offset.unsigned_abs() as f64 * 1e6 / ppm) as u64,
+        }
+    }
+}
+
+/// An adjustable clock, to apply the decisions of a `Steerer` and the corrections of a
+/// `DisciplineLoop` to.
+pub trait ClockAdjust {
+    type Error;
+
+    /// Applies a steering decision. A slew runs on top of the frequency correction, and ends after
+    /// its duration.
+    fn steer(&mut self, steering: &Steering) -> Result<(), Self::Error>;
+
+    /// Sets the frequency correction of the clock, in parts per million.
+    fn set_frequency(&mut self, ppm: f64) -> Result<(), Self::Error>;
+
+    /// Reports the estimated and the maximum error of the clock, in nanoseconds, for the
+    /// operating systems that keep them, and marks the clock as synchronized.
+    fn set_error(&mut self, estimated: u64, maximum: u64) -> Result<(), Self::Error> {
+        let _ = (estimated, maximum);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    /// Disciplines a local clock that runs 20ppm slow, with the given initial offset, for `updates`
+    /// updates every 16s, and returns the last correction and offset.
+    fn discipline(discipline: &mut DisciplineLoop, offset: f64, updates: i64) -> (Correction, f64) {
+        let mut offset = offset;
+        let mut correction = None;
+        for update in 0..updates {
+            let applied = discipline.update(update * 16_000_000_000, offset as i64);
+            offset += (20.0 - applied.frequency) * 1_000.0 * 16.0;
+            correction = Some(applied);
+        }
+        (correction.unwrap(), offset)
+    }
+
+    #[test]
+    fn test_steerer() {
+        let interval = |lower, upper| {
+            Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, lower, upper)))
+                .unwrap()
+                .unwrap()
+        };
+        let mut steerer = Steerer::default();
+        assert_eq!(steerer.steer(0, &interval(-5, 10)), Steering::Hold);
+        assert_eq!(
+            steerer.steer(0, &interval(-1_500_000, -500_000)),
+            Steering::Slew {
+                ppm: -500.0,
+                duration: 2_000_000_000,
+            }
+        );
+
+        // An offset beyond the step limit only steps once it persisted for the stepout:
+        let beyond = interval(200_000_000, 200_001_000);
+        assert_eq!(steerer.steer(1_000, &beyond), Steering::Hold);
+        assert_eq!(
+            steerer.steer(1_000 + STEPOUT as i64 - 1, &beyond),
+            Steering::Hold
+        );
+        assert_eq!(
+            steerer.steer(1_000 + STEPOUT as i64, &beyond),
+            Steering::Step { delta: 200_000_500 }
+        );
+        assert_eq!(steerer.steer(2 * STEPOUT as i64, &beyond), Steering::Hold);
+    }
+
+    #[test]
+    fn test_discipline_loop() {
+        let mut pll = DisciplineLoop::default();
+        let (correction, offset) = discipline(&mut pll, 10_000.0, 512);
+        assert_eq!(correction.mode, LoopMode::Pll);
+        assert!(
+            (correction.frequency_error - 20.0).abs() < 0.1,
+            "{correction:?}"
+        );
+        assert!(offset.abs() < 1_000.0);
+
+        // A large offset locks the frequency first, and returns to the phase once it is slewed out:
+        let mut fll = DisciplineLoop::default();
+        assert_eq!(fll.update(0, 50_000_000).mode, LoopMode::Fll);
+        fll.reset();
+        let (correction, offset) = discipline(&mut fll, 50_000_000.0, 512);
+        assert_eq!(correction.mode, LoopMode::Pll);
+        assert!(
+            (correction.frequency_error - 20.0).abs() < 0.1,
+            "{correction:?}"
+        );
+        assert!(offset.abs() < 1_000.0);
+    }
+}
@@ -0,0 +1,152 @@
+//! Slews by the frequency, for the clocks of operating systems that do not slew by themselves: the
+//! rate of a slew is added to the frequency correction until its duration elapsed, so that the
+//! backends only correct the frequency of, and step, the clock.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::Steering;
+
+/// The frequency correction of a clock, and the slew in progress on top of it.
+#[derive(Debug, Default)]
+pub(crate) struct Slewing {
+    /// The frequency correction, in parts per million.
+    frequency: f64,
+    /// The rate of the slew in progress, in parts per million, and when it ends.
+    slew: Option<(f64, Instant)>,
+}
+
+impl Slewing {
+    /// Returns the correction of the clock, in parts per million.
+    fn ppm(&self) -> f64 {
+        self.frequency + self.slew.map_or(0.0, |(ppm, _)| ppm)
+    }
+
+    /// Ends the slew in progress, if there is one, without correcting the clock.
+    pub(crate) fn cancel(&mut self) -> bool {
+        self.slew.take().is_some()
+    }
+}
+
+/// A clock that slews by correcting its frequency.
+pub(crate) trait FrequencySlew {
+    fn slewing(&mut self) -> &mut Slewing;
+
+    /// Corrects the frequency of the clock by `ppm` parts per million.
+    fn adjust_frequency(&self, ppm: f64) -> io::Result<()>;
+
+    /// Steps the clock by `delta` nanoseconds.
+    fn step(&self, delta: i64) -> io::Result<()>;
+}
+
+/// Ends the slew in progress once its duration elapsed, and returns whether one is still in
+/// progress.
+pub(crate) fn poll<C: FrequencySlew>(clock: &mut C) -> io::Result<bool> {
+    let slewing = clock.slewing();
+    match slewing.slew {
+        Some((_, end)) if Instant::now() >= end => {
+            slewing.slew = None;
+            let ppm = slewing.ppm();
+            clock.adjust_frequency(ppm)?;
+            Ok(false)
+        }
+        slew => Ok(slew.is_some()),
+    }
+}
+
+/// Steers as `ClockAdjust::steer`: a slew corrects the frequency until `poll` ends it, and a step
+/// ends the slew in progress first.
+pub(crate) fn steer<C: FrequencySlew>(clock: &mut C, steering: &Steering) -> io::Result<()> {
+    match *steering {
+        Steering::Slew { ppm, duration } => {
+            let slewing = clock.slewing();
+            slewing.slew = Some((ppm, Instant::now() + Duration::from_nanos(duration)));
+            let ppm = slewing.ppm();
+            clock.adjust_frequency(ppm)
+        }
+        Steering::Step { delta } => {
+            let slewing = clock.slewing();
+            if slewing.cancel() {
+                let ppm = slewing.ppm();
+                clock.adjust_frequency(ppm)?;
+            }
+            clock.step(delta)
+        }
+        Steering::Hold => Ok(()),
+    }
+}
+
+/// Sets the frequency correction as `ClockAdjust::set_frequency`, below the slew in progress.
+pub(crate) fn set_frequency<C: FrequencySlew>(clock: &mut C, ppm: f64) -> io::Result<()> {
+    let slewing = clock.slewing();
+    slewing.frequency = ppm;
+    let ppm = slewing.ppm();
+    clock.adjust_frequency(ppm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::vec::Vec;
+
+    #[derive(Default)]
+    struct Clock {
+        slewing: Slewing,
+        /// Every correction of the frequency, in parts per million, and every step.
+        adjustments: RefCell<Vec<(f64, i64)>>,
+    }
+
+    impl FrequencySlew for Clock {
+        fn slewing(&mut self) -> &mut Slewing {
+            &mut self.slewing
+        }
+
+        fn adjust_frequency(&self, ppm: f64) -> io::Result<()> {
+            self.adjustments.borrow_mut().push((ppm, 0));
+            Ok(())
+        }
+
+        fn step(&self, delta: i64) -> io::Result<()> {
+            self.adjustments.borrow_mut().push((0.0, delta));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_slew() {
+        let mut clock = Clock::default();
+        set_frequency(&mut clock, 10.0).unwrap();
+        let slew = Steering::Slew {
+            ppm: 25.0,
+            duration: 3_600_000_000_000,
+        };
+        steer(&mut clock, &slew).unwrap();
+        assert!(poll(&mut clock).unwrap());
+        // The frequency changes below the slew:
+        set_frequency(&mut clock, 5.0).unwrap();
+        // A step ends the slew first:
+        steer(&mut clock, &Steering::Step { delta: -4_000 }).unwrap();
+        assert!(!poll(&mut clock).unwrap());
+
+        // A slew of no duration is over by the next poll:
+        let slew = Steering::Slew {
+            ppm: -50.0,
+            duration: 0,
+        };
+        steer(&mut clock, &slew).unwrap();
+        assert!(!poll(&mut clock).unwrap());
+        assert_eq!(
+            *clock.adjustments.borrow(),
+            [
+                (10.0, 0),
+                (35.0, 0),
+                (30.0, 0),
+                (5.0, 0),
+                (0.0, -4_000),
+                (-45.0, 0),
+                (5.0, 0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! The system clock of Windows, adjusted through `SetSystemTimeAdjustmentPrecise` and stepped
+//! through `SetSystemTime`, which both require the `SeSystemtimePrivilege` of the process.
+//!
+//! Windows advances the clock by a time adjustment every time increment, both in 100ns units, so
+//! the frequency is corrected by the ratio of the adjustment to the increment. Adjusting the clock
+//! disables the time synchronization of the system itself until `release`.
+
+use std::io;
+
+use super::slew::{self, FrequencySlew, Slewing};
+use super::{ClockAdjust, Steering};
+
+/// The number of 100ns units since 1601, of `FILETIME`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct FileTime {
+    low: u32,
+    high: u32,
+}
+
+impl FileTime {
+    fn from_ticks(ticks: i64) -> FileTime {
+        FileTime {
+            low: ticks as u32,
+            high: (ticks >> 32) as u32,
+        }
+    }
+
+    fn ticks(self) -> i64 {
+        ((self.high as u64) << 32 | self.low as u64) as i64
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct SystemTime {
+    year: u16,
+    month: u16,
+    day_of_week: u16,
+    day: u16,
+    hour: u16,
+    minute: u16,
+    second: u16,
+    milliseconds: u16,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetSystemTimeAdjustmentPrecise(
+        adjustment: *mut u64,
+        increment: *mut u64,
+        disabled: *mut i32,
+    ) -> i32;
+    fn SetSystemTimeAdjustmentPrecise(adjustment: u64, disabled: i32) -> i32;
+    fn GetSystemTimePreciseAsFileTime(time: *mut FileTime);
+    fn FileTimeToSystemTime(file_time: *const FileTime, system_time: *mut SystemTime) -> i32;
+    fn SetSystemTime(system_time: *const SystemTime) -> i32;
+}
+
+fn check(result: i32) -> io::Result<()> {
+    if result == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns the time adjustment for a correction in parts per million of the time increment.
+fn adjustment(ppm: f64, increment: u64) -> u64 {
+    (increment as f64 * (1.0 + ppm / 1e6)).round() as u64
+}
+
+/// A `ClockAdjust` through the precise time adjustment of Windows. Slews are applied by the
+/// frequency until `poll` finds them over.
+#[derive(Debug)]
+pub struct SystemTimeAdjustment {
+    /// The time increment, in 100ns units.
+    increment: u64,
+    slewing: Slewing,
+}
+
+impl SystemTimeAdjustment {
+    pub fn new() -> io::Result<SystemTimeAdjustment> {
+        let (mut adjustment, mut increment, mut disabled) = (0, 0, 0);
+        // SAFETY: the pointers are to valid, exclusive locals for the duration of the call.
+        check(unsafe {
+            GetSystemTimeAdjustmentPrecise(&mut adjustment, &mut increment, &mut disabled)
+        })?;
+        Ok(SystemTimeAdjustment {
+            increment,
+            slewing: Slewing::default(),
+        })
+    }
+
+    /// Returns the current time of the system clock, in nanoseconds since the Unix epoch.
+    pub fn now(&self) -> i64 {
+        let mut time = FileTime::default();
+        // SAFETY: `time` is a valid, exclusive `FILETIME` for the duration of the call.
+        unsafe { GetSystemTimePreciseAsFileTime(&mut time) };
+        filetime_nanos(time)
+    }
+
+    /// Restores the frequency once the slew in progress is over, and returns whether one is still
+    /// in progress.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        slew::poll(self)
+    }
+
+    /// Returns the clock to the time synchronization of the system.
+    pub fn release(&mut self) -> io::Result<()> {
+        self.slewing.cancel();
+        // SAFETY: `SetSystemTimeAdjustmentPrecise` has no preconditions.
+        check(unsafe { SetSystemTimeAdjustmentPrecise(0, 1) })
+    }
+}
+
+impl FrequencySlew for SystemTimeAdjustment {
+    fn slewing(&mut self) -> &mut Slewing {
+        &mut self.slewing
+    }
+
+    fn adjust_frequency(&self, ppm: f64) -> io::Result<()> {
+        let adjustment = adjustment(ppm, self.increment);
+        // SAFETY: `SetSystemTimeAdjustmentPrecise` has no preconditions.
+        check(unsafe { SetSystemTimeAdjustmentPrecise(adjustment, 0) })
+    }
+
+    fn step(&self, delta: i64) -> io::Result<()> {
+        let mut time = FileTime::default();
+        // SAFETY: `time` is a valid, exclusive `FILETIME` for the duration of the call.
+        unsafe { GetSystemTimePreciseAsFileTime(&mut time) };
+        let time = FileTime::from_ticks(time.ticks().saturating_add(delta / 100));
+        let mut system_time = SystemTime::default();
+        // SAFETY: both pointers are to valid locals for the duration of the calls.
+        check(unsafe { FileTimeToSystemTime(&time, &mut system_time) })?;
+        check(unsafe { SetSystemTime(&system_time) })
+    }
+}
+
+/// The 100ns units between 1601 and the Unix epoch.
+const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+
+fn filetime_nanos(time: FileTime) -> i64 {
+    (time.ticks() - UNIX_EPOCH_TICKS).saturating_mul(100)
+}
+
+impl ClockAdjust for SystemTimeAdjustment {
+    type Error = io::Error;
+
+    fn steer(&mut self, steering: &Steering) -> io::Result<()> {
+        slew::steer(self, steering)
+    }
+
+    fn set_frequency(&mut self, ppm: f64) -> io::Result<()> {
+        slew::set_frequency(self, ppm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjustment() {
+        assert_eq!(adjustment(0.0, 156_250), 156_250);
+        assert_eq!(adjustment(100.0, 156_250), 156_266);
+        assert_eq!(adjustment(-500.0, 156_250), 156_172);
+        assert_eq!(
+            filetime_nanos(FileTime::from_ticks(UNIX_EPOCH_TICKS + 15)),
+            1_500
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! A compact binary encoding of bound sets and results, so that a `no_std` device can stream its
+//! clock bounds to a host that runs the selection, and vice versa.
+//!
+//! Every frame starts with a format version byte, followed by the postcard encoding of the
+//! payload. Postcard encodes integers as varints (zigzag for signed values), so a bound typically
+//! takes only a few bytes. The layout of version 1 is fixed: a bound is its value, source, and
+//! bound type (`0` for lower, `1` for upper), in that order.
+
+use alloc::vec::Vec;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Interval, MarzulloError, SourceBound};
+
+/// The version of the encoding produced by this crate.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The result of a selection as it is encoded: the outcome of `Interval::try_from_source_bounds`.
+pub type Selection<T = i64> = Result<Option<Interval<T>>, MarzulloError<T>>;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum EncodingError {
+    /// The frame is empty, so there is no version byte.
+    Empty,
+    UnsupportedVersion(u8),
+    Postcard(postcard::Error),
+}
+
+impl core::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            EncodingError::Empty => write!(f, "empty frame"),
+            EncodingError::UnsupportedVersion(version) => {
+                write!(f, "unsupported encoding version {}", version)
+            }
+            EncodingError::Postcard(error) => write!(f, "postcard: {}", error),
+        }
+    }
+}
+
+impl core::error::Error for EncodingError {}
+
+impl From<postcard::Error> for EncodingError {
+    fn from(error: postcard::Error) -> EncodingError {
+        EncodingError::Postcard(error)
+    }
+}
+
+fn encode<P: Serialize + ?Sized>(payload: &P) -> Result<Vec<u8>, EncodingError> {
+    let mut frame = Vec::from([FORMAT_VERSION]);
+    frame.extend(postcard::to_allocvec(payload)?);
+    Ok(frame)
+}
+
+fn encode_to_slice<'a, P: Serialize + ?Sized>(
+    payload: &P,
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], EncodingError> {
+    let (version, rest) = buffer.split_first_mut().ok_or(EncodingError::Postcard(
+        postcard::Error::SerializeBufferFull,
+    ))?;
+    *version = FORMAT_VERSION;
+    let length = postcard::to_slice(payload, rest)?.len();
+    Ok(&mut buffer[..length + 1])
+}
+
+fn decode<P: DeserializeOwned>(frame: &[u8]) -> Result<P, EncodingError> {
+    match frame.split_first() {
+        None => Err(EncodingError::Empty),
+        Some((&FORMAT_VERSION, payload)) => Ok(postcard::from_bytes(payload)?),
+        Some((&version, _)) => Err(EncodingError::UnsupportedVersion(version)),
+    }
+}
+
+pub fn encode_bounds<T: Serialize>(
+    source_bounds: &[SourceBound<T>],
+) -> Result<Vec<u8>, EncodingError> {
+    encode(source_bounds)
+}
+
+/// Encodes the bounds into `buffer` without allocating, and returns the used part of `buffer`.
+pub fn encode_bounds_to_slice<'a, T: Serialize>(
+    source_bounds: &[SourceBound<T>],
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], EncodingError> {
+    encode_to_slice(source_bounds, buffer)
+}
+
+pub fn decode_bounds<T: DeserializeOwned>(
+    frame: &[u8],
+) -> Result<Vec<SourceBound<T>>, EncodingError> {
+    decode(frame)
+}
+
+pub fn encode_selection<T: Serialize>(selection: &Selection<T>) -> Result<Vec<u8>, EncodingError> {
+    encode(selection)
+}
+
+/// Encodes the selection into `buffer` without allocating, and returns the used part of `buffer`.
+pub fn encode_selection_to_slice<'a, T: Serialize>(
+    selection: &Selection<T>,
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], EncodingError> {
+    encode_to_slice(selection, buffer)
+}
+
+pub fn decode_selection<T: DeserializeOwned>(frame: &[u8]) -> Result<Selection<T>, EncodingError> {
+    decode(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_round_trip() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, -10, 12));
+
+        let frame = encode_bounds(&source_bounds).unwrap();
+        // The version, the length, and three bytes per bound:
+        assert_eq!(frame, [1, 4, 22, 0, 0, 26, 0, 1, 19, 1, 0, 24, 1, 1]);
+        let decoded: Vec<SourceBound> = decode_bounds(&frame).unwrap();
+        assert!(decoded
+            .iter()
+            .zip(&source_bounds)
+            .all(|(a, b)| a == b && a.source == b.source));
+
+        let mut buffer = [0; 32];
+        let used = encode_bounds_to_slice(&source_bounds, &mut buffer).unwrap();
+        assert_eq!(used, frame.as_slice());
+        let mut buffer = [0; 4];
+        assert!(encode_bounds_to_slice(&source_bounds, &mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_selection_round_trip() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        let selection = Interval::try_from_source_bounds(source_bounds);
+
+        let frame = encode_selection(&selection).unwrap();
+        let decoded: Selection = decode_selection(&frame).unwrap();
+        let interval = decoded.unwrap().unwrap();
+        assert_eq!(interval.lower_bound(), 11);
+        assert_eq!(interval.upper_bound(), 12);
+        assert_eq!(interval.sources_true(), 2);
+
+        let frame = encode_selection::<i64>(&Ok(None)).unwrap();
+        assert!(matches!(decode_selection::<i64>(&frame), Ok(Ok(None))));
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_versions() {
+        assert_eq!(decode_bounds::<i64>(&[]).unwrap_err(), EncodingError::Empty);
+        assert_eq!(
+            decode_bounds::<i64>(&[2, 0]).unwrap_err(),
+            EncodingError::UnsupportedVersion(2)
+        );
+    }
+}
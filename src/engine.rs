@@ -0,0 +1,379 @@
+//! A reusable selection engine. The engine runs the algorithm like
+//! `Interval::try_from_source_bounds`, and reports every selection to the registered observers, so
+//! that embedders can wire selection events into their own telemetry without extra dependencies.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::options::{self, EngineOptions, Options};
+use crate::ordering::Order;
+use crate::tiebreak::Score;
+use crate::verdict::source_intervals;
+use crate::{
+    Interval, IntervalInvariant, MarzulloError, Report, SourceBound, SourceVerdict, Value, Verdict,
+};
+
+/// Receives the events of every selection of an `Engine`. All methods do nothing by default.
+pub trait Observer<T = i64> {
+    /// Called with the outcome of every selection, after the other events of the selection.
+    fn on_result(&mut self, _result: &Result<Option<Interval<T>>, MarzulloError<T>>) {}
+
+    /// Called for every source whose interval does not contain the chosen interval.
+    fn on_source_rejected(&mut self, _verdict: &SourceVerdict<T>) {}
+
+    /// Called when the algorithm detects a violation of its own invariants, which is a bug rather
+    /// than bad input.
+    fn on_invariant_violation(&mut self, _invariant: &IntervalInvariant<T>) {}
+
+    /// Called when the state of a source of a `SourceRegistry` changes, before the selection.
+    fn on_source_event(&mut self, _event: &SourceEvent) {}
+}
+
+/// A change of the state of a source of a `SourceRegistry`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SourceEvent {
+    /// The latest sample of the source outlived its time to live, and was dropped.
+    Expired { source: u8 },
+    /// The source failed to be sampled for the given number of polls in a row.
+    Unreachable { source: u8, missed: u32 },
+    /// The source was sampled for the first time, or again after it was unreachable.
+    Reachable { source: u8 },
+    /// The source fell outside the chosen interval in the given number of selections in a row, and
+    /// is excluded from selection.
+    Falseticker { source: u8, strikes: u32 },
+    /// A probe found the falseticker within the chosen interval again.
+    Rehabilitated { source: u8 },
+}
+
+pub struct Engine<T = i64> {
+    observers: Vec<Box<dyn Observer<T>>>,
+    order: Order,
+    score: Score,
+    stickiness: Option<Stickiness>,
+    /// Widens the previous interval to intersect the next one with, if the engine refines.
+    refinement: Option<Box<Widen<T>>>,
+    /// The last chosen interval, if the engine is sticky or refines.
+    previous: Option<Interval<T>>,
+}
+
+/// Returns the bounds of an interval widened by the uncertainty it accrued until the next
+/// selection, e.g. by the drift of the local clock between selections.
+pub type Widen<T> = dyn FnMut(&Interval<T>) -> (T, T);
+
+/// How much better a new interval must be than the previous one to replace it, so that
+/// successive selections do not flip-flop between nearly equal intervals.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stickiness {
+    /// The number of sources the new interval must be consistent with beyond those still
+    /// consistent with the previous interval.
+    pub min_overlap_gain: u8,
+    /// The fraction by which the new interval must be narrower than the previous one, for value
+    /// types with a `Value::width_to_f64`. For other value types, only `min_overlap_gain` counts.
+    pub min_width_gain: f64,
+}
+
+impl Default for Stickiness {
+    fn default() -> Stickiness {
+        Stickiness {
+            min_overlap_gain: 1,
+            min_width_gain: 0.1,
+        }
+    }
+}
+
+impl Stickiness {
+    /// Returns the previous interval, with the sources that are still consistent with it, unless
+    /// `winner` improves on it by the thresholds, or no source is consistent with it anymore.
+    fn keep<T: Value>(
+        &self,
+        previous: &Interval<T>,
+        winner: &Interval<T>,
+        source_bounds: &[SourceBound<T>],
+    ) -> Option<Interval<T>> {
+        let sources = source_intervals(source_bounds);
+        let support = sources
+            .iter()
+            .filter(|(_, lower, upper)| previous.is_contained_by(lower, upper))
+            .count();
+        let gain = (winner.sources_true as usize).saturating_sub(support);
+        if support == 0 || gain >= self.min_overlap_gain as usize {
+            return None;
+        }
+        let widths = (
+            T::width_to_f64(&previous.width()),
+            T::width_to_f64(&winner.width()),
+        );
+        if let (Some(previous), Some(winner)) = widths {
+            if winner < previous * (1.0 - self.min_width_gain) {
+                return None;
+            }
+        }
+        Some(Interval {
+            lower_bound: previous.lower_bound.clone(),
+            upper_bound: previous.upper_bound.clone(),
+            sources_true: support as u8,
+            sources_false: (sources.len() - support) as u8,
+        })
+    }
+}
+
+impl<T: Value> Default for Engine<T> {
+    fn default() -> Engine<T> {
+        Engine::new()
+    }
+}
+
+impl<T: Value> Engine<T> {
+    pub fn new() -> Engine<T> {
+        Engine {
+            observers: Vec::new(),
+            order: Order::default(),
+            score: Score::default(),
+            stickiness: None,
+            refinement: None,
+            previous: None,
+        }
+    }
+
+    /// Intersects every chosen interval with the previous one, widened by `widen`, so that the
+    /// interval narrows over successive selections as samples accumulate. If the intersection is
+    /// empty, the sources moved beyond the widened interval, and the chosen interval starts over.
+    pub fn with_refinement<W: FnMut(&Interval<T>) -> (T, T) + 'static>(
+        mut self,
+        widen: W,
+    ) -> Engine<T> {
+        self.refinement = Some(Box::new(widen));
+        self
+    }
+
+    /// Keeps the previous interval across selections unless a new interval improves on it by the
+    /// thresholds of `stickiness`.
+    pub fn with_stickiness(mut self, stickiness: Stickiness) -> Engine<T> {
+        self.stickiness = Some(stickiness);
+        self
+    }
+
+    /// Selects with the order and score of `options`, and with its stickiness and previous
+    /// interval, if it ran through a sticky engine.
+    pub fn with_options(mut self, options: Options<T>) -> Engine<T> {
+        self.order = options.order;
+        self.score = options.score;
+        if let Some(engine) = options.engine {
+            self.stickiness = Some(engine.stickiness);
+            self.previous = engine.previous;
+        }
+        self
+    }
+
+    /// Returns the options the next selection runs with.
+    pub fn options(&self) -> Options<T> {
+        Options {
+            order: self.order,
+            score: self.score,
+            engine: self.stickiness.map(|stickiness| EngineOptions {
+                stickiness,
+                previous: self.previous.clone(),
+            }),
+        }
+    }
+
+    /// Registers an observer of all later selections.
+    pub fn add_observer<O: Observer<T> + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Notifies the observers of a change of the state of a source.
+    pub(crate) fn notify(&mut self, event: SourceEvent) {
+        for observer in &mut self.observers {
+            observer.on_source_event(&event);
+        }
+    }
+
+    /// Returns the smallest interval consistent with the largest number of sources, as
+    /// `Interval::try_from_source_bounds` does, or the previous interval if the engine is sticky,
+    /// and notifies the observers.
+    pub fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        if self.observers.is_empty() && self.stickiness.is_none() && self.refinement.is_none() {
+            return options::sweep(self.order, self.score, source_bounds);
+        }
+
+        let mut result = options::sweep(self.order, self.score, source_bounds.clone());
+        if self.stickiness.is_some() || self.refinement.is_some() {
+            match &mut result {
+                Ok(Some(interval)) => {
+                    if let Some(previous) = &self.previous {
+                        let kept = self.stickiness.and_then(|stickiness| {
+                            stickiness.keep(previous, interval, &source_bounds)
+                        });
+                        if let Some(kept) = kept {
+                            *interval = kept;
+                        } else if let Some(widen) = &mut self.refinement {
+                            if let Some(refined) = refine(widen(previous), interval, &source_bounds)
+                            {
+                                *interval = refined;
+                            }
+                        }
+                    }
+                    self.previous = Some(interval.clone());
+                }
+                Ok(None) => self.previous = None,
+                Err(_) => {}
+            }
+        }
+        match &result {
+            Ok(Some(interval)) => {
+                for verdict in interval.verdicts(&source_bounds) {
+                    if verdict.verdict == Verdict::Falseticker {
+                        for observer in &mut self.observers {
+                            observer.on_source_rejected(&verdict);
+                        }
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(MarzulloError::IntervalInvariant(invariant)) => {
+                for observer in &mut self.observers {
+                    observer.on_invariant_violation(invariant);
+                }
+            }
+            Err(_) => {}
+        }
+        for observer in &mut self.observers {
+            observer.on_result(&result);
+        }
+        result
+    }
+
+    /// Selects as `select`, and reports the outcome with the options it ran with.
+    pub fn report(
+        &mut self,
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Report<T>, MarzulloError<T>> {
+        let options = self.options();
+        let interval = self.select(source_bounds.clone())?;
+        Ok(Report::new(&source_bounds, interval, options))
+    }
+}
+
+/// Intersects `interval` with `[lower, upper]`, with the sources consistent with the intersection,
+/// or returns `None` if it is empty.
+fn refine<T: Value>(
+    (lower, upper): (T, T),
+    interval: &Interval<T>,
+    source_bounds: &[SourceBound<T>],
+) -> Option<Interval<T>> {
+    let lower = lower.max(interval.lower_bound.clone());
+    let upper = upper.min(interval.upper_bound.clone());
+    if lower > upper {
+        return None;
+    }
+    let sources = source_intervals(source_bounds);
+    let mut refined = Interval {
+        lower_bound: lower,
+        upper_bound: upper,
+        sources_true: 0,
+        sources_false: 0,
+    };
+    let support = sources
+        .iter()
+        .filter(|(_, lower, upper)| refined.is_contained_by(lower, upper))
+        .count();
+    refined.sources_true = support as u8;
+    refined.sources_false = (sources.len() - support) as u8;
+    Some(refined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[derive(Default)]
+    struct Events {
+        results: usize,
+        rejected: Vec<u8>,
+    }
+
+    struct Recorder(Rc<RefCell<Events>>);
+
+    impl Observer for Recorder {
+        fn on_result(&mut self, _result: &Result<Option<Interval>, MarzulloError>) {
+            self.0.borrow_mut().results += 1;
+        }
+
+        fn on_source_rejected(&mut self, verdict: &SourceVerdict) {
+            self.0.borrow_mut().rejected.push(verdict.source);
+        }
+    }
+
+    #[test]
+    fn test_engine_notifies_observers() {
+        let events = Rc::new(RefCell::new(Events::default()));
+        let mut engine = Engine::new();
+        engine.add_observer(Recorder(Rc::clone(&events)));
+
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        source_bounds.extend(SourceBound::pair(2, 14, 15));
+        let interval = engine.select(source_bounds).unwrap().unwrap();
+        assert_eq!(interval.sources_false(), 1);
+        assert!(engine.select(Vec::new()).unwrap().is_none());
+
+        let events = events.borrow();
+        assert_eq!(events.results, 2);
+        assert_eq!(events.rejected, [2]);
+    }
+
+    #[test]
+    fn test_engine_refinement() {
+        let select = |engine: &mut Engine, lower, upper| {
+            let interval = engine
+                .select(Vec::from(SourceBound::pair(0, lower, upper)))
+                .unwrap()
+                .unwrap();
+            (interval.lower_bound(), interval.upper_bound())
+        };
+        let mut engine = Engine::new().with_refinement(|interval: &Interval| {
+            (interval.lower_bound() - 10, interval.upper_bound() + 10)
+        });
+        assert_eq!(select(&mut engine, 0, 100), (0, 100));
+        assert_eq!(select(&mut engine, 20, 140), (20, 110));
+        assert_eq!(select(&mut engine, 0, 100), (10, 100));
+        // The source moved away, so the interval starts over:
+        assert_eq!(select(&mut engine, 500, 600), (500, 600));
+    }
+
+    #[test]
+    fn test_engine_stickiness() {
+        // Two pairs of sources agree on disjoint intervals of nearly the same width:
+        let bounds = |width: i64| {
+            let mut source_bounds: Vec<SourceBound> = Vec::new();
+            source_bounds.extend(SourceBound::pair(0, 0, 1_000));
+            source_bounds.extend(SourceBound::pair(1, 900, 2_000));
+            source_bounds.extend(SourceBound::pair(2, 5_000, 6_000));
+            source_bounds.extend(SourceBound::pair(3, 6_000 - width, 7_000));
+            source_bounds
+        };
+        let lower =
+            |engine: &mut Engine, width| engine.select(bounds(width)).unwrap().unwrap().lower_bound;
+
+        let mut engine = Engine::new();
+        assert_eq!(lower(&mut engine, 101), 900);
+        assert_eq!(lower(&mut engine, 99), 5_901);
+
+        let mut engine = Engine::new().with_stickiness(Stickiness::default());
+        assert_eq!(lower(&mut engine, 101), 900);
+        let interval = engine.select(bounds(99)).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.sources_true()), (900, 2));
+        // A much narrower interval replaces it:
+        assert_eq!(lower(&mut engine, 50), 5_950);
+    }
+}
@@ -0,0 +1,90 @@
+//! The estimate most consumers of a selection want: the offset to apply to the local clock, and
+//! how far to trust it, rather than the chosen interval itself.
+
+use crate::Interval;
+
+/// An offset of the local clock, in nanoseconds, within `max_error` of the true offset.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockEstimate {
+    /// The midpoint of the chosen interval.
+    pub offset: i64,
+    /// Half the width of the chosen interval, rounded up, plus the dispersion propagated to it.
+    pub max_error: u64,
+    /// The fraction of the sources that overlap the chosen interval, in `[0, 1]`.
+    pub confidence: f64,
+    /// The local time of the selection, in nanoseconds.
+    pub computed_at: i64,
+}
+
+impl ClockEstimate {
+    /// Returns the estimate of the chosen interval, with `dispersion` added to its error, e.g. the
+    /// dispersion accrued since the samples were taken.
+    pub fn new(interval: &Interval, dispersion: u64, computed_at: i64) -> ClockEstimate {
+        let offset = (interval.lower_bound as i128 + interval.upper_bound as i128).div_euclid(2);
+        let sources = interval.sources_true as u32 + interval.sources_false as u32;
+        ClockEstimate {
+            offset: offset as i64,
+            // The midpoint rounds down, so the upper bound is the further from it:
+            max_error: interval.width().div_ceil(2).saturating_add(dispersion),
+            confidence: if sources == 0 {
+                0.0
+            } else {
+                interval.sources_true as f64 / sources as f64
+            },
+            computed_at,
+        }
+    }
+
+    /// Returns the lowest and highest offset within the error, saturating at the bounds of `i64`.
+    pub fn bounds(&self) -> (i64, i64) {
+        (
+            self.offset.saturating_sub_unsigned(self.max_error),
+            self.offset.saturating_add_unsigned(self.max_error),
+        )
+    }
+}
+
+impl Interval {
+    /// Returns the estimate of the interval, as `ClockEstimate::new`.
+    pub fn estimate(&self, dispersion: u64, computed_at: i64) -> ClockEstimate {
+        ClockEstimate::new(self, dispersion, computed_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_clock_estimate() {
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 8, 12), (1, 9, 13), (2, 10, 14), (3, 40, 60)]);
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        let estimate = interval.estimate(3, 1_000);
+        assert_eq!(
+            estimate,
+            ClockEstimate {
+                offset: 11,
+                max_error: 4,
+                confidence: 0.75,
+                computed_at: 1_000,
+            }
+        );
+        assert_eq!(estimate.bounds(), (7, 15));
+
+        // The midpoint of an interval over the whole domain does not overflow:
+        let interval =
+            Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, i64::MIN, i64::MAX)))
+                .unwrap()
+                .unwrap();
+        let estimate = interval.estimate(u64::MAX, 0);
+        assert_eq!((estimate.offset, estimate.max_error), (-1, u64::MAX));
+        assert_eq!(estimate.bounds(), (i64::MIN, i64::MAX));
+    }
+}
@@ -0,0 +1,113 @@
+//! Sensor fusion with asymmetric error bars: every sensor reads an estimate that may be off by up
+//! to some amount below and some other amount above, e.g. a rangefinder reading `+3/-1`. The fused
+//! range is the smallest range consistent with the most sensors, in the unit of the readings, as
+//! Marzullo's algorithm selects for clocks.
+
+use alloc::vec::Vec;
+
+use crate::{Interval, MarzulloError, SourceBound, Verdict};
+
+/// The reading of one sensor.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Reading {
+    pub sensor: u8,
+    pub estimate: i64,
+    /// How far below the estimate the true value may be.
+    pub minus_error: u64,
+    /// How far above the estimate the true value may be.
+    pub plus_error: u64,
+}
+
+impl Reading {
+    pub fn new(sensor: u8, estimate: i64, minus_error: u64, plus_error: u64) -> Reading {
+        Reading {
+            sensor,
+            estimate,
+            minus_error,
+            plus_error,
+        }
+    }
+
+    /// Returns the bounds of the range the true value may be in, saturated at the limits of `i64`,
+    /// which are then the unbounded sentinels.
+    pub fn bounds(&self) -> [SourceBound; 2] {
+        SourceBound::pair(
+            self.sensor,
+            self.estimate.saturating_sub_unsigned(self.minus_error),
+            self.estimate.saturating_add_unsigned(self.plus_error),
+        )
+    }
+}
+
+/// The fused range, and the sensors that agree and disagree with it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fused {
+    pub lower: i64,
+    pub upper: i64,
+    /// The sensors whose ranges contain the fused range, in order.
+    pub supporting: Vec<u8>,
+    /// The sensors whose ranges do not contain it, in order.
+    pub outliers: Vec<u8>,
+}
+
+impl Fused {
+    /// The middle of the fused range, rounded toward negative infinity, as a single estimate.
+    pub fn midpoint(&self) -> i64 {
+        (self.lower as i128 + self.upper as i128).div_euclid(2) as i64
+    }
+}
+
+/// Fuses the readings, or returns `None` if there are none. A sensor with several readings counts
+/// with its last one.
+pub fn fuse(readings: &[Reading]) -> Result<Option<Fused>, MarzulloError> {
+    let mut latest: Vec<Reading> = Vec::with_capacity(readings.len());
+    for reading in readings {
+        latest.retain(|other| other.sensor != reading.sensor);
+        latest.push(*reading);
+    }
+    let source_bounds: Vec<SourceBound> = latest.iter().flat_map(Reading::bounds).collect();
+    let Some(interval) = Interval::try_from_source_bounds(source_bounds.clone())? else {
+        return Ok(None);
+    };
+    let (supporting, outliers): (Vec<_>, Vec<_>) = interval
+        .verdicts(&source_bounds)
+        .into_iter()
+        .partition(|verdict| verdict.verdict == Verdict::Truechimer);
+    Ok(Some(Fused {
+        lower: interval.lower_bound(),
+        upper: interval.upper_bound(),
+        supporting: supporting.iter().map(|verdict| verdict.source).collect(),
+        outliers: outliers.iter().map(|verdict| verdict.source).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse() {
+        assert_eq!(fuse(&[]), Ok(None));
+        // Ranges in millimetres: a lidar at 1000 +3/-1, a sonar that reads long at 1005 +2/-8, a
+        // camera at 998 +10/-10, and a sonar reflection at 400:
+        let fused = fuse(&[
+            Reading::new(0, 1_000, 1, 3),
+            Reading::new(1, 1_005, 8, 2),
+            Reading::new(2, 998, 10, 10),
+            Reading::new(3, 400, 5, 5),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!((fused.lower, fused.upper), (999, 1_003));
+        assert_eq!(fused.midpoint(), 1_001);
+        assert_eq!(fused.supporting, [0, 1, 2]);
+        assert_eq!(fused.outliers, [3]);
+        assert_eq!(
+            Reading::new(0, i64::MAX - 1, 0, 5).bounds()[1].value(),
+            i64::MAX
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Two-level selection: Marzullo's algorithm runs within every group of sources, e.g. the servers
+//! of one data center, and then across the intervals of the groups, so that a site with many
+//! sources counts as one and cannot outvote the others. Either level may require a quorum of
+//! supporting sources, or of supporting groups.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::quorum::QuorumError;
+use crate::{Interval, MarzulloError, SourceBound};
+
+/// Why a group or the selection across groups failed.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum HierarchyError {
+    Selection(MarzulloError),
+    /// Fewer sources, or groups, than the quorum of the level support the interval.
+    Quorum(QuorumError),
+}
+
+impl core::fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            HierarchyError::Selection(error) => write!(f, "{}", error),
+            HierarchyError::Quorum(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for HierarchyError {}
+
+impl From<MarzulloError> for HierarchyError {
+    fn from(error: MarzulloError) -> HierarchyError {
+        HierarchyError::Selection(error)
+    }
+}
+
+/// The selection within one group.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct GroupSelection {
+    pub name: String,
+    /// The interval of the group, or `None` if it has no sources. Only groups with an interval
+    /// take part in the selection across groups.
+    pub result: Result<Option<Interval>, HierarchyError>,
+}
+
+/// The selections within every group, in the order of the groups, and across them.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HierarchicalSelection {
+    pub groups: Vec<GroupSelection>,
+    /// The interval across the groups, whose sources are the groups by their index, or `None` if
+    /// no group has an interval.
+    pub interval: Option<Interval>,
+}
+
+/// Selects within groups, and then across them.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct Hierarchy {
+    group_quorum: usize,
+    quorum: usize,
+}
+
+/// Checks that at least `quorum` sources support the interval.
+fn check(interval: Option<Interval>, quorum: usize) -> Result<Option<Interval>, HierarchyError> {
+    match interval {
+        Some(interval) if (interval.sources_true() as usize) < quorum => {
+            Err(HierarchyError::Quorum(QuorumError::NoQuorum {
+                support: interval.sources_true(),
+                quorum,
+            }))
+        }
+        interval => Ok(interval),
+    }
+}
+
+impl Hierarchy {
+    pub fn new() -> Hierarchy {
+        Hierarchy::default()
+    }
+
+    /// Requires the interval of every group to be supported by at least `quorum` of its sources,
+    /// or leaves the group out of the selection across groups.
+    pub fn with_group_quorum(mut self, quorum: usize) -> Hierarchy {
+        self.group_quorum = quorum;
+        self
+    }
+
+    /// Requires the interval across groups to be supported by at least `quorum` groups.
+    pub fn with_quorum(mut self, quorum: usize) -> Hierarchy {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Selects within every group of bounds, by its name, and across the groups.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than 256 groups.
+    pub fn select<N: Into<String>>(
+        &self,
+        groups: impl IntoIterator<Item = (N, Vec<SourceBound>)>,
+    ) -> Result<HierarchicalSelection, HierarchyError> {
+        let groups: Vec<GroupSelection> = groups
+            .into_iter()
+            .map(|(name, source_bounds)| GroupSelection {
+                name: name.into(),
+                result: Interval::try_from_source_bounds(source_bounds)
+                    .map_err(HierarchyError::from)
+                    .and_then(|interval| check(interval, self.group_quorum)),
+            })
+            .collect();
+        assert!(groups.len() <= 256, "at most 256 groups");
+        let source_bounds: Vec<SourceBound> = groups
+            .iter()
+            .enumerate()
+            .filter_map(|(index, group)| match &group.result {
+                Ok(Some(interval)) => Some(SourceBound::pair(
+                    index as u8,
+                    interval.lower_bound(),
+                    interval.upper_bound(),
+                )),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let interval = check(
+            Interval::try_from_source_bounds(source_bounds)?,
+            self.quorum,
+        )?;
+        Ok(HierarchicalSelection { groups, interval })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_hierarchy() {
+        // A site with four sources that agree among themselves, and two sites of two that agree
+        // with each other. Selecting over all sources at once, the first site would win:
+        let groups = [
+            (
+                "a",
+                bounds(&[(0, 100, 110), (1, 101, 111), (2, 102, 112), (3, 103, 113)]),
+            ),
+            ("b", bounds(&[(0, 0, 10), (1, 2, 12)])),
+            ("c", bounds(&[(0, 5, 15), (1, 4, 14)])),
+            ("d", bounds(&[(0, 500, 510)])),
+        ];
+        let selection = Hierarchy::new()
+            .with_group_quorum(2)
+            .select(groups.clone())
+            .unwrap();
+        let interval = selection.interval.unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (5, 10));
+        assert_eq!((interval.sources_true(), interval.sources_false()), (2, 1));
+        assert_eq!(
+            selection.groups[3].result,
+            Err(HierarchyError::Quorum(QuorumError::NoQuorum {
+                support: 1,
+                quorum: 2,
+            }))
+        );
+
+        assert_eq!(
+            Hierarchy::new().with_quorum(3).select(groups).err(),
+            Some(HierarchyError::Quorum(QuorumError::NoQuorum {
+                support: 2,
+                quorum: 3,
+            }))
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use ::chrono::{DateTime, Duration, Utc};
+
+use super::ConversionError;
+use crate::{Interval, SourceBound};
+
+/// Converts a `DateTime<Utc>` to nanoseconds since the Unix epoch.
+pub fn date_time_to_nanos(time: DateTime<Utc>) -> Result<i64, ConversionError> {
+    time.timestamp_nanos_opt()
+        .ok_or(ConversionError::OutOfRange)
+}
+
+/// Converts nanoseconds since the Unix epoch to a `DateTime<Utc>`. Every `i64` is representable.
+pub fn nanos_to_date_time(nanos: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(nanos)
+}
+
+fn duration_to_nanos(duration: Duration) -> Result<i64, ConversionError> {
+    let nanos = duration
+        .num_nanoseconds()
+        .ok_or(ConversionError::OutOfRange)?;
+    if nanos < 0 {
+        return Err(ConversionError::NegativeDuration);
+    }
+    Ok(nanos)
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of a source's interval given as a pair of `DateTime<Utc>`s.
+    pub fn try_from_date_times(
+        source: u8,
+        lower: DateTime<Utc>,
+        upper: DateTime<Utc>,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        Ok(SourceBound::pair(
+            source,
+            date_time_to_nanos(lower)?,
+            date_time_to_nanos(upper)?,
+        ))
+    }
+
+    /// Returns the lower and upper bound of a source's interval given as a center and an
+    /// uncertainty, i.e. `[center - uncertainty, center + uncertainty]`.
+    pub fn try_from_date_time_uncertainty(
+        source: u8,
+        center: DateTime<Utc>,
+        uncertainty: Duration,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        let center = date_time_to_nanos(center)?;
+        let uncertainty = duration_to_nanos(uncertainty)?;
+        Ok(SourceBound::pair(
+            source,
+            center
+                .checked_sub(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            center
+                .checked_add(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+impl Interval {
+    /// The lower bound as a `DateTime<Utc>`, or `None` if the interval is unbounded from below.
+    pub fn lower_bound_date_time(&self) -> Option<DateTime<Utc>> {
+        if self.is_lower_unbounded() {
+            return None;
+        }
+        Some(nanos_to_date_time(self.lower_bound))
+    }
+
+    /// The upper bound as a `DateTime<Utc>`, or `None` if the interval is unbounded from above.
+    pub fn upper_bound_date_time(&self) -> Option<DateTime<Utc>> {
+        if self.is_upper_unbounded() {
+            return None;
+        }
+        Some(nanos_to_date_time(self.upper_bound))
+    }
+
+    /// The width of the interval as a `chrono::Duration`.
+    pub fn width_chrono_duration(&self) -> Duration {
+        let width = self.width();
+        Duration::seconds((width / 1_000_000_000) as i64)
+            + Duration::nanoseconds((width % 1_000_000_000) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_time_nanos_round_trip() {
+        for nanos in [0, -1, 1_700_000_000_123_456_789, i64::MIN, i64::MAX] {
+            assert_eq!(
+                date_time_to_nanos(nanos_to_date_time(nanos)).unwrap(),
+                nanos
+            );
+        }
+        assert_eq!(
+            date_time_to_nanos(DateTime::<Utc>::MAX_UTC),
+            Err(ConversionError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_interval_from_date_times() {
+        let epoch = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(
+            SourceBound::try_from_date_time_uncertainty(0, epoch, Duration::milliseconds(10))
+                .unwrap(),
+        );
+        source_bounds.extend(
+            SourceBound::try_from_date_times(
+                1,
+                epoch + Duration::milliseconds(5),
+                epoch + Duration::milliseconds(20),
+            )
+            .unwrap(),
+        );
+
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            interval.lower_bound_date_time(),
+            Some(epoch + Duration::milliseconds(5))
+        );
+        assert_eq!(
+            interval.upper_bound_date_time(),
+            Some(epoch + Duration::milliseconds(10))
+        );
+        assert_eq!(interval.width_chrono_duration(), Duration::milliseconds(5));
+
+        assert_eq!(
+            SourceBound::try_from_date_time_uncertainty(0, epoch, Duration::milliseconds(-1)),
+            Err(ConversionError::NegativeDuration)
+        );
+    }
+}
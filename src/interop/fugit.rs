@@ -0,0 +1,208 @@
+//! Conversions for the `fugit` instant and duration types used in embedded firmware. Instants are
+//! relative to the epoch of the timer that produced them (usually boot), so all sources fed to the
+//! algorithm should share that timer. Tick rates that do not divide a nanosecond evenly (e.g. a
+//! 32.768 kHz RTC) are rounded outwards, so that converted intervals always contain the originals.
+
+use ::fugit::{Duration, Instant};
+
+use super::ConversionError;
+use crate::{Interval, SourceBound};
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// A `fugit` instant or duration, of any tick rate, viewed through its tick count.
+pub trait Ticks: Sized {
+    /// The tick period is `NOM / DENOM` seconds.
+    const NOM: u32;
+    const DENOM: u32;
+
+    fn ticks_u64(&self) -> u64;
+
+    /// Returns `None` if the ticks do not fit in the underlying representation.
+    fn from_ticks_u64(ticks: u64) -> Option<Self>;
+}
+
+macro_rules! impl_ticks {
+    ($($ty:ident<$repr:ty>),*) => {
+        $(
+            impl<const NOM: u32, const DENOM: u32> Ticks for $ty<$repr, NOM, DENOM> {
+                const NOM: u32 = NOM;
+                const DENOM: u32 = DENOM;
+
+                fn ticks_u64(&self) -> u64 {
+                    self.ticks() as u64
+                }
+
+                fn from_ticks_u64(ticks: u64) -> Option<Self> {
+                    <$repr>::try_from(ticks).ok().map(Self::from_ticks)
+                }
+            }
+        )*
+    };
+}
+
+impl_ticks!(Instant<u32>, Instant<u64>, Duration<u32>, Duration<u64>);
+
+/// Converts a tick count to nanoseconds, rounding up or down when the tick period is not a whole
+/// number of nanoseconds.
+fn ticks_to_nanos<T: Ticks>(value: &T, round_up: bool) -> Result<i64, ConversionError> {
+    let numerator = value.ticks_u64() as u128 * T::NOM as u128 * NANOS_PER_SECOND;
+    let denominator = T::DENOM as u128;
+    let nanos = if round_up {
+        numerator.div_ceil(denominator)
+    } else {
+        numerator / denominator
+    };
+    i64::try_from(nanos).map_err(|_| ConversionError::OutOfRange)
+}
+
+/// Converts nanoseconds to a tick count, rounding up or down when the tick period is not a whole
+/// number of nanoseconds.
+fn nanos_to_ticks<T: Ticks>(nanos: u64, round_up: bool) -> Option<T> {
+    let numerator = nanos as u128 * T::DENOM as u128;
+    let denominator = T::NOM as u128 * NANOS_PER_SECOND;
+    let ticks = if round_up {
+        numerator.div_ceil(denominator)
+    } else {
+        numerator / denominator
+    };
+    T::from_ticks_u64(u64::try_from(ticks).ok()?)
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of a source's interval given as a pair of `fugit`
+    /// instants.
+    pub fn try_from_fugit_instants<I: Ticks>(
+        source: u8,
+        lower: I,
+        upper: I,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        Ok(SourceBound::pair(
+            source,
+            ticks_to_nanos(&lower, false)?,
+            ticks_to_nanos(&upper, true)?,
+        ))
+    }
+
+    /// Returns the lower and upper bound of a source's interval given as a center instant and an
+    /// uncertainty duration, i.e. `[center - uncertainty, center + uncertainty]`. The two may have
+    /// different tick rates.
+    pub fn try_from_fugit_uncertainty<I: Ticks, D: Ticks>(
+        source: u8,
+        center: I,
+        uncertainty: D,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        let uncertainty = ticks_to_nanos(&uncertainty, true)?;
+        Ok(SourceBound::pair(
+            source,
+            ticks_to_nanos(&center, false)?
+                .checked_sub(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            ticks_to_nanos(&center, true)?
+                .checked_add(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+impl Interval {
+    /// The lower bound as a `fugit` instant, rounded down to a whole tick. Returns `None` if the
+    /// interval is unbounded from below or the bound precedes the timer's epoch.
+    pub fn lower_bound_fugit<I: Ticks>(&self) -> Option<I> {
+        if self.is_lower_unbounded() {
+            return None;
+        }
+        nanos_to_ticks(u64::try_from(self.lower_bound).ok()?, false)
+    }
+
+    /// The upper bound as a `fugit` instant, rounded up to a whole tick. Returns `None` if the
+    /// interval is unbounded from above or the bound precedes the timer's epoch.
+    pub fn upper_bound_fugit<I: Ticks>(&self) -> Option<I> {
+        if self.is_upper_unbounded() {
+            return None;
+        }
+        nanos_to_ticks(u64::try_from(self.upper_bound).ok()?, true)
+    }
+
+    /// The width of the interval as a `fugit` duration, rounded up to a whole tick. Returns `None`
+    /// if the width does not fit in the duration.
+    pub fn width_fugit<D: Ticks>(&self) -> Option<D> {
+        nanos_to_ticks(self.width(), true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    type Micros = Instant<u64, 1, 1_000_000>;
+    /// A 32.768 kHz real-time clock, whose tick is not a whole number of nanoseconds.
+    type RtcInstant = Instant<u32, 1, 32_768>;
+    type RtcDuration = Duration<u32, 1, 32_768>;
+
+    #[test]
+    fn test_interval_from_fugit_instants() {
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(
+            SourceBound::try_from_fugit_instants(
+                0,
+                Micros::from_ticks(1_000),
+                Micros::from_ticks(3_000),
+            )
+            .unwrap(),
+        );
+        source_bounds.extend(
+            SourceBound::try_from_fugit_uncertainty(
+                1,
+                Micros::from_ticks(3_000),
+                Duration::<u32, 1, 1_000>::from_ticks(1),
+            )
+            .unwrap(),
+        );
+
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), 2_000_000);
+        assert_eq!(interval.upper_bound(), 3_000_000);
+        assert_eq!(
+            interval.lower_bound_fugit(),
+            Some(Micros::from_ticks(2_000))
+        );
+        assert_eq!(
+            interval.upper_bound_fugit(),
+            Some(Micros::from_ticks(3_000))
+        );
+        assert_eq!(
+            interval.width_fugit(),
+            Some(Duration::<u64, 1, 1_000_000>::from_ticks(1_000))
+        );
+    }
+
+    #[test]
+    fn test_fugit_odd_tick_rates_round_outwards() {
+        let source_bounds = SourceBound::try_from_fugit_instants(
+            0,
+            RtcInstant::from_ticks(1),
+            RtcInstant::from_ticks(2),
+        )
+        .unwrap();
+        // One tick is 30517.578125ns:
+        assert_eq!(source_bounds[0].value(), 30_517);
+        assert_eq!(source_bounds[1].value(), 61_036);
+
+        let interval = Interval::try_from_source_bounds(source_bounds.to_vec())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            interval.lower_bound_fugit(),
+            Some(RtcInstant::from_ticks(0))
+        );
+        assert_eq!(
+            interval.upper_bound_fugit(),
+            Some(RtcInstant::from_ticks(3))
+        );
+        assert_eq!(interval.width_fugit(), Some(RtcDuration::from_ticks(2)));
+    }
+}
@@ -0,0 +1,40 @@
+//! Conversions between the crate's `i64` nanosecond domain and the time types of the standard
+//! library and other crates. Values are nanoseconds relative to the Unix epoch, and the
+//! `SourceBound::UNBOUNDED_LOWER` and `SourceBound::UNBOUNDED_UPPER` sentinels are reported as
+//! missing bounds rather than as (very distant) points in time.
+
+#[cfg(feature = "chrono")]
+pub mod chrono;
+#[cfg(feature = "fugit")]
+pub mod fugit;
+pub mod nmea;
+pub mod ntp;
+pub mod ptp;
+pub mod roughtime;
+#[cfg(feature = "std")]
+pub mod std_time;
+#[cfg(feature = "time")]
+pub mod time;
+
+/// A time value that cannot be represented in the target domain.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConversionError {
+    /// The value does not fit in `i64` nanoseconds since the Unix epoch, or the nanoseconds do not
+    /// fit in the target type.
+    OutOfRange,
+    /// An uncertainty or width was given as a negative duration.
+    NegativeDuration,
+}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ConversionError::OutOfRange => write!(f, "time value out of range"),
+            ConversionError::NegativeDuration => write!(f, "duration should not be negative"),
+        }
+    }
+}
+
+impl core::error::Error for ConversionError {}
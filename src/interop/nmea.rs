@@ -0,0 +1,240 @@
+//! Bounds from the time sentences of a GPS receiver, so that the receiver can participate as a
+//! source. The `RMC` and `ZDA` sentences of any talker (`$GPRMC`, `$GNZDA`, ...) are supported.
+//!
+//! A receiver emits the sentence for a second some time after that second starts, and that latency
+//! is only known approximately. The bounds are on the time at which the sentence was received:
+//! `[time + latency - uncertainty, time + latency + uncertainty]`, where `time` is the UTC time in
+//! the sentence.
+
+use super::ConversionError;
+use crate::SourceBound;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NmeaError {
+    /// The sentence does not have the fields of its type, or a field cannot be parsed.
+    Malformed,
+    /// The checksum after `*` does not match the sentence.
+    Checksum,
+    /// The sentence is neither `RMC` nor `ZDA`.
+    UnsupportedSentence,
+    /// The receiver reports that it has no valid fix, so its time is not trustworthy.
+    NoFix,
+    Conversion(ConversionError),
+}
+
+impl core::fmt::Display for NmeaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            NmeaError::Malformed => write!(f, "malformed NMEA sentence"),
+            NmeaError::Checksum => write!(f, "NMEA checksum mismatch"),
+            NmeaError::UnsupportedSentence => write!(f, "unsupported NMEA sentence"),
+            NmeaError::NoFix => write!(f, "receiver has no valid fix"),
+            NmeaError::Conversion(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for NmeaError {}
+
+impl From<ConversionError> for NmeaError {
+    fn from(error: ConversionError) -> NmeaError {
+        NmeaError::Conversion(error)
+    }
+}
+
+/// The latency of a receiver from the start of a second to the reception of its sentence, in
+/// nanoseconds.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReceiverModel {
+    pub latency: i64,
+    /// The maximum error of `latency`, in either direction.
+    pub uncertainty: i64,
+}
+
+fn digits(text: &str) -> Result<u32, NmeaError> {
+    if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(NmeaError::Malformed);
+    }
+    text.parse().map_err(|_| NmeaError::Malformed)
+}
+
+/// Returns the number of days from the Unix epoch to a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parses `hhmmss[.fraction]` into nanoseconds since midnight.
+fn time_of_day(text: &str) -> Result<i64, NmeaError> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.len() != 6 || fraction.len() > 9 {
+        return Err(NmeaError::Malformed);
+    }
+    let (hours, minutes, seconds) = (
+        digits(&whole[..2])?,
+        digits(&whole[2..4])?,
+        digits(&whole[4..])?,
+    );
+    // Allow a leap second:
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return Err(NmeaError::Malformed);
+    }
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        digits(fraction)? as i64 * 10i64.pow(9 - fraction.len() as u32)
+    };
+    Ok(((hours as i64 * 60 + minutes as i64) * 60 + seconds as i64) * NANOS_PER_SECOND + nanos)
+}
+
+fn date(year: u32, month: u32, day: u32, time: i64) -> Result<i64, NmeaError> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(NmeaError::Malformed);
+    }
+    days_from_civil(year as i64, month, day)
+        .checked_mul(86_400 * NANOS_PER_SECOND)
+        .and_then(|nanos| nanos.checked_add(time))
+        .ok_or(NmeaError::Conversion(ConversionError::OutOfRange))
+}
+
+/// Parses the UTC time of an `RMC` or `ZDA` sentence into nanoseconds since the Unix epoch. The
+/// checksum is verified if present. `RMC` sentences have a two-digit year, taken to be in 2000-2099.
+pub fn parse_time(sentence: &str) -> Result<i64, NmeaError> {
+    let sentence = sentence.trim_end();
+    if !sentence.is_ascii() {
+        return Err(NmeaError::Malformed);
+    }
+    let body = sentence.strip_prefix('$').ok_or(NmeaError::Malformed)?;
+    let body = match body.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum, 16).map_err(|_| NmeaError::Malformed)?;
+            if body.bytes().fold(0, |sum, byte| sum ^ byte) != expected {
+                return Err(NmeaError::Checksum);
+            }
+            body
+        }
+        None => body,
+    };
+
+    let mut fields = body.split(',');
+    let address = fields.next().ok_or(NmeaError::Malformed)?;
+    if address.len() != 5 {
+        return Err(NmeaError::Malformed);
+    }
+    let mut field = || fields.next().ok_or(NmeaError::Malformed);
+    match &address[2..] {
+        "RMC" => {
+            let time = time_of_day(field()?)?;
+            if field()? != "A" {
+                return Err(NmeaError::NoFix);
+            }
+            // Latitude, its hemisphere, longitude, its hemisphere, speed, and course:
+            for _ in 0..6 {
+                field()?;
+            }
+            let day_month_year = field()?;
+            if day_month_year.len() != 6 {
+                return Err(NmeaError::Malformed);
+            }
+            date(
+                2000 + digits(&day_month_year[4..])?,
+                digits(&day_month_year[2..4])?,
+                digits(&day_month_year[..2])?,
+                time,
+            )
+        }
+        "ZDA" => {
+            let time = time_of_day(field()?)?;
+            let day = digits(field()?)?;
+            let month = digits(field()?)?;
+            let year = digits(field()?)?;
+            date(year, month, day, time)
+        }
+        _ => Err(NmeaError::UnsupportedSentence),
+    }
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of the time at which `sentence` was received.
+    pub fn try_from_nmea(
+        source: u8,
+        sentence: &str,
+        model: &ReceiverModel,
+    ) -> Result<[SourceBound; 2], NmeaError> {
+        if model.uncertainty < 0 {
+            return Err(ConversionError::NegativeDuration.into());
+        }
+        let received = parse_time(sentence)?
+            .checked_add(model.latency)
+            .ok_or(ConversionError::OutOfRange)?;
+        Ok(SourceBound::pair(
+            source,
+            received
+                .checked_sub(model.uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            received
+                .checked_add(model.uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-11-14T22:13:20Z:
+    const TIME: i64 = 1_700_000_000 * NANOS_PER_SECOND;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("$GPZDA,221320.50,14,11,2023,00,00*65"),
+            Ok(TIME + 500_000_000)
+        );
+        assert_eq!(
+            parse_time("$GNRMC,221320.00,A,4807.038,N,01131.000,E,0.0,0.0,141123,,,A"),
+            Ok(TIME)
+        );
+        assert_eq!(
+            parse_time("$GPZDA,221320.50,14,11,2023,00,00*66"),
+            Err(NmeaError::Checksum)
+        );
+        assert_eq!(
+            parse_time("$GPRMC,221320.00,V,,,,,,,141123,,,N"),
+            Err(NmeaError::NoFix)
+        );
+        assert_eq!(
+            parse_time("$GPGGA,221320.00,4807.038,N"),
+            Err(NmeaError::UnsupportedSentence)
+        );
+        assert_eq!(
+            parse_time("$GPZDA,2213,14,11,2023"),
+            Err(NmeaError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_bounds_from_nmea() {
+        let model = ReceiverModel {
+            latency: 120_000_000,
+            uncertainty: 30_000_000,
+        };
+        let [lower, upper] =
+            SourceBound::try_from_nmea(5, "$GPZDA,221320,14,11,2023,00,00", &model).unwrap();
+        assert_eq!(lower.source(), 5);
+        assert_eq!(lower.value(), TIME + 90_000_000);
+        assert_eq!(upper.value(), TIME + 150_000_000);
+    }
+}
@@ -0,0 +1,177 @@
+//! The NTP timestamp formats of RFC 5905. A 64-bit timestamp counts seconds since 1900 in a 32-bit
+//! field that wraps every 136 years (an era), plus a 32-bit binary fraction of a second. The 32-bit
+//! short format, used for root delay and dispersion, is a duration of 16-bit seconds and 16-bit
+//! fraction. Timestamps are truncated to the nanosecond, as their fraction is finer than that, and
+//! durations in the short format are rounded up so that they are never underestimated.
+
+use super::ConversionError;
+
+/// The seconds from the NTP prime epoch (1900-01-01) to the Unix epoch.
+pub const UNIX_EPOCH_SECONDS: i64 = 2_208_988_800;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const ERA_SECONDS: i128 = 1 << 32;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtpTimestamp {
+    pub seconds: u32,
+    pub fraction: u32,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtpShort {
+    pub seconds: u16,
+    pub fraction: u16,
+}
+
+impl NtpTimestamp {
+    /// Reads the timestamp from its wire representation, seconds in the high 32 bits.
+    pub fn from_bits(bits: u64) -> NtpTimestamp {
+        NtpTimestamp {
+            seconds: (bits >> 32) as u32,
+            fraction: bits as u32,
+        }
+    }
+
+    pub fn to_bits(self) -> u64 {
+        (self.seconds as u64) << 32 | self.fraction as u64
+    }
+
+    fn era_nanos(self, era: i32) -> i128 {
+        let seconds = era as i128 * ERA_SECONDS + self.seconds as i128 - UNIX_EPOCH_SECONDS as i128;
+        seconds * NANOS_PER_SECOND + ((self.fraction as i128 * NANOS_PER_SECOND) >> 32)
+    }
+
+    /// Converts the timestamp in the given era to nanoseconds since the Unix epoch. Era 0 starts in
+    /// 1900 and era 1 in 2036.
+    pub fn to_nanos(self, era: i32) -> Result<i64, ConversionError> {
+        i64::try_from(self.era_nanos(era)).map_err(|_| ConversionError::OutOfRange)
+    }
+
+    /// Converts the timestamp to nanoseconds since the Unix epoch, in the era that puts it closest
+    /// to `pivot` (also in nanoseconds since the Unix epoch), such as the local clock. This
+    /// resolves the era of timestamps within 68 years of the pivot.
+    pub fn to_nanos_near(self, pivot: i64) -> Result<i64, ConversionError> {
+        let (era, _) = NtpTimestamp::from_nanos(pivot);
+        (era - 1..=era + 1)
+            .map(|era| self.era_nanos(era))
+            .min_by_key(|nanos| nanos.abs_diff(pivot as i128))
+            .and_then(|nanos| i64::try_from(nanos).ok())
+            .ok_or(ConversionError::OutOfRange)
+    }
+
+    /// Converts nanoseconds since the Unix epoch to an era and a timestamp in that era.
+    pub fn from_nanos(nanos: i64) -> (i32, NtpTimestamp) {
+        let nanos = nanos as i128 + UNIX_EPOCH_SECONDS as i128 * NANOS_PER_SECOND;
+        let seconds = nanos.div_euclid(NANOS_PER_SECOND);
+        let subsecond = nanos.rem_euclid(NANOS_PER_SECOND);
+        (
+            seconds.div_euclid(ERA_SECONDS) as i32,
+            NtpTimestamp {
+                seconds: seconds.rem_euclid(ERA_SECONDS) as u32,
+                fraction: ((subsecond << 32) / NANOS_PER_SECOND) as u32,
+            },
+        )
+    }
+}
+
+impl NtpShort {
+    /// Reads the short format from its wire representation, seconds in the high 16 bits.
+    pub fn from_bits(bits: u32) -> NtpShort {
+        NtpShort {
+            seconds: (bits >> 16) as u16,
+            fraction: bits as u16,
+        }
+    }
+
+    pub fn to_bits(self) -> u32 {
+        (self.seconds as u32) << 16 | self.fraction as u32
+    }
+
+    /// Converts the duration to nanoseconds, rounding up so that a delay or dispersion is never
+    /// underestimated.
+    pub fn to_nanos(self) -> i64 {
+        (self.to_bits() as u64 * NANOS_PER_SECOND as u64).div_ceil(1 << 16) as i64
+    }
+
+    /// Converts a duration in nanoseconds to the short format, rounding up.
+    pub fn from_nanos(nanos: i64) -> Result<NtpShort, ConversionError> {
+        if nanos < 0 {
+            return Err(ConversionError::NegativeDuration);
+        }
+        let bits = (nanos as u128 * (1 << 16)).div_ceil(NANOS_PER_SECOND as u128);
+        u32::try_from(bits)
+            .map(NtpShort::from_bits)
+            .map_err(|_| ConversionError::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_timestamps() {
+        // The Unix epoch, then half a second after it:
+        let epoch = NtpTimestamp {
+            seconds: UNIX_EPOCH_SECONDS as u32,
+            fraction: 0,
+        };
+        assert_eq!(epoch.to_nanos(0), Ok(0));
+        assert_eq!(NtpTimestamp::from_nanos(0), (0, epoch));
+        let half = NtpTimestamp::from_bits(epoch.to_bits() | 1 << 31);
+        assert_eq!(half.to_nanos(0), Ok(500_000_000));
+        assert_eq!(NtpTimestamp::from_nanos(500_000_000), (0, half));
+
+        // 2036-02-07T06:28:16Z, the start of era 1:
+        let era_1 = (ERA_SECONDS as i64 - UNIX_EPOCH_SECONDS) * 1_000_000_000;
+        assert_eq!(
+            NtpTimestamp::from_nanos(era_1),
+            (1, NtpTimestamp::default())
+        );
+        assert_eq!(NtpTimestamp::default().to_nanos(1), Ok(era_1));
+        assert_eq!(
+            NtpTimestamp::default().to_nanos(0),
+            Ok(-UNIX_EPOCH_SECONDS * 1_000_000_000)
+        );
+        assert_eq!(
+            NtpTimestamp::default().to_nanos(-2),
+            Err(ConversionError::OutOfRange)
+        );
+
+        // A timestamp just after the rollover is resolved into era 1 by a pivot just before it:
+        let (_, after) = NtpTimestamp::from_nanos(era_1 + 5_000_000_000);
+        assert_eq!(after.seconds, 5);
+        assert_eq!(after.to_nanos_near(era_1 - 1), Ok(era_1 + 5_000_000_000));
+        let (_, before) = NtpTimestamp::from_nanos(era_1 - 5_000_000_000);
+        assert_eq!(before.to_nanos_near(era_1 + 1), Ok(era_1 - 5_000_000_000));
+
+        // Negative Unix times round trip:
+        let (era, ts) = NtpTimestamp::from_nanos(-1_500_000_000);
+        assert_eq!(ts.to_nanos(era), Ok(-1_500_000_000));
+    }
+
+    #[test]
+    fn test_ntp_short() {
+        assert_eq!(NtpShort::from_bits(0x0001_8000).to_nanos(), 1_500_000_000);
+        // One unit of the fraction is about 15.26us, and rounds up:
+        assert_eq!(NtpShort::from_bits(1).to_nanos(), 15_259);
+        assert_eq!(
+            NtpShort::from_nanos(1_500_000_000),
+            Ok(NtpShort::from_bits(0x0001_8000))
+        );
+        assert_eq!(NtpShort::from_nanos(1), Ok(NtpShort::from_bits(1)));
+        assert_eq!(
+            NtpShort::from_nanos(-1),
+            Err(ConversionError::NegativeDuration)
+        );
+        assert_eq!(
+            NtpShort::from_nanos(65_536_000_000_000),
+            Err(ConversionError::OutOfRange)
+        );
+    }
+}
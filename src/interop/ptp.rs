@@ -0,0 +1,167 @@
+//! The IEEE 1588 (PTP) timestamp and time interval formats. A timestamp counts 48-bit seconds and
+//! 32-bit nanoseconds since the PTP epoch, which is the Unix epoch on the TAI timescale, so it is
+//! ahead of UTC by the current UTC offset announced by the grandmaster (37 seconds since 2017). A
+//! time interval, the format of the correctionField and of offsets, is signed nanoseconds scaled by
+//! 2^16.
+
+use super::ConversionError;
+use crate::SourceBound;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PtpTimestamp {
+    /// Only the low 48 bits are used.
+    pub seconds: u64,
+    pub nanoseconds: u32,
+}
+
+/// Nanoseconds scaled by 2^16, e.g. `TimeInterval(0x2_8000)` is 2.5ns.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeInterval(pub i64);
+
+impl PtpTimestamp {
+    /// Reads the 80-bit timestamp from its wire representation.
+    pub fn from_bytes(bytes: [u8; 10]) -> PtpTimestamp {
+        let mut seconds = [0; 8];
+        seconds[2..].copy_from_slice(&bytes[..6]);
+        PtpTimestamp {
+            seconds: u64::from_be_bytes(seconds),
+            nanoseconds: u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; 10] {
+        let mut bytes = [0; 10];
+        bytes[..6].copy_from_slice(&self.seconds.to_be_bytes()[2..]);
+        bytes[6..].copy_from_slice(&self.nanoseconds.to_be_bytes());
+        bytes
+    }
+
+    /// Converts the timestamp to UTC nanoseconds since the Unix epoch, given the current UTC offset
+    /// in seconds.
+    pub fn to_nanos(self, utc_offset: i16) -> Result<i64, ConversionError> {
+        let seconds = (self.seconds & 0xffff_ffff_ffff) as i128 - utc_offset as i128;
+        i64::try_from(seconds * NANOS_PER_SECOND + self.nanoseconds as i128)
+            .map_err(|_| ConversionError::OutOfRange)
+    }
+
+    /// Converts UTC nanoseconds since the Unix epoch to a timestamp, given the current UTC offset
+    /// in seconds.
+    pub fn from_nanos(nanos: i64, utc_offset: i16) -> Result<PtpTimestamp, ConversionError> {
+        let nanos = nanos as i128 + utc_offset as i128 * NANOS_PER_SECOND;
+        if nanos < 0 {
+            return Err(ConversionError::OutOfRange);
+        }
+        Ok(PtpTimestamp {
+            seconds: (nanos / NANOS_PER_SECOND) as u64,
+            nanoseconds: (nanos % NANOS_PER_SECOND) as u32,
+        })
+    }
+}
+
+impl TimeInterval {
+    /// Converts to nanoseconds, rounding towards negative infinity.
+    pub fn to_nanos_floor(self) -> i64 {
+        self.0 >> 16
+    }
+
+    /// Converts to nanoseconds, rounding towards positive infinity.
+    pub fn to_nanos_ceil(self) -> i64 {
+        (self.0 >> 16) + (self.0 & 0xffff != 0) as i64
+    }
+
+    pub fn from_nanos(nanos: i64) -> Result<TimeInterval, ConversionError> {
+        nanos
+            .checked_mul(1 << 16)
+            .map(TimeInterval)
+            .ok_or(ConversionError::OutOfRange)
+    }
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of a PTP-derived source: `timestamp` corrected by
+    /// `correction` (e.g. the correctionField or a measured offset), plus or minus `uncertainty`.
+    /// The bounds are rounded outwards to the nanosecond.
+    pub fn try_from_ptp(
+        source: u8,
+        timestamp: PtpTimestamp,
+        utc_offset: i16,
+        correction: TimeInterval,
+        uncertainty: TimeInterval,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        if uncertainty.0 < 0 {
+            return Err(ConversionError::NegativeDuration);
+        }
+        let center = timestamp.to_nanos(utc_offset)?;
+        let lower = correction.0.checked_sub(uncertainty.0);
+        let upper = correction.0.checked_add(uncertainty.0);
+        let (Some(lower), Some(upper)) = (lower, upper) else {
+            return Err(ConversionError::OutOfRange);
+        };
+        Ok(SourceBound::pair(
+            source,
+            center
+                .checked_add(TimeInterval(lower).to_nanos_floor())
+                .ok_or(ConversionError::OutOfRange)?,
+            center
+                .checked_add(TimeInterval(upper).to_nanos_ceil())
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ptp_timestamps() {
+        let timestamp = PtpTimestamp {
+            seconds: 1_700_000_037,
+            nanoseconds: 250,
+        };
+        assert_eq!(PtpTimestamp::from_bytes(timestamp.to_bytes()), timestamp);
+        assert_eq!(
+            timestamp.to_bytes(),
+            [0, 0, 0x65, 0x53, 0xf1, 0x25, 0, 0, 0, 0xfa]
+        );
+        assert_eq!(timestamp.to_nanos(37), Ok(1_700_000_000_000_000_250));
+        assert_eq!(
+            PtpTimestamp::from_nanos(1_700_000_000_000_000_250, 37),
+            Ok(timestamp)
+        );
+        assert_eq!(
+            PtpTimestamp::from_nanos(-1, 0),
+            Err(ConversionError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_ptp_time_intervals() {
+        assert_eq!(TimeInterval(0x2_8000).to_nanos_floor(), 2);
+        assert_eq!(TimeInterval(0x2_8000).to_nanos_ceil(), 3);
+        assert_eq!(TimeInterval(-0x2_8000).to_nanos_floor(), -3);
+        assert_eq!(TimeInterval(-0x2_8000).to_nanos_ceil(), -2);
+        assert_eq!(TimeInterval::from_nanos(-2), Ok(TimeInterval(-0x2_0000)));
+
+        let timestamp = PtpTimestamp {
+            seconds: 100,
+            nanoseconds: 0,
+        };
+        let [lower, upper] = SourceBound::try_from_ptp(
+            0,
+            timestamp,
+            0,
+            TimeInterval(0x2_8000),
+            TimeInterval(0x1_0000),
+        )
+        .unwrap();
+        assert_eq!(lower.value(), 100_000_000_001);
+        assert_eq!(upper.value(), 100_000_000_004);
+    }
+}
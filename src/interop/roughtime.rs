@@ -0,0 +1,62 @@
+//! Bounds from Roughtime responses. A response has the server's midpoint (`MIDP`), in microseconds
+//! since the Unix epoch, and a radius (`RADI`) in microseconds, such that the true time was within
+//! `[midpoint - radius, midpoint + radius]` when the server signed the response.
+//!
+//! The response was signed some time within the round trip of the request, so by the time it is
+//! received, the true time is within `[midpoint - radius, midpoint + radius + round_trip]`.
+
+use super::ConversionError;
+use crate::SourceBound;
+
+impl SourceBound {
+    /// Returns the lower and upper bound of the time at which a Roughtime response was received,
+    /// given the midpoint and radius of the response and the round trip of the request in
+    /// nanoseconds.
+    pub fn try_from_roughtime(
+        source: u8,
+        midpoint: u64,
+        radius: u32,
+        round_trip: i64,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        if round_trip < 0 {
+            return Err(ConversionError::NegativeDuration);
+        }
+        let midpoint = i64::try_from(midpoint)
+            .ok()
+            .and_then(|micros| micros.checked_mul(1_000))
+            .ok_or(ConversionError::OutOfRange)?;
+        let radius = radius as i64 * 1_000;
+        Ok(SourceBound::pair(
+            source,
+            midpoint
+                .checked_sub(radius)
+                .ok_or(ConversionError::OutOfRange)?,
+            midpoint
+                .checked_add(radius)
+                .and_then(|upper| upper.checked_add(round_trip))
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_from_roughtime() {
+        let [lower, upper] =
+            SourceBound::try_from_roughtime(1, 1_700_000_000_000_000, 1_000_000, 25_000_000)
+                .unwrap();
+        assert_eq!(lower.value(), 1_699_999_999_000_000_000);
+        assert_eq!(upper.value(), 1_700_000_001_025_000_000);
+        assert_eq!(
+            SourceBound::try_from_roughtime(1, u64::MAX, 0, 0),
+            Err(ConversionError::OutOfRange)
+        );
+        assert_eq!(
+            SourceBound::try_from_roughtime(1, 0, 0, -1),
+            Err(ConversionError::NegativeDuration)
+        );
+    }
+}
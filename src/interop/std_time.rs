@@ -0,0 +1,145 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::ConversionError;
+use crate::{Interval, SourceBound};
+
+/// Converts a `SystemTime` to nanoseconds since the Unix epoch.
+pub fn system_time_to_nanos(time: SystemTime) -> Result<i64, ConversionError> {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_nanos()).map_err(|_| ConversionError::OutOfRange),
+        Err(before) => i64::try_from(before.duration().as_nanos())
+            .map(|nanos| -nanos)
+            .map_err(|_| ConversionError::OutOfRange),
+    }
+}
+
+/// Converts nanoseconds since the Unix epoch to a `SystemTime`.
+pub fn nanos_to_system_time(nanos: i64) -> Result<SystemTime, ConversionError> {
+    let offset = Duration::from_nanos(nanos.unsigned_abs());
+    if nanos >= 0 {
+        UNIX_EPOCH.checked_add(offset)
+    } else {
+        UNIX_EPOCH.checked_sub(offset)
+    }
+    .ok_or(ConversionError::OutOfRange)
+}
+
+fn duration_to_nanos(duration: Duration) -> Result<i64, ConversionError> {
+    i64::try_from(duration.as_nanos()).map_err(|_| ConversionError::OutOfRange)
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of a source's interval given as a pair of `SystemTime`s.
+    pub fn try_from_system_times(
+        source: u8,
+        lower: SystemTime,
+        upper: SystemTime,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        Ok(SourceBound::pair(
+            source,
+            system_time_to_nanos(lower)?,
+            system_time_to_nanos(upper)?,
+        ))
+    }
+
+    /// Returns the lower and upper bound of a source's interval given as a center and an
+    /// uncertainty, i.e. `[center - uncertainty, center + uncertainty]`.
+    pub fn try_from_system_time_uncertainty(
+        source: u8,
+        center: SystemTime,
+        uncertainty: Duration,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        let center = system_time_to_nanos(center)?;
+        let uncertainty = duration_to_nanos(uncertainty)?;
+        Ok(SourceBound::pair(
+            source,
+            center
+                .checked_sub(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            center
+                .checked_add(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+impl Interval {
+    /// The lower bound as a `SystemTime`, or `None` if the interval is unbounded from below.
+    pub fn lower_bound_system_time(&self) -> Option<SystemTime> {
+        if self.is_lower_unbounded() {
+            return None;
+        }
+        nanos_to_system_time(self.lower_bound).ok()
+    }
+
+    /// The upper bound as a `SystemTime`, or `None` if the interval is unbounded from above.
+    pub fn upper_bound_system_time(&self) -> Option<SystemTime> {
+        if self.is_upper_unbounded() {
+            return None;
+        }
+        nanos_to_system_time(self.upper_bound).ok()
+    }
+
+    /// The width of the interval as a `Duration`.
+    pub fn width_duration(&self) -> Duration {
+        Duration::from_nanos(self.width())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time_nanos_round_trip() {
+        for nanos in [0, 1, -1, 1_700_000_000_123_456_789, -86_400_000_000_000] {
+            let time = nanos_to_system_time(nanos).unwrap();
+            assert_eq!(system_time_to_nanos(time).unwrap(), nanos);
+        }
+    }
+
+    #[test]
+    fn test_interval_from_system_times() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(
+            SourceBound::try_from_system_time_uncertainty(0, epoch, Duration::from_millis(10))
+                .unwrap(),
+        );
+        source_bounds.extend(
+            SourceBound::try_from_system_times(
+                1,
+                epoch + Duration::from_millis(5),
+                epoch + Duration::from_millis(20),
+            )
+            .unwrap(),
+        );
+        source_bounds.extend(SourceBound::pair_optional(
+            2,
+            None,
+            Some(system_time_to_nanos(epoch).unwrap() + 8_000_000),
+        ));
+
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            interval.lower_bound_system_time(),
+            Some(epoch + Duration::from_millis(5))
+        );
+        assert_eq!(
+            interval.upper_bound_system_time(),
+            Some(epoch + Duration::from_millis(8))
+        );
+        assert_eq!(interval.width_duration(), Duration::from_millis(3));
+        assert_eq!(interval.sources_true(), 3);
+
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, None));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound_system_time(), None);
+        assert_eq!(interval.upper_bound_system_time(), None);
+    }
+}
@@ -0,0 +1,150 @@
+use ::time::{Duration, OffsetDateTime};
+
+use super::ConversionError;
+use crate::{Interval, SourceBound};
+
+/// Converts an `OffsetDateTime` to nanoseconds since the Unix epoch.
+pub fn offset_date_time_to_nanos(time: OffsetDateTime) -> Result<i64, ConversionError> {
+    i64::try_from(time.unix_timestamp_nanos()).map_err(|_| ConversionError::OutOfRange)
+}
+
+/// Converts nanoseconds since the Unix epoch to an `OffsetDateTime` in UTC. Every `i64` is
+/// representable.
+pub fn nanos_to_offset_date_time(nanos: i64) -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp_nanos(nanos as i128)
+        .expect("i64 nanoseconds are within the range of OffsetDateTime")
+}
+
+fn duration_to_nanos(duration: Duration) -> Result<i64, ConversionError> {
+    if duration.is_negative() {
+        return Err(ConversionError::NegativeDuration);
+    }
+    i64::try_from(duration.whole_nanoseconds()).map_err(|_| ConversionError::OutOfRange)
+}
+
+impl SourceBound {
+    /// Returns the lower and upper bound of a source's interval given as a pair of
+    /// `OffsetDateTime`s.
+    pub fn try_from_offset_date_times(
+        source: u8,
+        lower: OffsetDateTime,
+        upper: OffsetDateTime,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        Ok(SourceBound::pair(
+            source,
+            offset_date_time_to_nanos(lower)?,
+            offset_date_time_to_nanos(upper)?,
+        ))
+    }
+
+    /// Returns the lower and upper bound of a source's interval given as a center and an
+    /// uncertainty, i.e. `[center - uncertainty, center + uncertainty]`.
+    pub fn try_from_offset_date_time_uncertainty(
+        source: u8,
+        center: OffsetDateTime,
+        uncertainty: Duration,
+    ) -> Result<[SourceBound; 2], ConversionError> {
+        let center = offset_date_time_to_nanos(center)?;
+        let uncertainty = duration_to_nanos(uncertainty)?;
+        Ok(SourceBound::pair(
+            source,
+            center
+                .checked_sub(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            center
+                .checked_add(uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+impl Interval {
+    /// The lower bound as an `OffsetDateTime`, or `None` if the interval is unbounded from below.
+    pub fn lower_bound_offset_date_time(&self) -> Option<OffsetDateTime> {
+        if self.is_lower_unbounded() {
+            return None;
+        }
+        Some(nanos_to_offset_date_time(self.lower_bound))
+    }
+
+    /// The upper bound as an `OffsetDateTime`, or `None` if the interval is unbounded from above.
+    pub fn upper_bound_offset_date_time(&self) -> Option<OffsetDateTime> {
+        if self.is_upper_unbounded() {
+            return None;
+        }
+        Some(nanos_to_offset_date_time(self.upper_bound))
+    }
+
+    /// The width of the interval as a `time::Duration`.
+    pub fn width_time_duration(&self) -> Duration {
+        let width = self.width();
+        Duration::new(
+            (width / 1_000_000_000) as i64,
+            (width % 1_000_000_000) as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_date_time_nanos_round_trip() {
+        for nanos in [0, -1, 1_700_000_000_123_456_789, i64::MIN, i64::MAX] {
+            assert_eq!(
+                offset_date_time_to_nanos(nanos_to_offset_date_time(nanos)).unwrap(),
+                nanos
+            );
+        }
+        let far_future = OffsetDateTime::UNIX_EPOCH + Duration::days(365 * 1_000);
+        assert_eq!(
+            offset_date_time_to_nanos(far_future),
+            Err(ConversionError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_interval_from_offset_date_times() {
+        let epoch = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(
+            SourceBound::try_from_offset_date_time_uncertainty(
+                0,
+                epoch,
+                Duration::milliseconds(10),
+            )
+            .unwrap(),
+        );
+        source_bounds.extend(
+            SourceBound::try_from_offset_date_times(
+                1,
+                epoch + Duration::milliseconds(5),
+                epoch + Duration::milliseconds(20),
+            )
+            .unwrap(),
+        );
+
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            interval.lower_bound_offset_date_time(),
+            Some(epoch + Duration::milliseconds(5))
+        );
+        assert_eq!(
+            interval.upper_bound_offset_date_time(),
+            Some(epoch + Duration::milliseconds(10))
+        );
+        assert_eq!(interval.width_time_duration(), Duration::milliseconds(5));
+
+        assert_eq!(
+            SourceBound::try_from_offset_date_time_uncertainty(
+                0,
+                epoch,
+                Duration::milliseconds(-1)
+            ),
+            Err(ConversionError::NegativeDuration)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! The intersection algorithm of NTP (RFC 5905, section 11.2.1), the variant of Marzullo's
+//! algorithm that DTS introduced. Rather than the smallest interval consistent with the most
+//! sources, it returns the largest interval consistent with a majority of `m - f` sources, for the
+//! smallest number of falsetickers `f`, and additionally requires that at most `f` of the
+//! midpoints lie outside of it. The interval is wider, but it is not pulled off center by a few
+//! tight falsetickers, which makes it a better input for the cluster and combine stages.
+
+use alloc::vec::Vec;
+
+use crate::pipeline::Select;
+use crate::verdict::source_intervals;
+use crate::{Interval, MarzulloError, SourceBound};
+
+/// Returns the intersection interval of the sources, or `None` if there are no sources at all.
+/// Sources missing either bound are skipped; use `validate_source_bounds` to reject such input.
+///
+/// Of the returned interval, `sources_true` is `m - f`, and `sources_false` is `f`. As the
+/// interval is wider than that of Marzullo's algorithm, fewer than `sources_true` sources may
+/// contain it entirely.
+pub fn intersect(source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError> {
+    let sources = source_intervals(source_bounds);
+    let m = sources.len();
+    if m == 0 {
+        return Ok(None);
+    }
+
+    // The edges of every source, as `(value, type)`, with lower bounds of -1, midpoints of 0, and
+    // upper bounds of 1:
+    let mut edges = Vec::with_capacity(m * 3);
+    for &(_, lower, upper) in &sources {
+        let midpoint = ((lower as i128 + upper as i128).div_euclid(2)) as i64;
+        edges.extend([(lower, -1), (midpoint, 0), (upper, 1)]);
+    }
+    edges.sort_unstable();
+
+    for f in 0..m.div_ceil(2) {
+        let (mut low, mut high) = (None, None);
+        let mut midpoints = 0;
+
+        let mut chime = 0_i64;
+        for &(value, edge) in &edges {
+            chime -= edge;
+            if chime >= (m - f) as i64 {
+                low = Some(value);
+                break;
+            }
+            if edge == 0 {
+                midpoints += 1;
+            }
+        }
+        let mut chime = 0_i64;
+        for &(value, edge) in edges.iter().rev() {
+            chime += edge;
+            if chime >= (m - f) as i64 {
+                high = Some(value);
+                break;
+            }
+            if edge == 0 {
+                midpoints += 1;
+            }
+        }
+
+        if midpoints > f {
+            continue;
+        }
+        if let (Some(low), Some(high)) = (low, high) {
+            if low <= high {
+                return Ok(Some(Interval {
+                    lower_bound: low,
+                    upper_bound: high,
+                    sources_true: (m - f) as u8,
+                    sources_false: f as u8,
+                }));
+            }
+        }
+    }
+    Err(MarzulloError::NoMajority { sources: m })
+}
+
+/// The intersection algorithm as the select stage of a `Pipeline`.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct Intersection;
+
+impl Select for Intersection {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        intersect(&source_bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_intersect() {
+        assert_eq!(intersect(&[]), Ok(None));
+
+        // Marzullo's algorithm chooses [13, 14] for the tight source 3, although source 2
+        // disagrees with it. The intersection spans both sets of 3 sources:
+        let source_bounds = bounds(&[(0, 0, 20), (1, 8, 16), (2, 9, 11), (3, 13, 14)]);
+        let interval = intersect(&source_bounds).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (9, 14));
+        assert_eq!((interval.sources_true(), interval.sources_false()), (3, 1));
+        let marzullo = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!((marzullo.lower_bound(), marzullo.upper_bound()), (13, 14));
+
+        assert_eq!(
+            intersect(&bounds(&[(0, 0, 1), (1, 5, 6)])),
+            Err(MarzulloError::NoMajority { sources: 2 })
+        );
+    }
+}
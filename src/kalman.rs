@@ -0,0 +1,188 @@
+//! A Kalman filter over successive selections, for a smoothed estimate of the offset of the local
+//! clock and of its frequency error, with their covariance, at any time between selections.
+//!
+//! The state is the offset, in nanoseconds, and the frequency, in nanoseconds per second (parts
+//! per billion), of the reference time against the local clock. The offset drifts with the
+//! frequency, which itself random-walks; both are perturbed by white noise of the configured
+//! spectral densities. Every chosen interval measures the offset at its midpoint, with its half
+//! width taken as one standard deviation.
+
+use crate::Interval;
+
+const NANOS_PER_SECOND: f64 = 1e9;
+
+/// The variance of the frequency before the first estimate, for an error of up to 500ppm.
+const INITIAL_FREQUENCY_VARIANCE: f64 = 500_000.0 * 500_000.0;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct KalmanConfig {
+    /// The spectral density of the white noise of the offset, in ns²/s.
+    pub phase_noise: f64,
+    /// The spectral density of the white noise of the frequency, in (ns/s)²/s.
+    pub frequency_noise: f64,
+}
+
+impl Default for KalmanConfig {
+    /// The noise of a typical crystal oscillator: 100ns of jitter per √s, and a frequency that
+    /// wanders by 1ppb per √s.
+    fn default() -> KalmanConfig {
+        KalmanConfig {
+            phase_noise: 1e4,
+            frequency_noise: 1.0,
+        }
+    }
+}
+
+/// The estimate of a `KalmanFilter` at a local time.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KalmanState {
+    /// The local time of the estimate, in nanoseconds since the Unix epoch.
+    pub time: i64,
+    /// The offset of the reference time from the local clock, in nanoseconds.
+    pub offset: f64,
+    /// The frequency of the reference time against the local clock, in nanoseconds per second.
+    pub frequency: f64,
+    /// The covariance of the offset and the frequency, in ns², ns²/s, and (ns/s)².
+    pub covariance: [[f64; 2]; 2],
+}
+
+impl KalmanState {
+    /// Returns the frequency in parts per million.
+    pub fn frequency_ppm(&self) -> f64 {
+        self.frequency / 1_000.0
+    }
+
+    /// Returns the variance of the offset, in ns².
+    pub fn offset_variance(&self) -> f64 {
+        self.covariance[0][0]
+    }
+
+    /// Returns the state extrapolated to the local time `time`, without a measurement.
+    pub fn predict(&self, time: i64, config: &KalmanConfig) -> KalmanState {
+        let dt = (time as f64 - self.time as f64) / NANOS_PER_SECOND;
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let (q0, q1) = (config.phase_noise, config.frequency_noise);
+        // P = F P Fᵀ + Q, with F = [[1, dt], [0, 1]], for the noise integrated over dt:
+        let p00 = p00 + dt * (p10 + p01) + dt * dt * p11 + q0 * dt + q1 * dt * dt * dt / 3.0;
+        let p01 = p01 + dt * p11 + q1 * dt * dt / 2.0;
+        let p10 = p10 + dt * p11 + q1 * dt * dt / 2.0;
+        let p11 = p11 + q1 * dt;
+        KalmanState {
+            time,
+            offset: self.offset + self.frequency * dt,
+            frequency: self.frequency,
+            covariance: [[p00, p01], [p10, p11]],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KalmanFilter {
+    config: KalmanConfig,
+    state: Option<KalmanState>,
+}
+
+impl KalmanFilter {
+    pub fn new(config: KalmanConfig) -> KalmanFilter {
+        KalmanFilter {
+            config,
+            state: None,
+        }
+    }
+
+    /// Fuses the interval chosen at the local time `time`, and returns the new estimate.
+    pub fn update(&mut self, time: i64, interval: &Interval) -> KalmanState {
+        let half_width = interval.width() as f64 / 2.0;
+        let midpoint = interval.lower_bound() as f64 + half_width;
+        self.update_offset(time, midpoint, (half_width * half_width).max(1.0))
+    }
+
+    /// Fuses a measurement of the offset with the given variance, in ns², at the local time
+    /// `time`, and returns the new estimate.
+    pub fn update_offset(&mut self, time: i64, offset: f64, variance: f64) -> KalmanState {
+        let state = match &self.state {
+            None => KalmanState {
+                time,
+                offset,
+                frequency: 0.0,
+                covariance: [[variance, 0.0], [0.0, INITIAL_FREQUENCY_VARIANCE]],
+            },
+            Some(state) => {
+                let predicted = state.predict(time, &self.config);
+                let [[p00, p01], [p10, p11]] = predicted.covariance;
+                // The gain K = P Hᵀ / (H P Hᵀ + R), with H = [1, 0]:
+                let innovation = offset - predicted.offset;
+                let s = p00 + variance;
+                let (k0, k1) = (p00 / s, p10 / s);
+                KalmanState {
+                    time,
+                    offset: predicted.offset + k0 * innovation,
+                    frequency: predicted.frequency + k1 * innovation,
+                    covariance: [
+                        [(1.0 - k0) * p00, (1.0 - k0) * p01],
+                        [p10 - k1 * p00, p11 - k1 * p01],
+                    ],
+                }
+            }
+        };
+        self.state = Some(state);
+        state
+    }
+
+    /// Returns the last estimate, as of the last measurement.
+    pub fn state(&self) -> Option<&KalmanState> {
+        self.state.as_ref()
+    }
+
+    /// Returns the estimate extrapolated to the local time `time`, for the best estimate between
+    /// selections.
+    pub fn predict(&self, time: i64) -> Option<KalmanState> {
+        Some(self.state.as_ref()?.predict(time, &self.config))
+    }
+
+    /// Forgets the estimate, e.g. after the local clock stepped.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_kalman_filter() {
+        let mut filter = KalmanFilter::new(KalmanConfig::default());
+        assert_eq!(filter.predict(0), None);
+        // The reference gains 10µs per second, 10ppm, measured every 16s to within ±5µs:
+        let mut variance = f64::MAX;
+        for poll in 0..64_i64 {
+            let time = poll * 16_000_000_000;
+            let offset =
+                1_000_000 + poll * 160_000 + [3_000, -4_000, 1_000, -2_000][poll as usize % 4];
+            let interval = Interval::try_from_source_bounds(Vec::from(SourceBound::pair(
+                0,
+                offset - 5_000,
+                offset + 5_000,
+            )))
+            .unwrap()
+            .unwrap();
+            let state = filter.update(time, &interval);
+            if poll > 0 {
+                assert!(state.offset_variance() <= variance * 1.01);
+            }
+            variance = state.offset_variance();
+        }
+        let state = *filter.state().unwrap();
+        assert!((state.frequency_ppm() - 10.0).abs() < 0.1, "{:?}", state);
+        assert!(variance < 25_000_000.0);
+
+        // Between selections, the offset keeps drifting with the frequency:
+        let predicted = filter.predict(state.time + 8_000_000_000).unwrap();
+        assert!((predicted.offset - state.offset - 80_000.0).abs() < 1_000.0);
+        assert!(predicted.offset_variance() > state.offset_variance());
+    }
+}
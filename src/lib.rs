@@ -1,44 +1,201 @@
-/// Marzullo's algorithm, invented by Keith Marzullo for his Ph.D. dissertation in 1984, is an
-/// agreement algorithm used to select sources for estimating accurate time from a number of noisy
-/// time sources. NTP uses a modified form of this called the Intersection algorithm, which returns
-/// a larger interval for further statistical sampling. However, here we want the smallest interval.
-/// Here is a description of the algorithm:
-/// https://en.wikipedia.org/wiki/Marzullo%27s_algorithm#Method
-/// This is a port of the TigerBeetle implementation done mainly by Joran Dirk Greef (https://github.com/jorangreef) and King Protty (https://github.com/kprotty):
-/// see it here https://github.com/tigerbeetle/tigerbeetle/blob/main/src/vsr/marzullo
-
-#[derive(Debug, Clone)]
-pub struct Interval {
-    lower_bound: i64,
-    upper_bound: i64,
+//! Marzullo's algorithm, invented by Keith Marzullo for his Ph.D. dissertation in 1984, is an
+//! agreement algorithm used to select sources for estimating accurate time from a number of noisy
+//! time sources. NTP uses a modified form of this called the Intersection algorithm, which returns
+//! a larger interval for further statistical sampling. However, here we want the smallest interval.
+//! Here is a description of the algorithm:
+//! https://en.wikipedia.org/wiki/Marzullo%27s_algorithm#Method
+//! This is a port of the TigerBeetle implementation done mainly by Joran Dirk Greef (https://github.com/jorangreef) and King Protty (https://github.com/kprotty):
+//! see it here https://github.com/tigerbeetle/tigerbeetle/blob/main/src/vsr/marzullo
+//!
+//! The crate is `no_std` (with `alloc`) when the default `std` feature is disabled.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use ordering::{BoundOrder, LowerFirst};
+use trace::{Sweep, SweepStep, Visitor};
+
+pub mod agreement;
+pub mod alarm;
+pub mod allen;
+mod arithmetic;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod averaging;
+pub mod berkeley;
+mod chart;
+pub mod circular;
+pub mod clock;
+pub mod cluster;
+pub mod combine;
+pub mod confidence;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod discipline;
+#[cfg(feature = "postcard")]
+pub mod encoding;
+pub mod engine;
+pub mod estimate;
+pub mod fusion;
+pub mod hierarchy;
+pub mod interop;
+pub mod intersection;
+pub mod kalman;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod midpoint;
+pub mod options;
+pub mod ordering;
+pub mod pipeline;
+pub mod prefilter;
+pub mod quorum;
+mod range;
+pub mod region;
+#[cfg(feature = "json")]
+pub mod replay;
+pub mod report;
+#[cfg(feature = "rand")]
+mod sample;
+mod scale;
+pub mod set;
+#[cfg(feature = "sntp")]
+pub mod sntp;
+pub mod source;
+pub mod stability;
+pub mod statistics;
+pub mod status;
+#[cfg(feature = "svg")]
+mod svg;
+pub mod tagged;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+pub mod tiebreak;
+pub mod trace;
+#[cfg(all(target_arch = "x86_64", feature = "tsc"))]
+pub mod tsc;
+pub mod units;
+pub mod validation;
+pub mod value;
+pub mod verdict;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod weighted;
+
+pub use clock::ClockTracker;
+pub use engine::{Engine, Observer, SourceEvent, Stickiness};
+pub use pipeline::{Holdover, Pipeline, SelectionOutcome, SendPipeline};
+pub use report::Report;
+pub use validation::{validate_source_bounds, ValidationIssue};
+pub use value::{Ordered, Unbounded, Value};
+pub use verdict::{SourceVerdict, Verdict};
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Interval<T = i64> {
+    lower_bound: T,
+    upper_bound: T,
     sources_true: u8,
     sources_false: u8,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BoundType {
     Lower,
     Upper,
 }
 
 #[derive(Debug, Clone)]
-pub struct SourceBound {
-    value: i64,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceBound<T = i64> {
+    value: T,
     /// An identifier, the index of the clock source in the list of clock sources:
     source: u8,
     bound_type: BoundType,
 }
 
-impl PartialEq for SourceBound {
+impl SourceBound {
+    /// The sentinel value of a lower bound for a source that cannot bound time from below.
+    pub const UNBOUNDED_LOWER: i64 = i64::MIN;
+    /// The sentinel value of an upper bound for a source that cannot bound time from above.
+    pub const UNBOUNDED_UPPER: i64 = i64::MAX;
+}
+
+impl<T: Value> SourceBound<T> {
+    pub fn new(source: u8, value: T, bound_type: BoundType) -> SourceBound<T> {
+        SourceBound {
+            value,
+            source,
+            bound_type,
+        }
+    }
+
+    pub fn lower(source: u8, value: T) -> SourceBound<T> {
+        SourceBound::new(source, value, BoundType::Lower)
+    }
+
+    pub fn upper(source: u8, value: T) -> SourceBound<T> {
+        SourceBound::new(source, value, BoundType::Upper)
+    }
+
+    /// Returns the lower and upper bound of a source's interval.
+    pub fn pair(source: u8, lower: T, upper: T) -> [SourceBound<T>; 2] {
+        [
+            SourceBound::lower(source, lower),
+            SourceBound::upper(source, upper),
+        ]
+    }
+
+    pub fn value(&self) -> T {
+        self.value.clone()
+    }
+
+    pub fn source(&self) -> u8 {
+        self.source
+    }
+
+    pub fn bound_type(&self) -> BoundType {
+        self.bound_type
+    }
+}
+
+impl<T: Unbounded> SourceBound<T> {
+    /// Returns the lower and upper bound of a source that may only bound time on one side
+    /// (e.g. "definitely after X"). A missing bound is replaced by the `Unbounded` sentinel for
+    /// that side, so that the source still contributes to the overlap count.
+    pub fn pair_optional(source: u8, lower: Option<T>, upper: Option<T>) -> [SourceBound<T>; 2] {
+        SourceBound::pair(
+            source,
+            lower.unwrap_or_else(T::unbounded_lower),
+            upper.unwrap_or_else(T::unbounded_upper),
+        )
+    }
+
+    /// Whether the bound is the sentinel for its side, i.e. the source does not bound time there.
+    pub fn is_unbounded(&self) -> bool {
+        match self.bound_type {
+            BoundType::Lower => self.value == T::unbounded_lower(),
+            BoundType::Upper => self.value == T::unbounded_upper(),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for SourceBound<T> {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value && self.bound_type == other.bound_type
     }
 }
 
-impl Eq for SourceBound {}
+impl<T: Eq> Eq for SourceBound<T> {}
 
-impl PartialOrd for SourceBound {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<T: Ord> PartialOrd for SourceBound<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
@@ -48,125 +205,351 @@ impl PartialOrd for SourceBound {
 /// deciding which comes first is necessary. Such an occurrence can be considered an overlap
 /// with no duration, which can be found by the algorithm by sorting the lower bound before the
 /// upper bound. Alternatively, if such pathological overlaps are considered objectionable then
-/// they can be avoided by sorting the upper bound before the lower bound.
-impl Ord for SourceBound {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+/// they can be avoided by sorting the upper bound before the lower bound, as
+/// `ordering::UpperFirst` does.
+impl<T: Ord> Ord for SourceBound<T> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         if self == other {
             // Use the source index to break the tie and ensure the sort is fully specified and stable
             // so that different sort algorithms sort the same way:
             if self.source < other.source {
-                return std::cmp::Ordering::Less;
+                return core::cmp::Ordering::Less;
             }
             if self.source > other.source {
-                return std::cmp::Ordering::Greater;
+                return core::cmp::Ordering::Greater;
             }
-            return std::cmp::Ordering::Equal;
+            return core::cmp::Ordering::Equal;
         }
 
         if self.value < other.value {
-            return std::cmp::Ordering::Less;
+            return core::cmp::Ordering::Less;
         }
 
         if self.value > other.value {
-            return std::cmp::Ordering::Greater;
+            return core::cmp::Ordering::Greater;
         }
 
         if self.bound_type == BoundType::Lower && other.bound_type == BoundType::Upper {
-            return std::cmp::Ordering::Less;
+            return core::cmp::Ordering::Less;
         }
 
         if self.bound_type == BoundType::Upper && other.bound_type == BoundType::Lower {
-            return std::cmp::Ordering::Greater;
+            return core::cmp::Ordering::Greater;
         }
 
         unreachable!("inconceivable! unable to compare SourceBound structs.")
     }
 }
 
-#[derive(Debug)]
-pub enum MarzulloError {
-    InvalidSourceBounds(String),
-    InvalidSourceBoundsOrder(String),
-    IntervalInvariant(String),
+/// An invariant of the sweep over the sorted source bounds that did not hold. Indices refer to
+/// positions in the sorted array of source bounds.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IntervalInvariant<T = i64> {
+    /// The bound at `index` is an upper bound for which no lower bound was seen before it.
+    UnmatchedUpperBound { index: usize, bound: SourceBound<T> },
+    /// The last visited bound should be an upper bound.
+    LastBoundNotUpper { index: usize, bound: SourceBound<T> },
+    /// The best count of overlapping intervals should be less than or equal to the number of sources.
+    BestExceedsSources { best: usize, sources: usize },
+    /// The sum of the interval's `sources_true` and `sources_false` should be equal to the number
+    /// of sources.
+    SourceCountMismatch {
+        sources_true: u8,
+        sources_false: u8,
+        sources: usize,
+    },
 }
 
-impl std::fmt::Display for MarzulloError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MarzulloError<T = i64> {
+    /// The first of the sorted bounds should be a lower bound.
+    InvalidSourceBounds {
+        index: usize,
+        bound: SourceBound<T>,
+    },
+    /// The bound at `previous_index` sorted after the bound at `index`.
+    InvalidSourceBoundsOrder {
+        previous_index: usize,
+        previous: SourceBound<T>,
+        index: usize,
+        bound: SourceBound<T>,
+    },
+    IntervalInvariant(IntervalInvariant<T>),
+    /// No interval is consistent with a majority of the sources, as the NTP intersection algorithm
+    /// requires.
+    NoMajority {
+        sources: usize,
+    },
+    /// There are more sources than the 255 that `sources_true` and `sources_false` can count.
+    TooManySources {
+        sources: usize,
+    },
+}
+
+impl<T: Value> MarzulloError<T> {
+    /// The source responsible for the error, if the error can be attributed to a single source.
+    pub fn offending_source(&self) -> Option<u8> {
+        match self {
+            MarzulloError::InvalidSourceBounds { bound, .. } => Some(bound.source),
+            MarzulloError::InvalidSourceBoundsOrder { bound, .. } => Some(bound.source),
+            MarzulloError::IntervalInvariant(IntervalInvariant::UnmatchedUpperBound {
+                bound,
+                ..
+            }) => Some(bound.source),
+            MarzulloError::IntervalInvariant(IntervalInvariant::LastBoundNotUpper {
+                bound,
+                ..
+            }) => Some(bound.source),
+            MarzulloError::IntervalInvariant(_)
+            | MarzulloError::NoMajority { .. }
+            | MarzulloError::TooManySources { .. } => None,
+        }
+    }
+}
+
+impl<T: Value> core::fmt::Display for IntervalInvariant<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
-            MarzulloError::InvalidSourceBounds(msg) => {
-                write!(f, "Invalid source bounds order: {}", msg)
+            IntervalInvariant::UnmatchedUpperBound { index, bound } => write!(
+                f,
+                "upper bound {:?} at index {} has no preceding lower bound",
+                bound, index
+            ),
+            IntervalInvariant::LastBoundNotUpper { index, bound } => write!(
+                f,
+                "expected last visited source bound {:?} at index {} to be an upper bound",
+                bound, index
+            ),
+            IntervalInvariant::BestExceedsSources { best, sources } => write!(
+                f,
+                "best count of overlapping intervals should be less than or equal to the number of sources. best: {}, sources: {}",
+                best, sources
+            ),
+            IntervalInvariant::SourceCountMismatch {
+                sources_true,
+                sources_false,
+                sources,
+            } => write!(
+                f,
+                "expected the sum of interval's sources_true ({}) and sources_false ({}) to be equal to the number of sources ({})",
+                sources_true, sources_false, sources
+            ),
+        }
+    }
+}
+
+impl<T: Value> core::fmt::Display for MarzulloError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            MarzulloError::InvalidSourceBounds { index, bound } => {
+                write!(
+                    f,
+                    "Invalid source bounds order: bound {:?} at index {} should be a lower bound",
+                    bound, index
+                )
+            }
+            MarzulloError::InvalidSourceBoundsOrder {
+                previous_index,
+                previous,
+                index,
+                bound,
+            } => {
+                write!(
+                    f,
+                    "Invalid source bounds sorting: expected {:?} at index {} to be less than or equal to {:?} at index {}",
+                    previous, previous_index, bound, index
+                )
             }
-            MarzulloError::InvalidSourceBoundsOrder(msg) => {
-                write!(f, "Invalid source bounds sorting: {}", msg)
+            MarzulloError::IntervalInvariant(invariant) => {
+                write!(f, "Interval invariant : {}", invariant)
             }
-            MarzulloError::IntervalInvariant(msg) => {
-                write!(f, "Interval invariant : {}", msg)
+            MarzulloError::NoMajority { sources } => {
+                write!(f, "No majority of the {} sources agrees", sources)
+            }
+            MarzulloError::TooManySources { sources } => {
+                write!(
+                    f,
+                    "{} sources are more than the 255 an interval can count",
+                    sources
+                )
             }
         }
     }
 }
 
-impl std::error::Error for MarzulloError {}
+impl<T: Value> core::error::Error for MarzulloError<T> {}
+
+impl<T: Value> Interval<T> {
+    pub fn lower_bound(&self) -> T {
+        self.lower_bound.clone()
+    }
+
+    pub fn upper_bound(&self) -> T {
+        self.upper_bound.clone()
+    }
+
+    /// The number of sources that overlap the interval.
+    pub fn sources_true(&self) -> u8 {
+        self.sources_true
+    }
 
-impl Interval {
-    /// Returns the smallest interval consistent with the largest number of sources.
+    /// The number of sources that do not overlap the interval.
+    pub fn sources_false(&self) -> u8 {
+        self.sources_false
+    }
+
+    /// The width of the interval. For the integer value types this never overflows, even when the
+    /// interval spans the `Unbounded` sentinels.
+    pub fn width(&self) -> T::Width {
+        T::width(&self.lower_bound, &self.upper_bound)
+    }
+
+    /// Returns the smallest interval consistent with the largest number of sources, or `None` if
+    /// there are no sources at all. The latter is deliberately distinct from a zero-width interval,
+    /// so that "no data" is not mistaken for perfect agreement.
     pub fn try_from_source_bounds(
-        source_bounds: Vec<SourceBound>,
-    ) -> Result<Interval, MarzulloError> {
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        Interval::observed::<LowerFirst, _>(source_bounds, Visitor(|_: &T, _, _, _| {}))
+    }
+
+    /// Selects like `try_from_source_bounds`, and calls `visitor` at every bound of the sweep, in
+    /// sorted order, with its value, type, and source, and the number of sources overlapping it
+    /// after it, so that custom statistics (e.g. time-weighted coverage) take a single pass
+    /// without sorting the bounds again.
+    pub fn try_from_source_bounds_with_visitor<V: FnMut(&T, BoundType, u8, usize)>(
+        source_bounds: Vec<SourceBound<T>>,
+        visitor: V,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        Interval::observed::<LowerFirst, _>(source_bounds, Visitor(visitor))
+    }
+
+    /// Selects like `try_from_source_bounds`, and returns every decision of the sweep with the
+    /// result, up to the error if there is one.
+    #[allow(clippy::type_complexity)]
+    pub fn try_from_source_bounds_traced(
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> (
+        Result<Option<Interval<T>>, MarzulloError<T>>,
+        Vec<SweepStep<T>>,
+    ) {
+        let mut steps = Vec::with_capacity(source_bounds.len());
+        let result = Interval::observed::<LowerFirst, _>(source_bounds, &mut steps);
+        (result, steps)
+    }
+
+    /// Selects like `try_from_source_bounds`, with the bounds of equal values sorted by the
+    /// strategy `O` rather than lower bounds first.
+    pub fn try_from_source_bounds_ordered<O: BoundOrder<T>>(
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        Interval::observed::<O, _>(source_bounds, Visitor(|_: &T, _, _, _| {}))
+    }
+
+    /// Selects with the tracing and metrics of the selection, if they are enabled.
+    fn observed<O: BoundOrder<T>, S: Sweep<T>>(
+        source_bounds: Vec<SourceBound<T>>,
+        sweep: S,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("selection", sources = source_bounds.len() / 2).entered();
+
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+
+        let result = Interval::select::<O, S>(source_bounds, sweep);
+
+        #[cfg(feature = "metrics")]
+        metrics::record(&result, start.elapsed());
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(Some(ivl)) => tracing::debug!(
+                best = ivl.sources_true,
+                rejected = ivl.sources_false,
+                width = ?ivl.width(),
+                "selected {:?}..={:?}",
+                ivl.lower_bound,
+                ivl.upper_bound
+            ),
+            Ok(None) => tracing::debug!("no sources"),
+            Err(error) => tracing::warn!(%error, "selection failed"),
+        }
+        result
+    }
+
+    fn select<O: BoundOrder<T>, S: Sweep<T>>(
+        source_bounds: Vec<SourceBound<T>>,
+        mut hooks: S,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
         // There are two bounds (lower and upper) per source.
         let sources = source_bounds.len() / 2;
         if sources == 0 {
-            return Ok(Interval {
-                lower_bound: 0,
-                upper_bound: 0,
-                sources_true: 0,
-                sources_false: 0,
-            });
+            return Ok(None);
+        }
+        if u8::try_from(sources).is_err() {
+            return Err(MarzulloError::TooManySources { sources });
         }
 
-        let mut bounds = source_bounds.clone();
-        bounds.sort();
-
-        if !bounds
-            .get(0)
-            .is_some_and(|b| b.bound_type == BoundType::Lower)
+        let mut bounds = source_bounds;
         {
-            return Err(MarzulloError::InvalidSourceBounds(
-                "first bound should be a lower bound".to_string(),
-            ));
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("sort", bounds = bounds.len()).entered();
+            bounds.sort_by(O::cmp);
+        }
+        // Verify that our sort implementation is correct:
+        for (idx, pair) in bounds.windows(2).enumerate() {
+            if O::cmp(&pair[0], &pair[1]) == core::cmp::Ordering::Greater {
+                return Err(MarzulloError::InvalidSourceBoundsOrder {
+                    previous_index: idx,
+                    previous: pair[0].clone(),
+                    index: idx + 1,
+                    bound: pair[1].clone(),
+                });
+            }
+        }
+        overlap_zero_width(&mut bounds);
+
+        if bounds[0].bound_type != BoundType::Lower {
+            return Err(MarzulloError::InvalidSourceBounds {
+                index: 0,
+                bound: bounds[0].clone(),
+            });
         }
 
         let mut best = 0;
-        let mut count = 0;
-        let mut iter_prev_bound: Option<&SourceBound> = None;
-        let mut interval: Option<Interval> = None;
+        let mut count: usize = 0;
+        let mut interval: Option<Interval<T>> = None;
 
+        #[cfg(feature = "tracing")]
+        let sweep = tracing::trace_span!("sweep").entered();
         for (idx, bound) in bounds.iter().enumerate() {
-            // Verify that our sort implementation is correct:
-            if let Some(prevb) = iter_prev_bound {
-                if prevb > bound {
-                    return Err(MarzulloError::InvalidSourceBoundsOrder(format!(
-                        "expected {:?} to be less than or equal to {:?}",
-                        prevb, bound
-                    )));
-                }
-            }
-
-            iter_prev_bound = Some(bound);
-
             // Update the current number of overlapping intervals:
             match bound.bound_type {
                 BoundType::Lower => count += 1,
-                BoundType::Upper => count -= 1,
+                BoundType::Upper => {
+                    count = count.checked_sub(1).ok_or_else(|| {
+                        MarzulloError::IntervalInvariant(IntervalInvariant::UnmatchedUpperBound {
+                            index: idx,
+                            bound: bound.clone(),
+                        })
+                    })?
+                }
             }
+            hooks.count(bound, count);
 
             // The last upper bound tuple will have a count of one less than the lower bound.
             // Therefore, we should never see count >= best for the last tuple:
             if count > best && idx < bounds.len() - 1 {
                 best = count;
+                hooks.candidate(&bound.value, &bounds[idx + 1].value, count);
                 interval = Some(Interval {
-                    lower_bound: bound.value,
-                    upper_bound: bounds[idx + 1].value,
+                    lower_bound: bound.value.clone(),
+                    upper_bound: bounds[idx + 1].value.clone(),
                     sources_true: 0,
                     sources_false: 0,
                 });
@@ -175,13 +558,16 @@ impl Interval {
                 && bounds[idx + 1].bound_type == BoundType::Upper
             {
                 // This is a tie for best overlap. Both intervals have the same number of sources.
-                // We want to choose the smaller of the two intervals:
-                let alternative = bounds[idx + 1].value - bound.value;
+                // We want to choose the smaller of the two intervals, unless a score ranks them.
+                // Widths are computed by the value type so that intervals spanning the unbounded
+                // sentinels do not overflow:
                 if let Some(ref ivl) = interval {
-                    if alternative < ivl.upper_bound - ivl.lower_bound {
+                    let replaced = hooks.replaces(&bound.value, &bounds[idx + 1].value, count, ivl);
+                    hooks.tie_break(&bound.value, &bounds[idx + 1].value, count, replaced);
+                    if replaced {
                         interval = Some(Interval {
-                            lower_bound: bound.value,
-                            upper_bound: bounds[idx + 1].value,
+                            lower_bound: bound.value.clone(),
+                            upper_bound: bounds[idx + 1].value.clone(),
                             sources_true: 0,
                             sources_false: 0,
                         });
@@ -190,16 +576,25 @@ impl Interval {
             }
         }
 
-        if !iter_prev_bound.is_some_and(|b| b.bound_type == BoundType::Upper) {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::trace!(best, "sweep done");
+            drop(sweep);
+        }
+
+        let last = bounds.len() - 1;
+        if bounds[last].bound_type != BoundType::Upper {
             return Err(MarzulloError::IntervalInvariant(
-                "expected last visited source bound to be an upper bound.".to_string(),
+                IntervalInvariant::LastBoundNotUpper {
+                    index: last,
+                    bound: bounds[last].clone(),
+                },
             ));
         }
 
         if best > sources {
             return Err(MarzulloError::IntervalInvariant(
-                format!( "best count of overlapping intervals should be less than or equal to the number of sources.
-                best: {}, sources: {}", best, sources)
+                IntervalInvariant::BestExceedsSources { best, sources },
             ));
         }
 
@@ -211,17 +606,20 @@ impl Interval {
             ivl
         });
 
-        if !interval
-            .as_ref()
-            .is_some_and(|ivl| ivl.sources_true + ivl.sources_false == sources as u8)
-        {
-            return Err(MarzulloError::IntervalInvariant(
-                "expected the sum of interval's sources_true and sources_false to be equal to the number of sources.".to_string()
-            ));
+        if let Some(ref ivl) = interval {
+            if ivl.sources_true as usize + ivl.sources_false as usize != sources {
+                return Err(MarzulloError::IntervalInvariant(
+                    IntervalInvariant::SourceCountMismatch {
+                        sources_true: ivl.sources_true,
+                        sources_false: ivl.sources_false,
+                        sources,
+                    },
+                ));
+            }
         }
 
         match interval {
-            Some(ivl) => Ok(ivl),
+            Some(ivl) => Ok(Some(ivl)),
             _ => unreachable!(
                 "this branch should never be reached since the computed interval was asserted to be valid."
             ),
@@ -229,6 +627,59 @@ impl Interval {
     }
 }
 
+/// Moves the bounds of every source of no width, whose upper bound the order sorted before its own
+/// lower bound, after the upper bounds of its value and before the lower bounds, lower bound first.
+/// The source then overlaps itself, and the other sources of no width at its value, but not the
+/// intervals that only touch it.
+fn overlap_zero_width<T: Value>(bounds: &mut [SourceBound<T>]) {
+    let mut start = 0;
+    while start < bounds.len() {
+        let value = &bounds[start].value;
+        let end = start
+            + bounds[start..]
+                .iter()
+                .take_while(|b| b.value == *value)
+                .count();
+        let run = &mut bounds[start..end];
+        start = end;
+        if run.len() < 2 {
+            continue;
+        }
+        let (mut lower, mut upper, mut upper_first) = ([false; 256], [false; 256], false);
+        for bound in run.iter() {
+            let source = bound.source as usize;
+            match bound.bound_type {
+                BoundType::Lower => {
+                    lower[source] = true;
+                    upper_first |= upper[source];
+                }
+                BoundType::Upper => upper[source] = true,
+            }
+        }
+        if upper_first {
+            run.sort_by_key(|bound| {
+                let zero_width = lower[bound.source as usize] && upper[bound.source as usize];
+                match (bound.bound_type, zero_width) {
+                    (BoundType::Upper, false) => 0,
+                    (BoundType::Lower, true) => 1,
+                    (BoundType::Upper, true) => 2,
+                    (BoundType::Lower, false) => 3,
+                }
+            });
+        }
+    }
+}
+
+impl<T: Unbounded> Interval<T> {
+    pub fn is_lower_unbounded(&self) -> bool {
+        self.lower_bound == T::unbounded_lower()
+    }
+
+    pub fn is_upper_unbounded(&self) -> bool {
+        self.upper_bound == T::unbounded_upper()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,7 +727,7 @@ mod tests {
     }
 
     fn source_bounds_generator(seed: Vec<i64>) -> Vec<SourceBound> {
-        let mut source_bounds = Vec::new();
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
         for (idx, value) in seed.iter().enumerate() {
             let bound_type = if idx % 2 == 0 {
                 BoundType::Lower
@@ -295,21 +746,27 @@ mod tests {
     #[test]
     fn test_marzullo_interval_from_source_bounds() {
         let source_bounds = source_bounds_generator(vec![11, 13, 10, 12, 8, 12]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 11);
         assert_eq!(interval.upper_bound, 12);
         assert_eq!(interval.sources_true, 3);
         assert_eq!(interval.sources_false, 0);
 
         let source_bounds = source_bounds_generator(vec![8, 12, 11, 13, 14, 15]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 11);
         assert_eq!(interval.upper_bound, 12);
         assert_eq!(interval.sources_true, 2);
         assert_eq!(interval.sources_false, 1);
 
         let source_bounds = source_bounds_generator(vec![-10, 10, -1, 1, 0, 0]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 0);
         assert_eq!(interval.upper_bound, 0);
         assert_eq!(interval.sources_true, 3);
@@ -317,7 +774,9 @@ mod tests {
 
         // The upper bound of the first interval overlaps inclusively with the lower of the last.
         let source_bounds = source_bounds_generator(vec![8, 12, 10, 11, 8, 10]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 10);
         assert_eq!(interval.upper_bound, 10);
         assert_eq!(interval.sources_true, 3);
@@ -326,7 +785,9 @@ mod tests {
         // The first smallest interval is selected. The alternative with equal overlap is 10..12.
         // However, while this shares the same number of sources, it is not the smallest interval.
         let source_bounds = source_bounds_generator(vec![8, 12, 10, 12, 8, 9]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 8);
         assert_eq!(interval.upper_bound, 9);
         assert_eq!(interval.sources_true, 2);
@@ -335,7 +796,9 @@ mod tests {
         // The last smallest interval is selected. The alternative with equal overlap is 7..9.
         // However, while this shares the same number of sources, it is not the smallest interval.
         let source_bounds = source_bounds_generator(vec![7, 9, 7, 12, 10, 11]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 10);
         assert_eq!(interval.upper_bound, 11);
         assert_eq!(interval.sources_true, 2);
@@ -343,23 +806,34 @@ mod tests {
 
         // The same idea as the previous test, but with negative offsets.
         let source_bounds = source_bounds_generator(vec![-9, -7, -12, -7, -11, -10]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, -11);
         assert_eq!(interval.upper_bound, -10);
         assert_eq!(interval.sources_true, 2);
         assert_eq!(interval.sources_false, 1);
 
-        // A cluster of one with no remote sources.
+        // A cluster of one with no remote sources has no interval, which is distinct from the
+        // sources agreeing on exactly zero:
         let source_bounds = source_bounds_generator(vec![]);
         let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        assert!(interval.is_none());
+
+        let source_bounds = source_bounds_generator(vec![0, 0, 0, 0, 0, 0]);
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 0);
         assert_eq!(interval.upper_bound, 0);
-        assert_eq!(interval.sources_true, 0);
+        assert_eq!(interval.sources_true, 3);
         assert_eq!(interval.sources_false, 0);
 
         // A cluster of two with one remote source.
         let source_bounds = source_bounds_generator(vec![1, 3]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 1);
         assert_eq!(interval.upper_bound, 3);
         assert_eq!(interval.sources_true, 1);
@@ -367,7 +841,9 @@ mod tests {
 
         // A cluster of three with agreement.
         let source_bounds = source_bounds_generator(vec![1, 3, 2, 2]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 2);
         assert_eq!(interval.upper_bound, 2);
         assert_eq!(interval.sources_true, 2);
@@ -375,10 +851,165 @@ mod tests {
 
         // A cluster of three with agreement.
         let source_bounds = source_bounds_generator(vec![1, 3, 4, 5]);
-        let interval = Interval::try_from_source_bounds(source_bounds).unwrap();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
         assert_eq!(interval.lower_bound, 4);
         assert_eq!(interval.upper_bound, 5);
         assert_eq!(interval.sources_true, 1);
         assert_eq!(interval.sources_false, 1);
     }
+
+    #[test]
+    fn test_marzullo_interval_with_unbounded_sources() {
+        // A source that can only say "definitely after 10" still counts towards the overlap:
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 8, 12));
+        source_bounds.extend(SourceBound::pair_optional(1, Some(10), None));
+        source_bounds.extend(SourceBound::pair(2, 11, 14));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), 11);
+        assert_eq!(interval.upper_bound(), 12);
+        assert_eq!(interval.sources_true(), 3);
+        assert_eq!(interval.sources_false(), 0);
+
+        // Fully unbounded sources overlap everything, and the width does not overflow:
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, None));
+        source_bounds.extend(SourceBound::pair_optional(1, None, None));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert!(interval.is_lower_unbounded());
+        assert!(interval.is_upper_unbounded());
+        assert_eq!(interval.width(), u64::MAX);
+        assert_eq!(interval.sources_true(), 2);
+
+        // A tie between a half-unbounded interval and a bounded one picks the bounded one:
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, Some(5)));
+        source_bounds.extend(SourceBound::pair(1, 7, 9));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), 7);
+        assert_eq!(interval.upper_bound(), 9);
+        assert_eq!(interval.sources_true(), 1);
+        assert_eq!(interval.sources_false(), 1);
+    }
+
+    #[test]
+    fn test_marzullo_interval_with_visitor() {
+        let source_bounds = source_bounds_generator(vec![8, 12, 11, 13, 14, 15]);
+        // The time-weighted coverage: the width of every step of the sweep by its count.
+        let mut coverage = 0;
+        let mut previous: Option<(i64, usize)> = None;
+        let mut sources = Vec::new();
+        let interval = Interval::try_from_source_bounds_with_visitor(
+            source_bounds,
+            |&value, bound_type, source, count| {
+                if let Some((previous, count)) = previous {
+                    coverage += (value - previous) * count as i64;
+                }
+                previous = Some((value, count));
+                sources.push((source, bound_type));
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (11, 12));
+        // The widths of the sources, 4 + 2 + 1:
+        assert_eq!(coverage, 7);
+        assert_eq!(sources[..2], [(0, BoundType::Lower), (1, BoundType::Lower)]);
+        assert_eq!(previous, Some((15, 0)));
+    }
+
+    #[test]
+    fn test_marzullo_errors_carry_offending_bounds() {
+        let source_bounds: Vec<SourceBound> =
+            vec![SourceBound::upper(3, 1), SourceBound::lower(3, 2)];
+        let error = Interval::try_from_source_bounds(source_bounds).unwrap_err();
+        assert_eq!(
+            error,
+            MarzulloError::InvalidSourceBounds {
+                index: 0,
+                bound: SourceBound::upper(3, 1),
+            }
+        );
+        assert_eq!(error.offending_source(), Some(3));
+
+        let source_bounds: Vec<SourceBound> = vec![
+            SourceBound::lower(0, 1),
+            SourceBound::upper(0, 2),
+            SourceBound::upper(5, 3),
+        ];
+        let error = Interval::try_from_source_bounds(source_bounds).unwrap_err();
+        assert_eq!(
+            error,
+            MarzulloError::IntervalInvariant(IntervalInvariant::UnmatchedUpperBound {
+                index: 2,
+                bound: SourceBound::upper(5, 3),
+            })
+        );
+        assert_eq!(error.offending_source(), Some(5));
+
+        let source_bounds: Vec<SourceBound> = vec![
+            SourceBound::lower(0, 1),
+            SourceBound::upper(0, 2),
+            SourceBound::lower(1, 3),
+            SourceBound::lower(1, 4),
+        ];
+        let error = Interval::try_from_source_bounds(source_bounds).unwrap_err();
+        assert_eq!(
+            error,
+            MarzulloError::IntervalInvariant(IntervalInvariant::LastBoundNotUpper {
+                index: 3,
+                bound: SourceBound::lower(1, 4),
+            })
+        );
+
+        // 256 sources are more than the interval can count:
+        let source_bounds: Vec<SourceBound> = (0..=u8::MAX)
+            .flat_map(|source| SourceBound::pair(source, 0, 1))
+            .collect();
+        let interval = Interval::try_from_source_bounds(source_bounds[..510].to_vec()).unwrap();
+        assert_eq!(interval.unwrap().sources_true(), 255);
+        let error = Interval::try_from_source_bounds(source_bounds).unwrap_err();
+        assert_eq!(error, MarzulloError::TooManySources { sources: 256 });
+        assert_eq!(error.offending_source(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let source_bounds = source_bounds_generator(vec![11, 13, 10, 12, 8, 12]);
+        let json = serde_json::to_string(&source_bounds).unwrap();
+        assert_eq!(
+            serde_json::to_value(&source_bounds[0]).unwrap(),
+            serde_json::json!({"value": 11, "source": 0, "bound_type": "Lower"})
+        );
+        let decoded: Vec<SourceBound> = serde_json::from_str(&json).unwrap();
+        assert!(decoded
+            .iter()
+            .zip(&source_bounds)
+            .all(|(a, b)| a == b && a.source == b.source));
+
+        let interval = Interval::try_from_source_bounds(decoded).unwrap();
+        let json = serde_json::to_string(&interval).unwrap();
+        let decoded: Option<Interval> = serde_json::from_str(&json).unwrap();
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.lower_bound, 11);
+        assert_eq!(decoded.upper_bound, 12);
+        assert_eq!(decoded.sources_true, 3);
+        assert_eq!(decoded.sources_false, 0);
+
+        let error = MarzulloError::IntervalInvariant(IntervalInvariant::BestExceedsSources {
+            best: 3,
+            sources: 2,
+        });
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(serde_json::from_str::<MarzulloError>(&json).unwrap(), error);
+    }
 }
@@ -0,0 +1,80 @@
+//! Clock-agreement health through the `metrics` facade, so that Prometheus or StatsD exporters
+//! pick it up without wrapper code. Every call to `Interval::try_from_source_bounds` records:
+//!
+//! - `SELECTIONS` and, on error, `SELECTION_ERRORS` (counters);
+//! - `SOURCES_TRUE`, `SOURCES_FALSE`, and `INTERVAL_WIDTH` (gauges), when an interval is chosen;
+//! - `SELECTION_DURATION`, in seconds (histogram).
+//!
+//! The width is only recorded for value types that can approximate it as `f64`, see
+//! `Value::width_to_f64`.
+
+use std::time::Duration;
+
+use crate::{Interval, MarzulloError, Value};
+
+pub const SELECTIONS: &str = "marzullo_selections_total";
+pub const SELECTION_ERRORS: &str = "marzullo_selection_errors_total";
+pub const SOURCES_TRUE: &str = "marzullo_sources_true";
+pub const SOURCES_FALSE: &str = "marzullo_sources_false";
+pub const INTERVAL_WIDTH: &str = "marzullo_interval_width";
+pub const SELECTION_DURATION: &str = "marzullo_selection_duration_seconds";
+
+pub(crate) fn record<T: Value>(
+    result: &Result<Option<Interval<T>>, MarzulloError<T>>,
+    elapsed: Duration,
+) {
+    ::metrics::counter!(SELECTIONS).increment(1);
+    ::metrics::histogram!(SELECTION_DURATION).record(elapsed);
+    match result {
+        Ok(Some(interval)) => {
+            ::metrics::gauge!(SOURCES_TRUE).set(interval.sources_true() as f64);
+            ::metrics::gauge!(SOURCES_FALSE).set(interval.sources_false() as f64);
+            if let Some(width) = T::width_to_f64(&interval.width()) {
+                ::metrics::gauge!(INTERVAL_WIDTH).set(width);
+            }
+        }
+        Ok(None) => {}
+        Err(_) => ::metrics::counter!(SELECTION_ERRORS).increment(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            let mut source_bounds: Vec<SourceBound> = Vec::new();
+            source_bounds.extend(SourceBound::pair(0, 11, 13));
+            source_bounds.extend(SourceBound::pair(1, 10, 12));
+            source_bounds.extend(SourceBound::pair(2, 14, 15));
+            Interval::try_from_source_bounds(source_bounds).unwrap();
+        });
+
+        let metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+            .collect();
+        let value = |name: &str| {
+            metrics
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+        assert_eq!(value(SELECTIONS), Some(&DebugValue::Counter(1)));
+        assert_eq!(value(SELECTION_ERRORS), None);
+        assert_eq!(value(SOURCES_FALSE), Some(&DebugValue::Gauge(1.0.into())));
+        assert_eq!(value(INTERVAL_WIDTH), Some(&DebugValue::Gauge(1.0.into())));
+        assert!(
+            matches!(value(SELECTION_DURATION), Some(DebugValue::Histogram(samples)) if samples.len() == 1)
+        );
+    }
+}
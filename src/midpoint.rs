@@ -0,0 +1,140 @@
+//! The fault-tolerant midpoint of Welch and Lynch, as in the clock synchronization of TTA and
+//! FlexRay: of `m` readings, the `f` highest and the `f` lowest are discarded, and the midpoint of
+//! the extremes of the rest is agreed on. Any `f` faulty readings, however far off, are either
+//! discarded or lie between correct ones, so that the result is within the range of the correct
+//! readings as long as `m > 3f`, even if the faulty clocks report differently to every reader.
+//!
+//! Over intervals, the lower and the upper bounds are reduced separately, which keeps the lower
+//! bound of the result at or below its upper bound.
+
+use alloc::vec::Vec;
+
+use crate::agreement::{self, AgreementAlgorithm};
+use crate::pipeline::Select;
+use crate::verdict::{source_intervals, Verdict};
+use crate::{Interval, MarzulloError, SourceBound, SourceVerdict};
+
+/// Returns the midpoint of the extremes of `values` without the `faults` highest and lowest.
+fn reduce(values: &mut [i64], faults: usize) -> i64 {
+    values.sort_unstable();
+    let (low, high) = (values[faults], values[values.len() - 1 - faults]);
+    (low as i128 + high as i128).div_euclid(2) as i64
+}
+
+/// Returns the fault-tolerant midpoint of the intervals of the sources, tolerating `faults` faulty
+/// sources, or `None` if there are no sources at all. Sources missing either bound are skipped.
+///
+/// Of the returned interval, `sources_true` is the number of sources whose intervals intersect it.
+/// Unless there are more than `2 * faults` sources, no reading survives, and the result is
+/// `MarzulloError::NoMajority`.
+pub fn fault_tolerant_midpoint(
+    source_bounds: &[SourceBound],
+    faults: usize,
+) -> Result<Option<Interval>, MarzulloError> {
+    let sources = source_intervals(source_bounds);
+    let m = sources.len();
+    if m == 0 {
+        return Ok(None);
+    }
+    if m <= 2 * faults {
+        return Err(MarzulloError::NoMajority { sources: m });
+    }
+    let (mut lowers, mut uppers): (Vec<i64>, Vec<i64>) = sources
+        .iter()
+        .map(|&(_, lower, upper)| (lower, upper))
+        .unzip();
+    let mut interval = Interval {
+        lower_bound: reduce(&mut lowers, faults),
+        upper_bound: reduce(&mut uppers, faults),
+        sources_true: 0,
+        sources_false: 0,
+    };
+    let truechimers = agreement::intersecting(&interval, source_bounds)
+        .iter()
+        .filter(|verdict| verdict.verdict == Verdict::Truechimer)
+        .count();
+    interval.sources_true = truechimers as u8;
+    interval.sources_false = (m - truechimers) as u8;
+    Ok(Some(interval))
+}
+
+/// The fault-tolerant midpoint as an agreement algorithm and the select stage of a `Pipeline`.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct WelchLynch {
+    faults: Option<usize>,
+}
+
+impl WelchLynch {
+    /// Tolerates `faults` faulty sources. By default, the most that the number of sources of every
+    /// selection can tolerate, `(m - 1) / 3`.
+    pub fn new(faults: usize) -> WelchLynch {
+        WelchLynch {
+            faults: Some(faults),
+        }
+    }
+}
+
+impl AgreementAlgorithm for WelchLynch {
+    fn name(&self) -> &'static str {
+        "welch-lynch"
+    }
+
+    fn interval(&self, source_bounds: &[SourceBound]) -> Result<Option<Interval>, MarzulloError> {
+        let faults = self
+            .faults
+            .unwrap_or_else(|| source_intervals(source_bounds).len().saturating_sub(1) / 3);
+        fault_tolerant_midpoint(source_bounds, faults)
+    }
+
+    /// As for the intersection algorithm, the truechimers are the sources whose intervals
+    /// intersect the chosen interval.
+    fn verdicts(&self, interval: &Interval, source_bounds: &[SourceBound]) -> Vec<SourceVerdict> {
+        agreement::intersecting(interval, source_bounds)
+    }
+}
+
+impl Select for WelchLynch {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        self.interval(&source_bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_fault_tolerant_midpoint() {
+        assert_eq!(fault_tolerant_midpoint(&[], 1), Ok(None));
+        // A faulty source far ahead, and one far behind, among five:
+        let source_bounds: Vec<SourceBound> = bounds(&[
+            (0, 8, 12),
+            (1, 9, 13),
+            (2, 10, 16),
+            (3, 1_000, 1_010),
+            (4, -1_000, -990),
+        ]);
+        let interval = fault_tolerant_midpoint(&source_bounds, 2).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (9, 13));
+        assert_eq!((interval.sources_true(), interval.sources_false()), (3, 2));
+        // As they are off to opposite sides, tolerating one fault discards both:
+        let interval = fault_tolerant_midpoint(&source_bounds, 1).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (9, 14));
+        // By default, five sources tolerate one fault:
+        assert_eq!(
+            WelchLynch::default().interval(&source_bounds),
+            Ok(Some(interval))
+        );
+        // Tolerating none, the midpoint is that of the faulty extremes:
+        let interval = fault_tolerant_midpoint(&source_bounds, 0).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (0, 10));
+        assert_eq!(
+            fault_tolerant_midpoint(&source_bounds[..4], 2),
+            Err(MarzulloError::NoMajority { sources: 2 })
+        );
+    }
+}
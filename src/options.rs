@@ -0,0 +1,128 @@
+//! The options of a selection, which a `Report` records, so that the selection can be re-run from
+//! them, e.g. by `replay`.
+
+use alloc::vec::Vec;
+
+use crate::engine::{Engine, Stickiness};
+use crate::ordering::{BoundOrder, LowerFirst, Order, UpperFirst};
+use crate::tiebreak::{narrowest, CandidateInterval, Score, Scorer};
+use crate::trace::Visitor;
+use crate::{Interval, MarzulloError, SourceBound, Value};
+
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options<T = i64> {
+    /// The order of the bounds of equal values.
+    pub order: Order,
+    /// The score of the candidates overlapped by equally many sources.
+    pub score: Score,
+    /// The sticky `Engine` the selection ran through, or `None` if it ran without one.
+    pub engine: Option<EngineOptions<T>>,
+}
+
+/// The state of a sticky `Engine` as of a selection. The widening of an engine that refines is a
+/// closure, and is not recorded.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineOptions<T = i64> {
+    pub stickiness: Stickiness,
+    /// The interval of the previous selection, which the engine keeps unless the selection
+    /// improves on it.
+    pub previous: Option<Interval<T>>,
+}
+
+/// The options of `Interval::try_from_source_bounds`.
+impl<T> Default for Options<T> {
+    fn default() -> Options<T> {
+        Options {
+            order: Order::default(),
+            score: Score::default(),
+            engine: None,
+        }
+    }
+}
+
+impl<T: Value> Options<T> {
+    /// Selects from `source_bounds` with the options, through a sticky engine if there is one.
+    pub fn select(
+        &self,
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+        match &self.engine {
+            Some(_) => Engine::new()
+                .with_options(self.clone())
+                .select(source_bounds),
+            None => sweep(self.order, self.score, source_bounds),
+        }
+    }
+}
+
+/// Selects with the bounds of equal values sorted by `order`, and the candidates ranked by `score`.
+pub(crate) fn sweep<T: Value>(
+    order: Order,
+    score: Score,
+    source_bounds: Vec<SourceBound<T>>,
+) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+    match order {
+        Order::LowerFirst => scored::<T, LowerFirst>(score, source_bounds),
+        Order::UpperFirst => scored::<T, UpperFirst>(score, source_bounds),
+    }
+}
+
+fn scored<T: Value, O: BoundOrder<T>>(
+    score: Score,
+    source_bounds: Vec<SourceBound<T>>,
+) -> Result<Option<Interval<T>>, MarzulloError<T>> {
+    match score {
+        Score::Narrowest => Interval::observed::<O, _>(source_bounds, Visitor(|_: &T, _, _, _| {})),
+        Score::Overlapping(source) => {
+            let score = move |candidate: &CandidateInterval<T>| {
+                (candidate.contains_source(source), narrowest(candidate))
+            };
+            Interval::observed::<O, _>(source_bounds, Scorer::new(score))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_options_select() {
+        // Two intervals that touch at 10, and a disjoint pair with source 3:
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 0, 10), (1, 10, 20), (2, 30, 40), (3, 31, 39)]);
+        let select = |options: Options| {
+            let interval = options.select(source_bounds.clone()).unwrap().unwrap();
+            (interval.lower_bound(), interval.upper_bound())
+        };
+        assert_eq!(select(Options::default()), (10, 10));
+        let options = Options {
+            order: Order::UpperFirst,
+            ..Options::default()
+        };
+        assert_eq!(select(options), (31, 39));
+        let options = Options {
+            score: Score::Overlapping(3),
+            ..Options::default()
+        };
+        assert_eq!(select(options), (31, 39));
+
+        // A sticky engine that disregards widths keeps the previous interval, which is consistent
+        // with as many sources:
+        let stickiness = Stickiness {
+            min_overlap_gain: 1,
+            min_width_gain: 1.0,
+        };
+        let options = Options {
+            engine: Some(EngineOptions {
+                stickiness,
+                previous: Interval::try_from_source_bounds(bounds(&[(2, 31, 39)])).unwrap(),
+            }),
+            ..Options::default()
+        };
+        assert_eq!(select(options), (31, 39));
+    }
+}
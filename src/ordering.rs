@@ -0,0 +1,100 @@
+//! Strategies to order the bounds of equal values, as a type parameter of
+//! `Interval::try_from_source_bounds_ordered`. The order decides whether intervals that only touch
+//! overlap.
+
+use core::cmp::Ordering;
+
+use crate::{BoundType, SourceBound};
+
+/// A total order of source bounds, which must order bounds by their values first.
+pub trait BoundOrder<T> {
+    fn cmp(a: &SourceBound<T>, b: &SourceBound<T>) -> Ordering;
+}
+
+/// Sorts a lower bound before an upper bound of the same value, so that an interval ending where
+/// another begins overlaps it for no duration. This is the `Ord` of `SourceBound`, and the order
+/// of `Interval::try_from_source_bounds`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct LowerFirst;
+
+impl<T: Ord> BoundOrder<T> for LowerFirst {
+    fn cmp(a: &SourceBound<T>, b: &SourceBound<T>) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Sorts an upper bound before a lower bound of the same value, so that intervals which only touch
+/// do not overlap. A source of no width still overlaps itself, and the other sources of no width
+/// at its value: the sweep moves its bounds between the upper and the lower bounds of the value.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct UpperFirst;
+
+impl<T: Ord> BoundOrder<T> for UpperFirst {
+    fn cmp(a: &SourceBound<T>, b: &SourceBound<T>) -> Ordering {
+        let bound_types = match (a.bound_type, b.bound_type) {
+            (BoundType::Upper, BoundType::Lower) => Ordering::Less,
+            (BoundType::Lower, BoundType::Upper) => Ordering::Greater,
+            _ => Ordering::Equal,
+        };
+        a.value
+            .cmp(&b.value)
+            .then(bound_types)
+            .then(a.source.cmp(&b.source))
+    }
+}
+
+/// A strategy of this module by name, e.g. for the `Options` of a selection.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Order {
+    #[default]
+    LowerFirst,
+    UpperFirst,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+    use crate::Interval;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_bound_order() {
+        // Two intervals that touch at 10, and a third of no width at 30:
+        let source_bounds: Vec<SourceBound> = bounds(&[(0, 0, 10), (1, 10, 20), (2, 30, 30)]);
+        let interval =
+            Interval::try_from_source_bounds_ordered::<LowerFirst>(source_bounds.clone())
+                .unwrap()
+                .unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (10, 10));
+        assert_eq!(interval.sources_true(), 2);
+
+        let interval = Interval::try_from_source_bounds_ordered::<UpperFirst>(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (30, 30));
+        assert_eq!(interval.sources_true(), 1);
+
+        // Sources of no width at one value overlap each other, but not the intervals that only
+        // touch them:
+        let source_bounds: Vec<SourceBound> = bounds(&[
+            (0, 10, 10),
+            (1, 0, 10),
+            (2, 10, 10),
+            (3, 10, 20),
+            (4, 10, 10),
+        ]);
+        let interval =
+            Interval::try_from_source_bounds_ordered::<LowerFirst>(source_bounds.clone())
+                .unwrap()
+                .unwrap();
+        assert_eq!((interval.lower_bound(), interval.sources_true()), (10, 5));
+        let interval = Interval::try_from_source_bounds_ordered::<UpperFirst>(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (10, 10));
+        assert_eq!(interval.sources_true(), 3);
+    }
+}
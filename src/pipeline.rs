@@ -0,0 +1,891 @@
+//! The full mitigation of NTP as one pipeline: the samples of every source pass through its clock
+//! filter, the filtered samples are selected over, the truechimers are pruned by the cluster
+//! algorithm, and the survivors are combined into an estimate. Each stage can be replaced, e.g.
+//! to select with an `Engine` that has observers registered, or to keep more survivors. A
+//! `SendPipeline` only takes stages that are `Send`, so that it can move between threads.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::agreement::Marzullo;
+use crate::clock::{StepDetector, Time};
+use crate::cluster::{self, Candidate, Clustering, MIN_SURVIVORS};
+use crate::combine::{self, Estimate};
+use crate::interop::ConversionError;
+use crate::prefilter::{self, Exclusion, PreFilter};
+use crate::source::filter::PHI;
+use crate::source::{ClockFilter, FilteredSample, Root, Sample};
+use crate::verdict;
+use crate::{Engine, Interval, MarzulloError, SourceBound};
+
+/// The selection stage, e.g. an `Engine`.
+pub trait Select {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError>;
+}
+
+impl Select for Engine {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        Engine::select(self, source_bounds)
+    }
+}
+
+/// The cluster stage, which prunes the truechimers of the selection.
+pub trait Cluster {
+    fn cluster(&mut self, candidates: Vec<Candidate>) -> Clustering;
+}
+
+/// The cluster algorithm of NTP, keeping at least `min_survivors`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct NtpCluster {
+    pub min_survivors: usize,
+}
+
+impl Default for NtpCluster {
+    fn default() -> NtpCluster {
+        NtpCluster {
+            min_survivors: MIN_SURVIVORS,
+        }
+    }
+}
+
+impl Cluster for NtpCluster {
+    fn cluster(&mut self, candidates: Vec<Candidate>) -> Clustering {
+        cluster::cluster(candidates, self.min_survivors)
+    }
+}
+
+/// The combine stage, which estimates the offset from the survivors.
+pub trait Combine {
+    fn combine(&mut self, survivors: &[Candidate]) -> Option<Estimate>;
+}
+
+/// The combine algorithm of NTP, weighting survivors by the inverse of their distance.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct NtpCombine;
+
+impl Combine for NtpCombine {
+    fn combine(&mut self, survivors: &[Candidate]) -> Option<Estimate> {
+        combine::combine(survivors)
+    }
+}
+
+/// The trait objects a `Pipeline` boxes its stages as: `Local` stages, or `Sendable` ones, which
+/// are all `Send`.
+pub trait Stages: 'static {
+    type Time: Time + ?Sized;
+    type PreFilter: PreFilter + ?Sized;
+    type Select: Select + ?Sized;
+    type Cluster: Cluster + ?Sized;
+    type Combine: Combine + ?Sized;
+
+    /// Returns the default select, cluster, and combine stages.
+    #[allow(clippy::type_complexity)]
+    fn defaults() -> (Box<Self::Select>, Box<Self::Cluster>, Box<Self::Combine>);
+}
+
+/// Stages of any type.
+#[derive(Debug)]
+pub enum Local {}
+
+/// Stages that are `Send`.
+#[derive(Debug)]
+pub enum Sendable {}
+
+/// A stage that a pipeline of the `Stages` boxes as the trait object `B`.
+pub trait Stage<B: ?Sized> {
+    fn boxed(self) -> Box<B>;
+}
+
+macro_rules! stage {
+    ($($stage:ident),*) => {$(
+        impl<S: $stage + 'static> Stage<dyn $stage> for S {
+            fn boxed(self) -> Box<dyn $stage> {
+                Box::new(self)
+            }
+        }
+
+        impl<S: $stage + Send + 'static> Stage<dyn $stage + Send> for S {
+            fn boxed(self) -> Box<dyn $stage + Send> {
+                Box::new(self)
+            }
+        }
+    )*};
+}
+
+stage!(Time, PreFilter, Select, Cluster, Combine);
+
+/// The select stage is an `Engine` without observers.
+impl Stages for Local {
+    type Time = dyn Time;
+    type PreFilter = dyn PreFilter;
+    type Select = dyn Select;
+    type Cluster = dyn Cluster;
+    type Combine = dyn Combine;
+
+    fn defaults() -> (Box<dyn Select>, Box<dyn Cluster>, Box<dyn Combine>) {
+        (
+            Box::new(Engine::new()),
+            Box::new(NtpCluster::default()),
+            Box::new(NtpCombine),
+        )
+    }
+}
+
+/// The select stage is `Marzullo`, as an `Engine` without observers, which is not `Send`.
+impl Stages for Sendable {
+    type Time = dyn Time + Send;
+    type PreFilter = dyn PreFilter + Send;
+    type Select = dyn Select + Send;
+    type Cluster = dyn Cluster + Send;
+    type Combine = dyn Combine + Send;
+
+    fn defaults() -> (
+        Box<dyn Select + Send>,
+        Box<dyn Cluster + Send>,
+        Box<dyn Combine + Send>,
+    ) {
+        (
+            Box::new(Marzullo),
+            Box::new(NtpCluster::default()),
+            Box::new(NtpCombine),
+        )
+    }
+}
+
+/// Why a source did not survive.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rejection {
+    /// The bounds of the sample could not be computed.
+    Invalid(ConversionError),
+    /// A pre-filter excluded the source from the selection.
+    Excluded(Exclusion),
+    /// The latest sample of the source is older than the maximum age, by the latest sample of any
+    /// source.
+    Stale { age: i64, max_age: i64 },
+    /// The source's interval does not intersect the chosen interval, which `support` sources
+    /// overlap, and lies `distance` away from it, as `SourceVerdict::distance`: negative below it,
+    /// positive above it.
+    DisjointFromMajority { distance: i64, support: u8 },
+    /// The cluster algorithm discarded the source, with this selection jitter.
+    Clustered { selection_jitter: i64 },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rejected {
+    pub source: u8,
+    pub reason: Rejection,
+}
+
+/// The outcome of every stage of a `Pipeline` run.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectionOutcome {
+    /// The chosen interval, or `None` if there were no valid samples.
+    pub interval: Option<Interval>,
+    /// Every source with a valid sample that no pre-filter excluded, by source, with its root
+    /// delay, root dispersion, and synchronization distance, e.g. to reject sources beyond a
+    /// maximum distance, and its distance from the chosen interval, if there is one.
+    pub candidates: Vec<Candidate>,
+    /// The survivors of the cluster stage, by their distance, the smallest first.
+    pub survivors: Vec<Candidate>,
+    /// The estimate of the combine stage, or `None` if there are no survivors.
+    pub estimate: Option<Estimate>,
+    /// The sources that did not survive, in the order of the stages that rejected them.
+    pub rejected: Vec<Rejected>,
+    /// By how much the realtime clock stepped since the last run, if it did, in which case the
+    /// filters and retained samples were reset before this run.
+    pub step: Option<i64>,
+    /// Whether the sources no longer agree, so that `estimate` is the last estimate of agreeing
+    /// sources extrapolated instead, if the pipeline holds over.
+    pub holdover: Option<Holdover>,
+}
+
+impl SelectionOutcome {
+    /// Returns why the source did not survive, or `None` if it did, or was not sampled.
+    pub fn rejection(&self, source: u8) -> Option<Rejection> {
+        self.rejected
+            .iter()
+            .find(|rejected| rejected.source == source)
+            .map(|rejected| rejected.reason)
+    }
+}
+
+/// How long a `Pipeline` has been holding over.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Holdover {
+    /// The local time of the last estimate of agreeing sources.
+    pub since: i64,
+    /// The time since, in nanoseconds.
+    pub duration: i64,
+}
+
+fn unfiltered(sample: Sample) -> FilteredSample {
+    FilteredSample {
+        sample,
+        jitter: 0,
+        dispersion: sample.uncertainty,
+    }
+}
+
+/// A pipeline of the filter, select, cluster, and combine stages. By default, the stages are those
+/// of NTP, with an `Engine` without observers, or with `Marzullo` for `Sendable` stages.
+pub struct Pipeline<K: Stages = Local> {
+    filters: Option<BTreeMap<u8, ClockFilter>>,
+    /// The frequency tolerance at which retained samples age, if they are retained.
+    tolerance: Option<i64>,
+    /// The latest sample and root of every source, if samples are retained.
+    retained: BTreeMap<u8, (Sample, Root)>,
+    /// The latest local time of any sample.
+    now: i64,
+    /// The maximum age of the latest sample of a source, if samples expire.
+    max_age: Option<i64>,
+    steps: Option<(StepDetector, Box<K::Time>)>,
+    /// The rate at which the error grows in holdover, in parts per million, if the pipeline holds
+    /// over.
+    holdover: Option<i64>,
+    /// The local time and the last estimate of agreeing sources, with the frequency of the
+    /// survivors, in parts per million.
+    agreed: Option<(i64, Estimate, f64)>,
+    prefilters: Vec<Box<K::PreFilter>>,
+    /// The minimum width of the chosen interval, if it is clamped.
+    min_width: Option<u64>,
+    selector: Box<K::Select>,
+    cluster: Box<K::Cluster>,
+    combine: Box<K::Combine>,
+}
+
+/// A `Pipeline` of stages that are `Send`, and so is the pipeline.
+pub type SendPipeline = Pipeline<Sendable>;
+
+impl<K: Stages> Default for Pipeline<K> {
+    fn default() -> Pipeline<K> {
+        let (selector, cluster, combine) = K::defaults();
+        Pipeline {
+            filters: Some(BTreeMap::new()),
+            tolerance: None,
+            retained: BTreeMap::new(),
+            now: i64::MIN,
+            max_age: None,
+            steps: None,
+            holdover: None,
+            agreed: None,
+            prefilters: Vec::new(),
+            min_width: None,
+            selector,
+            cluster,
+            combine,
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+}
+
+impl<K: Stages> Pipeline<K> {
+    /// Passes samples to the select stage as they are, e.g. if they are filtered already.
+    pub fn without_filter(mut self) -> Pipeline<K> {
+        self.filters = None;
+        self
+    }
+
+    /// Retains the latest sample of every source, so that a source that was not sampled in a run
+    /// is still selected over, with the uncertainty of its sample grown by `tolerance` parts per
+    /// million of its age. Its age is relative to the latest sample of any source, so that a
+    /// source that stops responding becomes less and less influential, rather than pinning the
+    /// result with a stale, falsely tight interval.
+    pub fn with_aging(mut self, tolerance: i64) -> Pipeline<K> {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Rejects the sources whose latest sample is older than `max_age` nanoseconds, by the latest
+    /// sample of any source, and forgets their retained samples. Without aging, only the samples
+    /// of a run are selected over, so this only rejects samples that were taken long before the
+    /// others.
+    pub fn with_max_age(mut self, max_age: i64) -> Pipeline<K> {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Checks `time` for steps of the realtime clock before every run, and resets the pipeline if
+    /// it stepped, so that samples from before and after a step are not filtered together.
+    pub fn with_step_detection<M: Stage<K::Time>>(mut self, time: M) -> Pipeline<K> {
+        let mut detector = StepDetector::default();
+        let time = time.boxed();
+        detector.check(time.as_ref());
+        self.steps = Some((detector, time));
+        self
+    }
+
+    /// Holds over when the sources no longer agree: rather than failing, or selecting the interval
+    /// of a minority, runs return the last estimate of a majority of the sources, extrapolated
+    /// with the frequency their clock filters learned, and with its distance grown by `rate` parts
+    /// per million of the time since.
+    pub fn with_holdover(mut self, rate: i64) -> Pipeline<K> {
+        self.holdover = Some(rate);
+        self
+    }
+
+    /// Runs the bounds of every source through `prefilter` before the select stage, after the
+    /// pre-filters added before it.
+    pub fn with_prefilter<P: Stage<K::PreFilter>>(mut self, prefilter: P) -> Pipeline<K> {
+        self.prefilters.push(prefilter.boxed());
+        self
+    }
+
+    /// Widens the chosen interval evenly on both sides to at least `min_width`, so that the outcome
+    /// never claims more precision than the sources can deliver, e.g. when their intervals only
+    /// touch. The sources counted for it are still those of the interval as chosen.
+    pub fn with_min_width(mut self, min_width: u64) -> Pipeline<K> {
+        self.min_width = Some(min_width);
+        self
+    }
+
+    pub fn with_selector<S: Stage<K::Select>>(mut self, selector: S) -> Pipeline<K> {
+        self.selector = selector.boxed();
+        self
+    }
+
+    pub fn with_cluster<C: Stage<K::Cluster>>(mut self, cluster: C) -> Pipeline<K> {
+        self.cluster = cluster.boxed();
+        self
+    }
+
+    pub fn with_combine<C: Stage<K::Combine>>(mut self, combine: C) -> Pipeline<K> {
+        self.combine = combine.boxed();
+        self
+    }
+
+    /// Forgets the samples of every source, e.g. after the local clock was set.
+    pub fn reset(&mut self) {
+        if let Some(filters) = &mut self.filters {
+            filters.clear();
+        }
+        self.retained.clear();
+        self.now = i64::MIN;
+        self.agreed = None;
+    }
+
+    /// Returns the clock filter of a source, once it has been sampled.
+    pub fn filter(&self, source: u8) -> Option<&ClockFilter> {
+        self.filters.as_ref()?.get(&source)
+    }
+
+    /// Runs the stages over the latest sample of every source that was sampled, by its index, for
+    /// sources that are reference clocks or whose samples account for their root already.
+    pub fn run(
+        &mut self,
+        samples: impl IntoIterator<Item = (u8, Sample)>,
+    ) -> Result<SelectionOutcome, MarzulloError> {
+        self.run_with_root(
+            samples
+                .into_iter()
+                .map(|(source, sample)| (source, sample, Root::default())),
+        )
+    }
+
+    /// Runs the stages over the latest sample of every source, with the root delay and root
+    /// dispersion of the source. The bounds of every source span its synchronization distance: the
+    /// uncertainty of its filtered sample plus that of its root.
+    pub fn run_with_root(
+        &mut self,
+        samples: impl IntoIterator<Item = (u8, Sample, Root)>,
+    ) -> Result<SelectionOutcome, MarzulloError> {
+        let step = match &mut self.steps {
+            Some((detector, time)) => detector.check(time.as_ref()),
+            None => None,
+        };
+        if step.is_some() {
+            self.reset();
+        }
+        let mut current = BTreeMap::new();
+        for (source, sample, root) in samples {
+            self.now = self.now.max(sample.local_time);
+            let filtered = match &mut self.filters {
+                Some(filters) => filters
+                    .entry(source)
+                    .or_insert_with(|| {
+                        ClockFilter::new().with_tolerance(self.tolerance.unwrap_or(PHI))
+                    })
+                    .add(sample),
+                None => unfiltered(sample),
+            };
+            if self.tolerance.is_some() {
+                self.retained.insert(source, (sample, root));
+            }
+            current.insert(source, (filtered, root, sample.local_time));
+        }
+        if let Some(tolerance) = self.tolerance {
+            for (&source, &(sample, root)) in &self.retained {
+                let filtered = match self
+                    .filters
+                    .as_ref()
+                    .and_then(|filters| filters.get(&source))
+                {
+                    Some(filter) => filter.best(self.now).expect("the filter holds a sample"),
+                    None => unfiltered(sample.aged(self.now, tolerance)),
+                };
+                current.insert(source, (filtered, root, sample.local_time));
+            }
+        }
+        let mut stale = Vec::new();
+        if let Some(max_age) = self.max_age {
+            current.retain(|&source, &mut (_, _, local_time)| {
+                let age = self.now.saturating_sub(local_time);
+                if age <= max_age {
+                    return true;
+                }
+                self.retained.remove(&source);
+                stale.push(Rejected {
+                    source,
+                    reason: Rejection::Stale { age, max_age },
+                });
+                false
+            });
+        }
+
+        let mut outcome = SelectionOutcome {
+            step,
+            rejected: stale,
+            ..SelectionOutcome::default()
+        };
+        let mut candidates = BTreeMap::new();
+        let mut source_bounds = Vec::new();
+        for (source, (filtered, root, _)) in current {
+            let candidate = Candidate::from_filtered(source, &filtered, root);
+            let distance = Sample {
+                uncertainty: candidate.distance,
+                ..filtered.sample
+            };
+            match distance.bounds(source) {
+                Ok(bounds) => {
+                    source_bounds.extend(bounds);
+                    candidates.insert(source, candidate);
+                }
+                Err(error) => outcome.rejected.push(Rejected {
+                    source,
+                    reason: Rejection::Invalid(error),
+                }),
+            }
+        }
+        for prefilter in &mut self.prefilters {
+            let excluded;
+            (source_bounds, excluded) = prefilter.prefilter(source_bounds);
+            for excluded in excluded {
+                candidates.remove(&excluded.source);
+                outcome.rejected.push(Rejected {
+                    source: excluded.source,
+                    reason: Rejection::Excluded(excluded.reason),
+                });
+            }
+        }
+        outcome.candidates = candidates.values().copied().collect();
+
+        let interval = match self.selector.select(source_bounds) {
+            Err(MarzulloError::NoMajority { .. }) if self.holdover.is_some() => None,
+            result => result?,
+        };
+        let Some(mut interval) = interval else {
+            return Ok(self.hold(outcome));
+        };
+        if let Some(min_width) = self.min_width {
+            (interval.lower_bound, interval.upper_bound) =
+                prefilter::widen(interval.lower_bound, interval.upper_bound, min_width);
+        }
+        // As in NTP, the truechimers are the sources whose intervals intersect the chosen
+        // interval. For Marzullo's algorithm these contain it, but the interval of `Intersection`
+        // may be wider than some of them:
+        let mut truechimers = Vec::with_capacity(candidates.len());
+        for candidate in candidates.values_mut() {
+            let lower = candidate.offset.saturating_sub(candidate.distance);
+            let upper = candidate.offset.saturating_add(candidate.distance);
+            // Offsets always have a distance:
+            let distance = verdict::distance(&lower, &upper, &interval).unwrap_or_default();
+            candidate.interval_distance = Some(distance);
+            if distance == 0 {
+                truechimers.push(*candidate);
+            } else {
+                outcome.rejected.push(Rejected {
+                    source: candidate.source,
+                    reason: Rejection::DisjointFromMajority {
+                        distance,
+                        support: interval.sources_true(),
+                    },
+                });
+            }
+        }
+        outcome.candidates = candidates.into_values().collect();
+        let majority = interval.sources_true() as usize * 2 > outcome.candidates.len();
+        outcome.interval = Some(interval);
+
+        let clustering = self.cluster.cluster(truechimers);
+        outcome
+            .rejected
+            .extend(clustering.discarded.iter().map(|discard| Rejected {
+                source: discard.candidate.source,
+                reason: Rejection::Clustered {
+                    selection_jitter: discard.selection_jitter,
+                },
+            }));
+        outcome.estimate = self.combine.combine(&clustering.survivors);
+        outcome.survivors = clustering.survivors;
+        match outcome.estimate {
+            Some(estimate) if majority => {
+                self.agreed = Some((self.now, estimate, self.frequency(&outcome.survivors)));
+                Ok(outcome)
+            }
+            _ => Ok(self.hold(outcome)),
+        }
+    }
+
+    /// Returns the mean frequency of the survivors whose clock filters learned one.
+    fn frequency(&self, survivors: &[Candidate]) -> f64 {
+        let Some(filters) = &self.filters else {
+            return 0.0;
+        };
+        let frequencies = survivors
+            .iter()
+            .filter_map(|survivor| filters.get(&survivor.source)?.frequency());
+        let (sum, count) = frequencies.fold((0.0, 0), |(sum, count), ppm| (sum + ppm, count + 1));
+        if count == 0 {
+            return 0.0;
+        }
+        sum / count as f64
+    }
+
+    /// Replaces the estimate with the extrapolated last estimate of agreeing sources, if the
+    /// pipeline holds over and has one.
+    fn hold(&self, mut outcome: SelectionOutcome) -> SelectionOutcome {
+        let (Some(rate), Some((since, estimate, frequency))) = (self.holdover, self.agreed) else {
+            return outcome;
+        };
+        let duration = self.now.saturating_sub(since).max(0);
+        let drift = (frequency * duration as f64 / 1e6) as i64;
+        let growth = (duration as i128 * rate as i128 / 1_000_000) as i64;
+        outcome.estimate = Some(Estimate {
+            offset: estimate.offset.saturating_add(drift),
+            jitter: estimate.jitter,
+            distance: estimate.distance.saturating_add(growth),
+        });
+        outcome.holdover = Some(Holdover { since, duration });
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intersection::Intersection;
+    use crate::prefilter::MaxWidth;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    fn sample(offset: i64, uncertainty: i64) -> Sample {
+        Sample {
+            local_time: 0,
+            offset,
+            uncertainty,
+        }
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::new().with_cluster(NtpCluster { min_survivors: 2 });
+        let outcome = pipeline
+            .run([
+                (0, sample(1_000, 500)),
+                (1, sample(1_200, 400)),
+                (2, sample(1_500, 1_000)),
+                (3, sample(9_000, 100)),
+                (4, sample(0, -1)),
+            ])
+            .unwrap();
+        let interval = outcome.interval.unwrap();
+        assert_eq!(
+            (interval.lower_bound(), interval.upper_bound()),
+            (800, 1_500)
+        );
+        assert_eq!(
+            outcome.rejected[..2],
+            [
+                Rejected {
+                    source: 4,
+                    reason: Rejection::Invalid(ConversionError::NegativeDuration),
+                },
+                Rejected {
+                    source: 3,
+                    reason: Rejection::DisjointFromMajority {
+                        distance: 7_400,
+                        support: 3,
+                    },
+                },
+            ]
+        );
+        // The survivors disagree by more than their (zero) jitter, so the cluster stage prunes
+        // down to 2, discarding the most distant:
+        assert_eq!(outcome.rejected[2].source, 2);
+        let sources: Vec<_> = outcome.survivors.iter().map(|s| s.source).collect();
+        assert_eq!(sources, [1, 0]);
+        let estimate = outcome.estimate.unwrap();
+        assert!((1_000..1_200).contains(&estimate.offset));
+        assert_eq!(estimate.distance, 400);
+        assert!(pipeline.filter(0).is_some() && pipeline.filter(3).is_some());
+
+        // The survivors rank by their synchronization distance, including their root:
+        let outcome = Pipeline::new()
+            .without_filter()
+            .run_with_root([
+                (0, sample(1_000, 500), Root::default()),
+                (
+                    1,
+                    sample(1_200, 400),
+                    Root {
+                        delay: 800,
+                        dispersion: 200,
+                    },
+                ),
+                (
+                    2,
+                    sample(1_100, 300),
+                    Root {
+                        delay: 0,
+                        dispersion: 300,
+                    },
+                ),
+                (
+                    3,
+                    sample(-2_000, 100),
+                    Root {
+                        delay: 2_000,
+                        dispersion: 0,
+                    },
+                ),
+            ])
+            .unwrap();
+        let distances: Vec<_> = outcome.candidates.iter().map(|c| c.distance).collect();
+        assert_eq!(distances, [500, 1_000, 600, 1_100]);
+        let distances: Vec<_> = outcome
+            .candidates
+            .iter()
+            .map(|c| c.interval_distance)
+            .collect();
+        assert_eq!(distances, [Some(0), Some(0), Some(0), Some(-1_400)]);
+        assert_eq!(
+            outcome.rejected,
+            [Rejected {
+                source: 3,
+                reason: Rejection::DisjointFromMajority {
+                    distance: -1_400,
+                    support: 3,
+                },
+            }]
+        );
+        let sources: Vec<_> = outcome.survivors.iter().map(|s| s.source).collect();
+        assert_eq!(sources, [0, 2, 1]);
+
+        let outcome = Pipeline::new().without_filter().run([]).unwrap();
+        assert_eq!(outcome, SelectionOutcome::default());
+
+        // With the intersection algorithm, source 2 is a truechimer although it does not contain
+        // the interval:
+        let outcome = Pipeline::new()
+            .with_selector(Intersection)
+            .run([
+                (0, sample(10, 10)),
+                (1, sample(12, 4)),
+                (2, sample(10, 1)),
+                (3, sample(13, 0)),
+            ])
+            .unwrap();
+        assert_eq!(outcome.interval.unwrap().upper_bound(), 13);
+        assert!(outcome
+            .survivors
+            .iter()
+            .any(|survivor| survivor.source == 2));
+
+        let outcome = Pipeline::new()
+            .with_prefilter(MaxWidth::new(1_000))
+            .run([(0, sample(0, 100)), (1, sample(50, 5_000))])
+            .unwrap();
+        assert_eq!(outcome.candidates.len(), 1);
+        assert_eq!(
+            outcome.rejected,
+            [Rejected {
+                source: 1,
+                reason: Rejection::Excluded(Exclusion::TooWide {
+                    width: 10_000,
+                    max_width: 1_000,
+                }),
+            }]
+        );
+
+        let outcome = Pipeline::new()
+            .with_min_width(100)
+            .run([(0, sample(0, 10)), (1, sample(20, 10))])
+            .unwrap();
+        let interval = outcome.interval.unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (-40, 60));
+        assert_eq!(interval.sources_true(), 2);
+    }
+
+    #[test]
+    fn test_pipeline_aging() {
+        const SECOND: i64 = 1_000_000_000;
+        let at = |local_time: i64, offset: i64| Sample {
+            local_time,
+            offset,
+            uncertainty: 1_000,
+        };
+        let mut pipeline = Pipeline::new().without_filter().with_aging(15);
+        pipeline
+            .run([(0, at(0, 0)), (1, at(0, 100)), (2, at(0, 200))])
+            .unwrap();
+        // Source 0 stopped responding 10s ago, so its uncertainty grew by 150µs:
+        let outcome = pipeline
+            .run([(1, at(10 * SECOND, 100)), (2, at(10 * SECOND, 200))])
+            .unwrap();
+        let distances: Vec<_> = outcome.candidates.iter().map(|c| c.distance).collect();
+        assert_eq!(distances, [151_000, 1_000, 1_000]);
+
+        // Unless it expires once it is 5s old:
+        let mut pipeline = Pipeline::new()
+            .without_filter()
+            .with_aging(15)
+            .with_max_age(5 * SECOND);
+        pipeline
+            .run([(0, at(0, 0)), (1, at(0, 100)), (2, at(0, 200))])
+            .unwrap();
+        let outcome = pipeline
+            .run([(1, at(10 * SECOND, 100)), (2, at(10 * SECOND, 200))])
+            .unwrap();
+        assert_eq!(outcome.candidates.len(), 2);
+        assert_eq!(
+            outcome.rejection(0),
+            Some(Rejection::Stale {
+                age: 10 * SECOND,
+                max_age: 5 * SECOND,
+            })
+        );
+        assert_eq!(outcome.rejection(1), None);
+
+        // Without aging, it is not selected over at all:
+        let mut pipeline = Pipeline::new().without_filter();
+        pipeline.run([(0, at(0, 0))]).unwrap();
+        let outcome = pipeline.run([(1, at(SECOND, 0))]).unwrap();
+        assert_eq!(outcome.candidates.len(), 1);
+    }
+
+    #[test]
+    fn test_pipeline_holdover() {
+        const SECOND: i64 = 1_000_000_000;
+        let at = |local_time: i64, offset: i64| Sample {
+            local_time,
+            offset,
+            uncertainty: 1_000,
+        };
+        let mut pipeline = Pipeline::new()
+            .with_selector(Intersection)
+            .with_holdover(10);
+        // The sources agree on an offset that grows by 2µs per second, 2ppm:
+        for poll in 0..4 {
+            let time = poll * 16 * SECOND;
+            let offset = 5_000 + poll * 32_000;
+            let outcome = pipeline
+                .run([(0, at(time, offset)), (1, at(time, offset + 100))])
+                .unwrap();
+            assert_eq!(outcome.holdover, None);
+        }
+        let agreed = pipeline.agreed.unwrap().1;
+
+        // Then they disagree, 100s after the last agreement:
+        let time = 48 * SECOND + 100 * SECOND;
+        let outcome = pipeline
+            .run([(0, at(time, 0)), (1, at(time, 1_000_000))])
+            .unwrap();
+        assert_eq!(
+            outcome.holdover,
+            Some(Holdover {
+                since: 48 * SECOND,
+                duration: 100 * SECOND,
+            })
+        );
+        let estimate = outcome.estimate.unwrap();
+        assert!((estimate.offset - agreed.offset - 200_000).abs() < 1_000);
+        assert_eq!(estimate.distance, agreed.distance + 1_000_000);
+
+        // Without holdover, the disagreement is an error:
+        let mut pipeline = Pipeline::new().with_selector(Intersection);
+        assert!(pipeline
+            .run([(0, at(0, 0)), (1, at(0, 1_000_000))])
+            .is_err());
+    }
+
+    #[test]
+    fn test_send_pipeline() {
+        fn send<S: Send>(pipeline: S) -> S {
+            pipeline
+        }
+        let samples = [(0, sample(10, 10)), (1, sample(12, 4)), (2, sample(30, 1))];
+        // The default stages select as those of a `Pipeline`:
+        let mut pipeline = send(SendPipeline::default().with_prefilter(MaxWidth::new(1_000)));
+        assert_eq!(
+            pipeline.run(samples).unwrap(),
+            Pipeline::new()
+                .with_prefilter(MaxWidth::new(1_000))
+                .run(samples)
+                .unwrap()
+        );
+        let mut pipeline = send(SendPipeline::default().with_selector(Intersection));
+        assert_eq!(
+            pipeline
+                .run(samples)
+                .unwrap()
+                .interval
+                .unwrap()
+                .sources_true(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_pipeline_step() {
+        struct SteppedTime(Rc<Cell<i64>>);
+
+        impl Time for SteppedTime {
+            fn monotonic(&self) -> u64 {
+                0
+            }
+
+            fn realtime(&self) -> i64 {
+                self.0.get()
+            }
+        }
+
+        let realtime = Rc::new(Cell::new(0));
+        let mut pipeline = Pipeline::new().with_step_detection(SteppedTime(Rc::clone(&realtime)));
+        let outcome = pipeline.run([(0, sample(0, 10))]).unwrap();
+        assert_eq!(outcome.step, None);
+        realtime.set(-3_600_000_000_000);
+        let outcome = pipeline.run([(0, sample(500, 10))]).unwrap();
+        assert_eq!(outcome.step, Some(-3_600_000_000_000));
+        // The sample from before the step is gone:
+        assert_eq!(outcome.estimate.unwrap().offset, 500);
+        assert_eq!(pipeline.filter(0).unwrap().best(0).unwrap().jitter, 0);
+    }
+}
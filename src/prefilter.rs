@@ -0,0 +1,298 @@
+//! Pre-filters, which exclude sources from a selection before it runs, and report why. A source
+//! that is excluded is neither counted in `sources_true` nor in `sources_false`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::verdict::source_intervals;
+use crate::{BoundType, SourceBound};
+
+/// Why a pre-filter excluded a source.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Exclusion {
+    /// The interval of the source is wider than the maximum, so that it would overlap everything
+    /// without narrowing anything down.
+    TooWide { width: u64, max_width: u64 },
+    /// The interval of the source is further from the local estimate than the panic threshold, so
+    /// that the source is insane rather than merely wrong.
+    Insane { distance: u64, max_distance: u64 },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Excluded {
+    pub source: u8,
+    pub reason: Exclusion,
+}
+
+/// A pre-filter of the bounds of a selection.
+pub trait PreFilter {
+    /// Returns the bounds to select over, and the sources excluded from them.
+    fn prefilter(&mut self, source_bounds: Vec<SourceBound>) -> (Vec<SourceBound>, Vec<Excluded>);
+}
+
+/// Drops the bounds of the excluded sources.
+pub(crate) fn exclude(source_bounds: &mut Vec<SourceBound>, excluded: &[Excluded]) {
+    source_bounds.retain(|bound| {
+        !excluded
+            .iter()
+            .any(|excluded| excluded.source == bound.source)
+    });
+}
+
+/// Returns `[lower, upper]` widened evenly on both sides to at least `min_width`. What the bounds
+/// of `i64` cut off one side is pushed onto the other. An inverted interval is left as it is, for
+/// the selection to reject.
+pub(crate) fn widen(lower: i64, upper: i64, min_width: u64) -> (i64, i64) {
+    if lower > upper {
+        return (lower, upper);
+    }
+    let Some(missing) = min_width.checked_sub(upper.abs_diff(lower)) else {
+        return (lower, upper);
+    };
+    let (min, max) = (i128::from(i64::MIN), i128::from(i64::MAX));
+    let missing = i128::from(missing);
+    let mut lower = i128::from(lower) - missing / 2;
+    let mut upper = i128::from(upper) + (missing - missing / 2);
+    if lower < min {
+        upper += min - lower;
+        lower = min;
+    }
+    if upper > max {
+        lower = (lower - (upper - max)).max(min);
+        upper = max;
+    }
+    (lower as i64, upper as i64)
+}
+
+/// Excludes the sources whose intervals are wider than a maximum, by default or per source.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MaxWidth {
+    max_width: u64,
+    sources: BTreeMap<u8, u64>,
+}
+
+impl MaxWidth {
+    pub fn new(max_width: u64) -> MaxWidth {
+        MaxWidth {
+            max_width,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Allows a different maximum width for the source, e.g. a reference clock that is known to be
+    /// more or less precise than the others.
+    pub fn with_source(mut self, source: u8, max_width: u64) -> MaxWidth {
+        self.sources.insert(source, max_width);
+        self
+    }
+}
+
+impl PreFilter for MaxWidth {
+    fn prefilter(
+        &mut self,
+        mut source_bounds: Vec<SourceBound>,
+    ) -> (Vec<SourceBound>, Vec<Excluded>) {
+        let excluded: Vec<Excluded> = source_intervals(&source_bounds)
+            .into_iter()
+            .filter_map(|(source, lower, upper)| {
+                let width = upper.abs_diff(lower);
+                let max_width = *self.sources.get(&source).unwrap_or(&self.max_width);
+                (width > max_width).then_some(Excluded {
+                    source,
+                    reason: Exclusion::TooWide { width, max_width },
+                })
+            })
+            .collect();
+        exclude(&mut source_bounds, &excluded);
+        (source_bounds, excluded)
+    }
+}
+
+/// The panic threshold of ntpd: no sane source is 1000s off the local clock.
+pub const PANIC_THRESHOLD: u64 = 1_000_000_000_000;
+
+/// Excludes the sources whose intervals lie further than a maximum distance from the local
+/// estimate of the offset, as the sanity checks of ntpd reject insane servers.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Sanity {
+    estimate: i64,
+    max_distance: u64,
+}
+
+impl Default for Sanity {
+    fn default() -> Sanity {
+        Sanity::new(PANIC_THRESHOLD)
+    }
+}
+
+impl Sanity {
+    /// Excludes sources beyond `max_distance` of the local clock, at an offset of zero.
+    pub fn new(max_distance: u64) -> Sanity {
+        Sanity {
+            estimate: 0,
+            max_distance,
+        }
+    }
+
+    /// Measures the distance from this estimate of the offset of the local clock instead, e.g. the
+    /// last estimate of a `Pipeline` while the local clock is not yet set.
+    pub fn with_estimate(mut self, estimate: i64) -> Sanity {
+        self.estimate = estimate;
+        self
+    }
+
+    pub fn set_estimate(&mut self, estimate: i64) {
+        self.estimate = estimate;
+    }
+}
+
+impl PreFilter for Sanity {
+    fn prefilter(
+        &mut self,
+        mut source_bounds: Vec<SourceBound>,
+    ) -> (Vec<SourceBound>, Vec<Excluded>) {
+        let excluded: Vec<Excluded> = source_intervals(&source_bounds)
+            .into_iter()
+            .filter_map(|(source, lower, upper)| {
+                let distance = if self.estimate < lower {
+                    lower.abs_diff(self.estimate)
+                } else if self.estimate > upper {
+                    self.estimate.abs_diff(upper)
+                } else {
+                    0
+                };
+                (distance > self.max_distance).then_some(Excluded {
+                    source,
+                    reason: Exclusion::Insane {
+                        distance,
+                        max_distance: self.max_distance,
+                    },
+                })
+            })
+            .collect();
+        exclude(&mut source_bounds, &excluded);
+        (source_bounds, excluded)
+    }
+}
+
+/// Widens the intervals of the sources to at least a precision floor, around their midpoints, and
+/// excludes none. Sources that truncate their uncertainty may claim intervals of no width at all,
+/// which would otherwise pin the chosen interval to a single value.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Precision {
+    floor: u64,
+}
+
+impl Precision {
+    pub fn new(floor: u64) -> Precision {
+        Precision { floor }
+    }
+}
+
+impl PreFilter for Precision {
+    fn prefilter(
+        &mut self,
+        mut source_bounds: Vec<SourceBound>,
+    ) -> (Vec<SourceBound>, Vec<Excluded>) {
+        let widened: BTreeMap<u8, (i64, i64)> = source_intervals(&source_bounds)
+            .into_iter()
+            .map(|(source, lower, upper)| (source, widen(lower, upper, self.floor)))
+            .collect();
+        for bound in &mut source_bounds {
+            if let Some(&(lower, upper)) = widened.get(&bound.source) {
+                bound.value = match bound.bound_type {
+                    BoundType::Lower => lower,
+                    BoundType::Upper => upper,
+                };
+            }
+        }
+        (source_bounds, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+    use crate::Interval;
+
+    #[test]
+    fn test_max_width() {
+        let source_bounds: Vec<SourceBound> = bounds(&[(0, 8, 12), (1, 9, 13), (2, -1_000, 1_000)]);
+        let (bounds, excluded) = MaxWidth::new(100).prefilter(source_bounds.clone());
+        assert_eq!(
+            excluded,
+            [Excluded {
+                source: 2,
+                reason: Exclusion::TooWide {
+                    width: 2_000,
+                    max_width: 100,
+                },
+            }]
+        );
+        let interval = Interval::try_from_source_bounds(bounds).unwrap().unwrap();
+        assert_eq!((interval.sources_true(), interval.sources_false()), (2, 0));
+
+        let (_, excluded) = MaxWidth::new(100)
+            .with_source(2, 2_000)
+            .prefilter(source_bounds);
+        assert!(excluded.is_empty());
+    }
+
+    #[test]
+    fn test_sanity() {
+        const SECOND: i64 = 1_000_000_000;
+        // A server that is a day off, one a second off, and one that contains the local clock:
+        let source_bounds: Vec<SourceBound> = bounds(&[
+            (0, 86_400 * SECOND, 86_401 * SECOND),
+            (1, SECOND, 2 * SECOND),
+            (2, -SECOND, SECOND),
+        ]);
+        let (bounds, excluded) = Sanity::default().prefilter(source_bounds.clone());
+        assert_eq!(
+            excluded,
+            [Excluded {
+                source: 0,
+                reason: Exclusion::Insane {
+                    distance: 86_400 * SECOND as u64,
+                    max_distance: PANIC_THRESHOLD,
+                },
+            }]
+        );
+        assert_eq!(bounds.len(), 4);
+
+        // Against an estimate a day ahead, the others are insane instead:
+        let (_, excluded) = Sanity::default()
+            .with_estimate(86_400 * SECOND)
+            .prefilter(source_bounds);
+        let sources: Vec<u8> = excluded.iter().map(|excluded| excluded.source).collect();
+        assert_eq!(sources, [1, 2]);
+    }
+
+    #[test]
+    fn test_precision() {
+        let source_bounds: Vec<SourceBound> = bounds(&[(0, 10, 10), (1, 11, 12), (2, 0, 100)]);
+        let (bounds, excluded) = Precision::new(10).prefilter(source_bounds);
+        assert!(excluded.is_empty());
+        assert_eq!(
+            source_intervals(&bounds),
+            [(0, 5, 15), (1, 7, 17), (2, 0, 100)]
+        );
+        // Rather than the single instant of source 0 that the others contain:
+        let interval = Interval::try_from_source_bounds(bounds).unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (7, 15));
+        assert_eq!(widen(i64::MAX, i64::MAX, 10), (i64::MAX - 10, i64::MAX));
+        assert_eq!(widen(i64::MIN, i64::MIN + 1, 10), (i64::MIN, i64::MIN + 10));
+        assert_eq!(widen(0, 0, u64::MAX), (i64::MIN, i64::MAX));
+        assert_eq!(widen(i64::MAX, i64::MAX, u64::MAX), (i64::MIN, i64::MAX));
+
+        // An inverted source is left for the selection to reject:
+        let (inverted, _) = Precision::new(100).prefilter(crate::testing::bounds(&[(0, 10, 5)]));
+        assert_eq!(source_intervals(&inverted), [(0, 10, 5)]);
+        assert!(Interval::try_from_source_bounds(inverted).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Byzantine quorums: to tolerate `f` faulty clocks, which may report anything, and differently to
+//! every reader, a deployment needs `3f + 1` sources, and an agreed interval needs the support of a
+//! quorum of `2f + 1` of them, so that the correct sources among any two quorums still overlap.
+
+use alloc::vec::Vec;
+
+use crate::pipeline::Select;
+use crate::{Engine, Interval, MarzulloError, Pipeline, SourceBound};
+
+/// Why a deployment or a selection falls short of a `ByzantineQuorum`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum QuorumError {
+    /// There are too few sources to tolerate the faults at all.
+    TooFewSources {
+        sources: usize,
+        required: usize,
+        faults: usize,
+    },
+    /// Fewer sources than the quorum support the chosen interval.
+    NoQuorum { support: u8, quorum: usize },
+}
+
+impl core::fmt::Display for QuorumError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            QuorumError::TooFewSources {
+                sources,
+                required,
+                faults,
+            } => write!(
+                f,
+                "{} sources cannot tolerate {} faulty clocks, which requires {}",
+                sources, faults, required
+            ),
+            QuorumError::NoQuorum { support, quorum } => write!(
+                f,
+                "only {} sources support the interval, short of a quorum of {}",
+                support, quorum
+            ),
+        }
+    }
+}
+
+impl core::error::Error for QuorumError {}
+
+/// The sources and quorum needed to tolerate a number of faulty clocks.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ByzantineQuorum {
+    faults: usize,
+}
+
+impl ByzantineQuorum {
+    pub fn new(faults: usize) -> ByzantineQuorum {
+        ByzantineQuorum { faults }
+    }
+
+    pub fn faults(&self) -> usize {
+        self.faults
+    }
+
+    /// The number of sources needed, `3f + 1`.
+    pub fn required_sources(&self) -> usize {
+        3 * self.faults + 1
+    }
+
+    /// The number of sources that must support a chosen interval, `2f + 1`.
+    pub fn quorum(&self) -> usize {
+        2 * self.faults + 1
+    }
+
+    /// Checks that a deployment of `sources` sources can tolerate the faults.
+    pub fn validate(&self, sources: usize) -> Result<(), QuorumError> {
+        if sources < self.required_sources() {
+            return Err(QuorumError::TooFewSources {
+                sources,
+                required: self.required_sources(),
+                faults: self.faults,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that a quorum supports the interval.
+    pub fn check(&self, interval: &Interval) -> Result<(), QuorumError> {
+        if (interval.sources_true() as usize) < self.quorum() {
+            return Err(QuorumError::NoQuorum {
+                support: interval.sources_true(),
+                quorum: self.quorum(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Validates the number of sources of a deployment, and returns `pipeline` selecting with an
+    /// `Engine` that requires a quorum.
+    pub fn configure(&self, pipeline: Pipeline, sources: usize) -> Result<Pipeline, QuorumError> {
+        self.validate(sources)?;
+        Ok(pipeline.with_selector(Quorum::new(Engine::new(), *self)))
+    }
+}
+
+/// A select stage that fails with `MarzulloError::NoMajority` unless a quorum supports the
+/// interval chosen by its inner stage. Sources that were not sampled count against the quorum as
+/// much as those that disagree.
+pub struct Quorum<S> {
+    selector: S,
+    quorum: ByzantineQuorum,
+}
+
+impl<S: Select> Quorum<S> {
+    pub fn new(selector: S, quorum: ByzantineQuorum) -> Quorum<S> {
+        Quorum { selector, quorum }
+    }
+}
+
+impl<S: Select> Select for Quorum<S> {
+    fn select(
+        &mut self,
+        source_bounds: Vec<SourceBound>,
+    ) -> Result<Option<Interval>, MarzulloError> {
+        let sources = source_bounds.len() / 2;
+        match self.selector.select(source_bounds)? {
+            Some(interval) if self.quorum.check(&interval).is_err() => {
+                Err(MarzulloError::NoMajority { sources })
+            }
+            interval => Ok(interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Sample;
+
+    #[test]
+    fn test_byzantine_quorum() {
+        let quorum = ByzantineQuorum::new(1);
+        assert_eq!((quorum.required_sources(), quorum.quorum()), (4, 3));
+        assert_eq!(
+            quorum.configure(Pipeline::new(), 3).err(),
+            Some(QuorumError::TooFewSources {
+                sources: 3,
+                required: 4,
+                faults: 1,
+            })
+        );
+
+        let sample = |offset| Sample {
+            local_time: 0,
+            offset,
+            uncertainty: 10,
+        };
+        let mut pipeline = quorum.configure(Pipeline::new(), 4).unwrap();
+        let outcome = pipeline
+            .run([
+                (0, sample(0)),
+                (1, sample(5)),
+                (2, sample(8)),
+                (3, sample(500)),
+            ])
+            .unwrap();
+        assert_eq!(outcome.interval.unwrap().sources_true(), 3);
+        // Two pairs that agree among themselves are no quorum:
+        assert_eq!(
+            Pipeline::new()
+                .with_selector(Quorum::new(Engine::new(), quorum))
+                .run([
+                    (0, sample(0)),
+                    (1, sample(5)),
+                    (2, sample(500)),
+                    (3, sample(505))
+                ])
+                .err(),
+            Some(MarzulloError::NoMajority { sources: 4 })
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Conversions of the chosen interval into standard library ranges, for containment checks and
+//! clamping with range-based code.
+
+use core::ops::{Range, RangeInclusive};
+
+use crate::interop::ConversionError;
+use crate::{Interval, Value};
+
+impl<T: Value> Interval<T> {
+    pub fn to_range_inclusive(&self) -> RangeInclusive<T> {
+        self.lower_bound.clone()..=self.upper_bound.clone()
+    }
+}
+
+impl<T: Value> From<Interval<T>> for RangeInclusive<T> {
+    fn from(interval: Interval<T>) -> RangeInclusive<T> {
+        interval.lower_bound..=interval.upper_bound
+    }
+}
+
+macro_rules! impl_range_for_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl Interval<$ty> {
+                /// Returns the half-open range of the interval, or `OutOfRange` if the upper bound
+                /// is the largest value of the type, so that there is no exclusive end.
+                pub fn to_range(&self) -> Result<Range<$ty>, ConversionError> {
+                    let end = self
+                        .upper_bound
+                        .checked_add(1)
+                        .ok_or(ConversionError::OutOfRange)?;
+                    Ok(self.lower_bound..end)
+                }
+            }
+
+            impl TryFrom<Interval<$ty>> for Range<$ty> {
+                type Error = ConversionError;
+
+                fn try_from(interval: Interval<$ty>) -> Result<Range<$ty>, ConversionError> {
+                    interval.to_range()
+                }
+            }
+        )*
+    };
+}
+
+impl_range_for_integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_range_conversions() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.to_range_inclusive(), 11..=12);
+        assert_eq!(interval.to_range(), Ok(11..13));
+        assert_eq!(Range::try_from(interval.clone()), Ok(11..13));
+        let range = RangeInclusive::from(interval);
+        assert!(range.contains(&12));
+        assert_eq!(20.clamp(*range.start(), *range.end()), 12);
+
+        let source_bounds = Vec::from(SourceBound::pair_optional(0, Some(5), None));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.to_range_inclusive(), 5..=i64::MAX);
+        assert_eq!(interval.to_range(), Err(ConversionError::OutOfRange));
+    }
+}
@@ -0,0 +1,190 @@
+//! Agreement in several dimensions at once, e.g. on the offset and the frequency error of a clock:
+//! every source bounds a box, axis-aligned, and the chosen region is the smallest box consistent
+//! with the most sources, as the interval of Marzullo's algorithm is in one dimension.
+//!
+//! Boxes that intersect pairwise all intersect, so the sources consistent with a region are those
+//! whose boxes contain its lower corner, which is made of lower bounds of the sources. The search
+//! tries every such corner, so that it takes `O(m^(N+1) N)` time for `m` sources: it is meant for
+//! the few dimensions and the few sources of clock selection.
+
+use alloc::vec::Vec;
+
+/// The box of one source, with the lower and upper bound of every dimension.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SourceBox<const N: usize> {
+    pub source: u8,
+    pub lower: [i64; N],
+    pub upper: [i64; N],
+}
+
+impl<const N: usize> SourceBox<N> {
+    pub fn new(source: u8, lower: [i64; N], upper: [i64; N]) -> SourceBox<N> {
+        SourceBox {
+            source,
+            lower,
+            upper,
+        }
+    }
+
+    fn contains(&self, point: &[i64; N]) -> bool {
+        (0..N).all(|i| self.lower[i] <= point[i] && point[i] <= self.upper[i])
+    }
+}
+
+/// The box consistent with the most sources.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Region<const N: usize> {
+    lower_bound: [i64; N],
+    upper_bound: [i64; N],
+    sources_true: u8,
+    sources_false: u8,
+}
+
+impl<const N: usize> Region<N> {
+    pub fn lower_bound(&self) -> [i64; N] {
+        self.lower_bound
+    }
+
+    pub fn upper_bound(&self) -> [i64; N] {
+        self.upper_bound
+    }
+
+    /// The number of sources whose boxes contain the region.
+    pub fn sources_true(&self) -> u8 {
+        self.sources_true
+    }
+
+    /// The number of sources whose boxes do not contain the region.
+    pub fn sources_false(&self) -> u8 {
+        self.sources_false
+    }
+
+    /// The width of the region in every dimension.
+    pub fn width(&self) -> [u64; N] {
+        core::array::from_fn(|i| self.upper_bound[i].abs_diff(self.lower_bound[i]))
+    }
+
+    /// The product of the widths, for comparing regions by their size.
+    fn volume(&self) -> f64 {
+        self.width().iter().map(|&width| width as f64).product()
+    }
+}
+
+/// Returns the smallest region consistent with the most sources, or `None` if there are no
+/// sources at all. A box whose lower bound lies above its upper bound in any dimension is
+/// consistent with no region. Of regions of the same size and support, the first found is chosen.
+///
+/// # Panics
+///
+/// Panics if there are more than 256 sources.
+pub fn select<const N: usize>(boxes: &[SourceBox<N>]) -> Option<Region<N>> {
+    assert!(boxes.len() <= 256, "at most 256 sources");
+    if boxes.is_empty() || N == 0 {
+        return None;
+    }
+    let corners: [Vec<i64>; N] = core::array::from_fn(|i| {
+        let mut values: Vec<i64> = boxes.iter().map(|source| source.lower[i]).collect();
+        values.sort_unstable();
+        values.dedup();
+        values
+    });
+
+    let mut best: Option<Region<N>> = None;
+    let mut indices = [0_usize; N];
+    loop {
+        let corner: [i64; N] = core::array::from_fn(|i| corners[i][indices[i]]);
+        let consistent: Vec<&SourceBox<N>> = boxes
+            .iter()
+            .filter(|source| source.contains(&corner))
+            .collect();
+        if !consistent.is_empty() {
+            let region = Region {
+                // The corner itself may lie above the intersection, in dimensions where no
+                // consistent source has its lower bound at the corner:
+                lower_bound: core::array::from_fn(|i| {
+                    consistent
+                        .iter()
+                        .map(|source| source.lower[i])
+                        .max()
+                        .unwrap()
+                }),
+                upper_bound: core::array::from_fn(|i| {
+                    consistent
+                        .iter()
+                        .map(|source| source.upper[i])
+                        .min()
+                        .unwrap()
+                }),
+                sources_true: consistent.len() as u8,
+                sources_false: (boxes.len() - consistent.len()) as u8,
+            };
+            let better = match &best {
+                None => true,
+                Some(best) => {
+                    region.sources_true > best.sources_true
+                        || (region.sources_true == best.sources_true
+                            && region.volume() < best.volume())
+                }
+            };
+            if better {
+                best = Some(region);
+            }
+        }
+
+        // The next corner, as an odometer over the lower bounds of every dimension:
+        let mut dimension = 0;
+        loop {
+            indices[dimension] += 1;
+            if indices[dimension] < corners[dimension].len() {
+                break;
+            }
+            indices[dimension] = 0;
+            dimension += 1;
+            if dimension == N {
+                return best;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interval, SourceBound};
+
+    #[test]
+    fn test_select_region() {
+        assert_eq!(select::<2>(&[]), None);
+        // Offsets in nanoseconds, and frequency errors in parts per billion. Sources 0 to 2 agree
+        // on both; source 3 agrees on the offset only, and source 4 on the frequency only:
+        let boxes = [
+            SourceBox::new(0, [0, -50], [100, 50]),
+            SourceBox::new(1, [20, -20], [120, 80]),
+            SourceBox::new(2, [40, -40], [90, 30]),
+            SourceBox::new(3, [30, 500], [60, 600]),
+            SourceBox::new(4, [5_000, 0], [6_000, 10]),
+        ];
+        let region = select(&boxes).unwrap();
+        assert_eq!(region.lower_bound(), [40, -20]);
+        assert_eq!(region.upper_bound(), [90, 30]);
+        assert_eq!((region.sources_true(), region.sources_false()), (3, 2));
+
+        // In one dimension, the region is the interval of Marzullo's algorithm:
+        let intervals = [(8, 12), (11, 13), (14, 15), (10, 14)];
+        let boxes: Vec<_> = (0..4)
+            .map(|i| SourceBox::new(i as u8, [intervals[i].0], [intervals[i].1]))
+            .collect();
+        let source_bounds: Vec<SourceBound> = (0..4)
+            .flat_map(|i| SourceBound::pair(i as u8, intervals[i].0, intervals[i].1))
+            .collect();
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        let region = select(&boxes).unwrap();
+        assert_eq!(
+            (region.lower_bound(), region.upper_bound()),
+            ([interval.lower_bound()], [interval.upper_bound()])
+        );
+        assert_eq!(region.sources_true(), interval.sources_true());
+    }
+}
@@ -0,0 +1,145 @@
+//! Capturing a computation for bug reports. A capture records the library version, the exact
+//! input and options, and the output of a selection in a single self-describing JSON blob, and
+//! `replay` re-runs the selection from the blob, so that a report of a wrong interval comes with a
+//! reproducible artifact.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::options::Options;
+use crate::{Interval, MarzulloError, SourceBound, Value};
+
+/// The name that identifies a capture blob.
+pub const CAPTURE_FORMAT: &str = "marzullo-capture";
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub struct Capture<T = i64> {
+    /// Always `CAPTURE_FORMAT`.
+    pub format: String,
+    /// The version of the crate that produced the output.
+    pub version: String,
+    /// The options of the selection, as a `Report` records them. Captures without options ran
+    /// with the default options.
+    #[serde(default)]
+    pub options: Options<T>,
+    pub source_bounds: Vec<SourceBound<T>>,
+    pub output: Result<Option<Interval<T>>, MarzulloError<T>>,
+}
+
+impl<T: Value> Capture<T> {
+    /// Runs the algorithm over `source_bounds` and captures the input and output.
+    pub fn new(source_bounds: Vec<SourceBound<T>>) -> Capture<T> {
+        Capture::with_options(source_bounds, Options::default())
+    }
+
+    /// Runs the algorithm over `source_bounds` with `options`, e.g. those of `Engine::options`, and
+    /// captures the input, options, and output.
+    pub fn with_options(source_bounds: Vec<SourceBound<T>>, options: Options<T>) -> Capture<T> {
+        Capture {
+            format: String::from(CAPTURE_FORMAT),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            output: options.select(source_bounds.clone()),
+            options,
+            source_bounds,
+        }
+    }
+}
+
+impl<T: Value + Serialize> Capture<T> {
+    pub fn to_blob(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// A capture and the output of re-running it with this version of the crate.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Replay<T = i64> {
+    pub capture: Capture<T>,
+    pub output: Result<Option<Interval<T>>, MarzulloError<T>>,
+}
+
+impl<T: Value> Replay<T> {
+    /// Whether the output is the captured output.
+    pub fn is_reproduced(&self) -> bool {
+        self.output == self.capture.output
+    }
+}
+
+/// Re-runs the selection captured in `blob`, with its options.
+pub fn replay<T: Value + DeserializeOwned>(blob: &str) -> Result<Replay<T>, serde_json::Error> {
+    let capture: Capture<T> = serde_json::from_str(blob)?;
+    if capture.format != CAPTURE_FORMAT {
+        return Err(serde::de::Error::custom("not a marzullo capture"));
+    }
+    Ok(Replay {
+        output: capture.options.select(capture.source_bounds.clone()),
+        capture,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::EngineOptions;
+    use crate::ordering::Order;
+    use crate::tiebreak::Score;
+    use crate::Stickiness;
+
+    #[test]
+    fn test_capture_and_replay() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        let capture = Capture::new(source_bounds);
+        let blob = capture.to_blob().unwrap();
+        assert!(blob.contains(r#""format":"marzullo-capture""#));
+
+        let replayed: Replay = replay(&blob).unwrap();
+        assert!(replayed.is_reproduced());
+        assert_eq!(replayed.capture, capture);
+        assert_eq!(replayed.output.unwrap().unwrap().upper_bound(), 12);
+
+        // A capture whose recorded output differs from the current output:
+        let mut capture = capture;
+        capture.output = Ok(None);
+        let replayed: Replay = replay(&capture.to_blob().unwrap()).unwrap();
+        assert!(!replayed.is_reproduced());
+
+        assert!(replay::<i64>(
+            r#"{"format":"other","version":"0","source_bounds":[],"output":{"Ok":null}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_replay_options() {
+        // Two intervals that touch at 10, and a disjoint pair with source 3:
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 0, 10));
+        source_bounds.extend(SourceBound::pair(1, 10, 20));
+        source_bounds.extend(SourceBound::pair(2, 30, 40));
+        source_bounds.extend(SourceBound::pair(3, 31, 39));
+        let options = Options {
+            order: Order::UpperFirst,
+            score: Score::Overlapping(3),
+            engine: Some(EngineOptions {
+                stickiness: Stickiness::default(),
+                previous: Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, 32, 38)))
+                    .unwrap(),
+            }),
+        };
+        let capture = Capture::with_options(source_bounds.clone(), options);
+        let interval = capture.output.clone().unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (32, 38));
+
+        let replayed: Replay = replay(&capture.to_blob().unwrap()).unwrap();
+        assert!(replayed.is_reproduced());
+        assert_eq!(replayed.capture, capture);
+        // Without the options, the selection differs:
+        assert_ne!(Capture::new(source_bounds).output, capture.output);
+    }
+}
@@ -0,0 +1,252 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::options::Options;
+use crate::verdict::{source_intervals, SourceVerdict, Verdict};
+use crate::{Interval, MarzulloError, SourceBound, Value};
+
+/// The outcome of a selection together with the verdict of every source, for dashboards and log
+/// aggregators.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Width: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Width: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Report<T: Value = i64> {
+    /// The chosen interval, or `None` if there were no sources.
+    pub interval: Option<Interval<T>>,
+    pub width: Option<T::Width>,
+    pub sources: usize,
+    pub sources_true: u8,
+    pub sources_false: u8,
+    /// The verdict of every source, ordered by source.
+    pub verdicts: Vec<SourceVerdict<T>>,
+    /// The options the selection ran with.
+    pub options: Options<T>,
+}
+
+impl<T: Value> Report<T> {
+    /// Builds the report of an interval chosen from `source_bounds` with `options`.
+    pub fn new(
+        source_bounds: &[SourceBound<T>],
+        interval: Option<Interval<T>>,
+        options: Options<T>,
+    ) -> Report<T> {
+        let verdicts = match interval {
+            Some(ref ivl) => ivl.verdicts(source_bounds),
+            None => Vec::new(),
+        };
+        Report {
+            width: interval.as_ref().map(Interval::width),
+            sources: source_intervals(source_bounds).len(),
+            sources_true: interval.as_ref().map_or(0, Interval::sources_true),
+            sources_false: interval.as_ref().map_or(0, Interval::sources_false),
+            interval,
+            verdicts,
+            options,
+        }
+    }
+
+    /// Runs the algorithm over `source_bounds` and reports the outcome.
+    pub fn try_from_source_bounds(
+        source_bounds: Vec<SourceBound<T>>,
+    ) -> Result<Report<T>, MarzulloError<T>> {
+        Report::try_from_source_bounds_with_options(source_bounds, Options::default())
+    }
+
+    /// Runs the algorithm over `source_bounds` with `options`, and reports the outcome.
+    pub fn try_from_source_bounds_with_options(
+        source_bounds: Vec<SourceBound<T>>,
+        options: Options<T>,
+    ) -> Result<Report<T>, MarzulloError<T>> {
+        let interval = options.select(source_bounds.clone())?;
+        Ok(Report::new(&source_bounds, interval, options))
+    }
+}
+
+/// Renders the report for operators, e.g. `11..=12 (2/3 sources agree, width 1us, rejected: 2)`,
+/// with the width suffixed by the unit of the values.
+pub struct ReportDisplay<'a, T: Value> {
+    report: &'a Report<T>,
+    unit: &'a str,
+}
+
+impl<T: Value> Report<T> {
+    /// Renders the report with the width in `unit`, such as `"ns"` or `"us"`. The `Display`
+    /// implementation of the report renders the width without a unit.
+    pub fn with_unit<'a>(&'a self, unit: &'a str) -> ReportDisplay<'a, T> {
+        ReportDisplay { report: self, unit }
+    }
+}
+
+impl<T: Value + fmt::Display> fmt::Display for ReportDisplay<'_, T>
+where
+    T::Width: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let report = self.report;
+        let (Some(interval), Some(width)) = (&report.interval, &report.width) else {
+            return write!(f, "no sources");
+        };
+        write!(
+            f,
+            "{}..={} ({}/{} sources agree, width {}{}, rejected: ",
+            interval.lower_bound,
+            interval.upper_bound,
+            report.sources_true,
+            report.sources,
+            width,
+            self.unit
+        )?;
+        let mut rejected = report
+            .verdicts
+            .iter()
+            .filter(|verdict| verdict.verdict == Verdict::Falseticker)
+            .peekable();
+        if rejected.peek().is_none() {
+            write!(f, "none")?;
+        }
+        for (i, verdict) in rejected.enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", verdict.source)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T: Value + fmt::Display> fmt::Display for Report<T>
+where
+    T::Width: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.with_unit("").fmt(f)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: Value + serde::Serialize> Report<T>
+where
+    T::Width: serde::Serialize,
+{
+    pub fn to_json(&self) -> Result<alloc::string::String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: Value + serde::Serialize> Interval<T> {
+    pub fn to_json(&self) -> Result<alloc::string::String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ordering::Order;
+    use crate::{Engine, Stickiness};
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_report() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        source_bounds.extend(SourceBound::pair(2, 14, 15));
+        let report = Report::try_from_source_bounds(source_bounds).unwrap();
+        assert_eq!(report.options, Options::default());
+        assert_eq!(report.width, Some(1));
+        assert_eq!(report.sources, 3);
+        assert_eq!(report.sources_true, 2);
+        assert_eq!(report.sources_false, 1);
+        assert_eq!(report.verdicts.len(), 3);
+        assert_eq!(report.verdicts[2].verdict, Verdict::Falseticker);
+        let distances: Vec<_> = report.verdicts.iter().map(|v| v.distance).collect();
+        assert_eq!(distances, [Some(0), Some(0), Some(2)]);
+        assert_eq!(
+            report.with_unit("us").to_string(),
+            "11..=12 (2/3 sources agree, width 1us, rejected: 2)"
+        );
+
+        let report = Report::<i64>::try_from_source_bounds(Vec::new()).unwrap();
+        assert!(report.interval.is_none());
+        assert_eq!(report.sources, 0);
+        assert!(report.verdicts.is_empty());
+        assert_eq!(report.to_string(), "no sources");
+
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 8, 12));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        let report = Report::try_from_source_bounds(source_bounds.clone()).unwrap();
+        assert_eq!(
+            report.to_string(),
+            "10..=12 (2/2 sources agree, width 2, rejected: none)"
+        );
+
+        // The report of an engine records the options it ran with, from which the selection runs
+        // again:
+        let options = Options {
+            order: Order::UpperFirst,
+            ..Options::default()
+        };
+        let mut engine = Engine::new()
+            .with_options(options)
+            .with_stickiness(Stickiness::default());
+        let previous = engine
+            .select(Vec::from(SourceBound::pair(0, 10, 20)))
+            .unwrap();
+        let report = engine.report(source_bounds.clone()).unwrap();
+        assert_eq!(report.options.order, Order::UpperFirst);
+        assert_eq!(report.options.clone().engine.unwrap().previous, previous);
+        assert_eq!(
+            report.options.select(source_bounds).unwrap(),
+            report.interval
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_report_to_json() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 14, 15));
+        let report = Report::try_from_source_bounds(source_bounds).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "interval": {
+                    "lower_bound": 14,
+                    "upper_bound": 15,
+                    "sources_true": 1,
+                    "sources_false": 1
+                },
+                "width": 1,
+                "sources": 2,
+                "sources_true": 1,
+                "sources_false": 1,
+                "verdicts": [
+                    {
+                        "source": 0, "lower": 11, "upper": 13, "verdict": "Falseticker",
+                        "distance": -1
+                    },
+                    {
+                        "source": 1, "lower": 14, "upper": 15, "verdict": "Truechimer",
+                        "distance": 0
+                    }
+                ],
+                "options": {"order": "LowerFirst", "score": "Narrowest", "engine": null}
+            })
+        );
+        assert_eq!(
+            report.interval.unwrap().to_json().unwrap(),
+            r#"{"lower_bound":14,"upper_bound":15,"sources_true":1,"sources_false":1}"#
+        );
+    }
+}
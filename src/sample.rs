@@ -0,0 +1,43 @@
+//! Random values within the chosen interval, e.g. to jitter scheduling decisions within the agreed
+//! window, without all nodes picking the same midpoint.
+
+use rand::distr::uniform::SampleUniform;
+use rand::Rng;
+
+use crate::{Interval, Value};
+
+impl<T: Value + SampleUniform> Interval<T> {
+    /// Returns a value within the interval, bounds included, chosen uniformly by `rng`.
+    pub fn sample_point<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        rng.random_range(self.lower_bound.clone()..=self.upper_bound.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sample_point() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let interval = Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, -2, 2)))
+            .unwrap()
+            .unwrap();
+        let mut seen = [false; 5];
+        for _ in 0..100 {
+            let point = interval.sample_point(&mut rng);
+            assert!((-2..=2).contains(&point));
+            seen[(point + 2) as usize] = true;
+        }
+        assert_eq!(seen, [true; 5]);
+
+        let interval = Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, 5, 5)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.sample_point(&mut rng), 5);
+    }
+}
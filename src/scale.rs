@@ -0,0 +1,57 @@
+//! The scale of the renderings of a report, the ASCII chart and the SVG, which spans the bounded
+//! values of its sources. Unbounded sides are clamped to its edges.
+
+use alloc::vec::Vec;
+
+use crate::{Report, SourceBound};
+
+/// Maps values onto a rendering, from `min` at its start to `max` at its end.
+pub(crate) struct Scale {
+    pub(crate) min: i64,
+    pub(crate) max: i64,
+}
+
+impl Scale {
+    /// The scale from the first to the last of `values`, which are sorted.
+    pub(crate) fn new(values: &[i64]) -> Scale {
+        Scale {
+            min: values.first().copied().unwrap_or(0),
+            max: values.last().copied().unwrap_or(0),
+        }
+    }
+
+    /// The column of `value` of `columns` columns.
+    pub(crate) fn column(&self, value: i64, columns: usize) -> usize {
+        let value = value.clamp(self.min, self.max);
+        if self.max == self.min {
+            return 0;
+        }
+        let offset = (value as i128 - self.min as i128) * (columns as i128 - 1);
+        (offset / (self.max as i128 - self.min as i128)) as usize
+    }
+
+    /// How far `value` lies from the start, as a fraction of the whole length.
+    #[cfg(feature = "svg")]
+    pub(crate) fn fraction(&self, value: i64) -> f64 {
+        let value = value.clamp(self.min, self.max);
+        if self.max == self.min {
+            return 0.0;
+        }
+        (value as i128 - self.min as i128) as f64 / (self.max as i128 - self.min as i128) as f64
+    }
+}
+
+/// The distinct bounded values of the sources of `report`, sorted.
+pub(crate) fn bounded_values(report: &Report<i64>) -> Vec<i64> {
+    let mut values: Vec<i64> = report
+        .verdicts
+        .iter()
+        .flat_map(|verdict| [verdict.lower, verdict.upper])
+        .filter(|&value| {
+            value != SourceBound::UNBOUNDED_LOWER && value != SourceBound::UNBOUNDED_UPPER
+        })
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+    values
+}
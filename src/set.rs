@@ -0,0 +1,184 @@
+//! Sets of integers as sorted, disjoint closed intervals, with the algebra of sets over them:
+//! union, intersection, difference, and complement within `i64`. Intervals that overlap or touch
+//! are merged, so that every set has exactly one representation, e.g. `[1, 3] ∪ [4, 5]` is
+//! `[1, 5]`.
+
+use alloc::vec::Vec;
+
+use crate::verdict::source_intervals;
+use crate::{Interval, SourceBound};
+
+/// A set of integers, as sorted, disjoint, and non-adjacent closed intervals.
+#[derive(PartialEq, Eq, Debug, Clone, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntervalSet {
+    ranges: Vec<(i64, i64)>,
+}
+
+impl IntervalSet {
+    pub fn new() -> IntervalSet {
+        IntervalSet::default()
+    }
+
+    /// The set of every `i64`.
+    pub fn full() -> IntervalSet {
+        IntervalSet {
+            ranges: Vec::from([(i64::MIN, i64::MAX)]),
+        }
+    }
+
+    /// Returns the set of the closed intervals `[lower, upper]`. Intervals with `lower > upper` are
+    /// empty.
+    pub fn from_ranges(ranges: impl IntoIterator<Item = (i64, i64)>) -> IntervalSet {
+        let mut ranges: Vec<(i64, i64)> = ranges
+            .into_iter()
+            .filter(|(lower, upper)| lower <= upper)
+            .collect();
+        ranges.sort_unstable();
+        let mut normalized: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+        for (lower, upper) in ranges {
+            match normalized.last_mut() {
+                Some((_, last)) if lower <= last.saturating_add(1) => *last = (*last).max(upper),
+                _ => normalized.push((lower, upper)),
+            }
+        }
+        IntervalSet { ranges: normalized }
+    }
+
+    /// Returns the set of the integers covered by the intervals of at least `k` sources. Sources
+    /// missing either bound are skipped.
+    pub fn coverage(source_bounds: &[SourceBound], k: usize) -> IntervalSet {
+        // Every source enters at its lower bound, and leaves just past its upper bound:
+        let mut events: Vec<(i128, i64)> = source_intervals(source_bounds)
+            .into_iter()
+            .filter(|(_, lower, upper)| lower <= upper)
+            .flat_map(|(_, lower, upper)| [(lower as i128, 1), (upper as i128 + 1, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut ranges = Vec::new();
+        let (mut count, mut start) = (0, None);
+        for (value, change) in events {
+            count += change;
+            match start {
+                None if count >= k as i64 && k > 0 => start = Some(value),
+                Some(lower) if count < k as i64 => {
+                    ranges.push((lower as i64, (value - 1) as i64));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        IntervalSet::from_ranges(ranges)
+    }
+
+    /// The intervals of the set, in order.
+    pub fn ranges(&self) -> &[(i64, i64)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let index = self.ranges.partition_point(|&(_, upper)| upper < value);
+        self.ranges
+            .get(index)
+            .is_some_and(|&(lower, _)| lower <= value)
+    }
+
+    /// Returns the set with `[lower, upper]` added.
+    pub fn insert(&self, lower: i64, upper: i64) -> IntervalSet {
+        self.union(&IntervalSet::from_ranges([(lower, upper)]))
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        IntervalSet::from_ranges(self.ranges.iter().chain(&other.ranges).copied())
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(&(a, b)), Some(&(c, d))) = (self.ranges.get(i), other.ranges.get(j)) {
+            if a.max(c) <= b.min(d) {
+                ranges.push((a.max(c), b.min(d)));
+            }
+            // Advance past the interval that ends first, which meets no later interval:
+            if b < d {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Returns the integers of `i64` that are not in the set.
+    pub fn complement(&self) -> IntervalSet {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 1);
+        let mut next = Some(i64::MIN);
+        for &(lower, upper) in &self.ranges {
+            if let Some(start) = next {
+                if start < lower {
+                    ranges.push((start, lower - 1));
+                }
+            }
+            next = upper.checked_add(1);
+        }
+        if let Some(start) = next {
+            ranges.push((start, i64::MAX));
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        self.intersection(&other.complement())
+    }
+}
+
+impl From<&Interval> for IntervalSet {
+    fn from(interval: &Interval) -> IntervalSet {
+        IntervalSet::from_ranges([(interval.lower_bound(), interval.upper_bound())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_interval_set() {
+        let a = IntervalSet::from_ranges([(10, 20), (1, 3), (4, 5), (30, 29)]);
+        assert_eq!(a.ranges(), [(1, 5), (10, 20)]);
+        let b = IntervalSet::from_ranges([(5, 12), (18, 40)]);
+        assert_eq!(a.union(&b).ranges(), [(1, 40)]);
+        assert_eq!(a.intersection(&b).ranges(), [(5, 5), (10, 12), (18, 20)]);
+        assert_eq!(a.difference(&b).ranges(), [(1, 4), (13, 17)]);
+        assert_eq!(
+            a.complement().ranges(),
+            [(i64::MIN, 0), (6, 9), (21, i64::MAX)]
+        );
+        assert_eq!(a.complement().complement(), a);
+        assert!(IntervalSet::new().complement() == IntervalSet::full());
+        assert!(IntervalSet::full().complement().is_empty());
+        assert!(a.contains(12) && !a.contains(7) && !a.contains(21));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 0, 10), (1, 5, 15), (2, 8, 9), (3, 14, 20)]);
+        assert_eq!(
+            IntervalSet::coverage(&source_bounds, 2).ranges(),
+            [(5, 10), (14, 15)]
+        );
+        // The interval of Marzullo's algorithm is within the coverage of its `sources_true`:
+        let interval = Interval::try_from_source_bounds(source_bounds.clone())
+            .unwrap()
+            .unwrap();
+        let coverage = IntervalSet::coverage(&source_bounds, interval.sources_true() as usize);
+        assert_eq!(coverage, IntervalSet::from(&interval));
+        assert!(IntervalSet::coverage(&source_bounds, 4).is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+//! A blocking SNTP client (RFC 4330): every server is queried once over UDP, its response turned
+//! into offset bounds with the four-timestamp round-trip math, and the selection run over all of
+//! them.
+
+pub mod packet;
+pub mod pool;
+#[cfg(all(target_os = "linux", feature = "timestamping"))]
+mod timestamping;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::interop::ntp::NtpTimestamp;
+use crate::interop::std_time::system_time_to_nanos;
+use crate::interop::ConversionError;
+use crate::source::{ClockSource, Sample, SourceKind, SourceMetadata};
+use crate::{Interval, MarzulloError, SourceBound, Verdict};
+use packet::{KissCode, Response};
+
+/// The default time to wait for a response.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+/// The minimum and maximum poll interval, as powers of two of seconds.
+pub const MIN_POLL: u8 = 6;
+pub const MAX_POLL: u8 = 17;
+
+#[derive(Debug)]
+pub enum SntpError {
+    Io(io::Error),
+    /// The response is too short, or is not from a server.
+    Malformed,
+    /// The origin timestamp of the response is not the transmit timestamp of the request, so it
+    /// is not a response to it.
+    Bogus,
+    /// The server reports that its clock is not synchronized.
+    Unsynchronized,
+    /// The server sent a Kiss-o'-Death response.
+    KissOfDeath(KissCode),
+    /// The server was demobilized by an earlier Kiss-o'-Death response, so it is not queried.
+    Demobilized,
+    Conversion(ConversionError),
+}
+
+impl core::fmt::Display for SntpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SntpError::Io(error) => write!(f, "{}", error),
+            SntpError::Malformed => write!(f, "malformed NTP response"),
+            SntpError::Bogus => write!(f, "NTP response does not match the request"),
+            SntpError::Unsynchronized => write!(f, "NTP server is not synchronized"),
+            SntpError::KissOfDeath(code) => write!(f, "NTP server sent Kiss-o'-Death {}", code),
+            SntpError::Demobilized => write!(f, "NTP server is demobilized"),
+            SntpError::Conversion(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for SntpError {}
+
+impl From<io::Error> for SntpError {
+    fn from(error: io::Error) -> SntpError {
+        SntpError::Io(error)
+    }
+}
+
+impl From<ConversionError> for SntpError {
+    fn from(error: ConversionError) -> SntpError {
+        SntpError::Conversion(error)
+    }
+}
+
+fn now() -> Result<i64, SntpError> {
+    Ok(system_time_to_nanos(SystemTime::now())?)
+}
+
+/// Where the sockets of queries are bound, for multi-homed machines where the route of the
+/// queries (and so their delay) depends on the source address.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct Binding {
+    /// The local address to send from, of the same family as the server. By default, the
+    /// operating system chooses it.
+    pub local_address: Option<IpAddr>,
+    /// The network interface to send through, Linux and Android only.
+    pub interface: Option<String>,
+}
+
+impl Binding {
+    /// Returns a socket bound as configured and connected to `server`.
+    fn socket(&self, server: &SocketAddr) -> io::Result<UdpSocket> {
+        let local = match (self.local_address, server) {
+            (Some(address), _) => SocketAddr::new(address, 0),
+            (None, SocketAddr::V4(_)) => ([0, 0, 0, 0], 0).into(),
+            (None, SocketAddr::V6(_)) => ([0; 8], 0).into(),
+        };
+        let socket = Socket::new(Domain::for_address(local), Type::DGRAM, Some(Protocol::UDP))?;
+        if let Some(interface) = &self.interface {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.bind_device(Some(interface.as_bytes()))?;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("cannot bind to interface {}", interface),
+            ));
+        }
+        socket.bind(&local.into())?;
+        socket.connect(&(*server).into())?;
+        Ok(socket.into())
+    }
+}
+
+/// The order in which to use the addresses of a hostname with both IPv4 and IPv6 addresses.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub enum IpPreference {
+    /// The order of the resolver.
+    #[default]
+    Any,
+    PreferIpv4,
+    PreferIpv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl IpPreference {
+    /// Filters and (stably) reorders resolved addresses by the preference.
+    pub fn apply(self, addresses: &mut Vec<SocketAddr>) {
+        match self {
+            IpPreference::Any => {}
+            IpPreference::PreferIpv4 => addresses.sort_by_key(SocketAddr::is_ipv6),
+            IpPreference::PreferIpv6 => addresses.sort_by_key(SocketAddr::is_ipv4),
+            IpPreference::Ipv4Only => addresses.retain(SocketAddr::is_ipv4),
+            IpPreference::Ipv6Only => addresses.retain(SocketAddr::is_ipv6),
+        }
+    }
+}
+
+/// Parses the response to a request with the transmit timestamp `transmit`.
+fn response(packet: &[u8], transmit: NtpTimestamp) -> Result<Response, SntpError> {
+    let response = Response::parse(packet)?;
+    if response.origin != transmit {
+        return Err(SntpError::Bogus);
+    }
+    Ok(response)
+}
+
+/// A server, as a clock source.
+pub struct SntpServer {
+    address: SocketAddr,
+    id: String,
+    timeout: Duration,
+    binding: Binding,
+    #[cfg(all(target_os = "linux", feature = "timestamping"))]
+    hardware_timestamps: bool,
+    /// The stratum of the last response.
+    stratum: Option<u8>,
+    /// The poll interval, as a power of two of seconds.
+    poll: u8,
+    demobilized: bool,
+}
+
+impl SntpServer {
+    pub fn new(address: SocketAddr) -> SntpServer {
+        SntpServer {
+            address,
+            id: address.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            binding: Binding::default(),
+            #[cfg(all(target_os = "linux", feature = "timestamping"))]
+            hardware_timestamps: false,
+            stratum: None,
+            poll: MIN_POLL,
+            demobilized: false,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> SntpServer {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_binding(mut self, binding: Binding) -> SntpServer {
+        self.binding = binding;
+        self
+    }
+
+    /// Uses the timestamps of the NIC rather than those of the kernel, if it supports them.
+    #[cfg(all(target_os = "linux", feature = "timestamping"))]
+    pub fn with_hardware_timestamps(mut self, hardware_timestamps: bool) -> SntpServer {
+        self.hardware_timestamps = hardware_timestamps;
+        self
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the interval at which the server should be polled, which is doubled by every
+    /// `RATE` Kiss-o'-Death response.
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1 << self.poll)
+    }
+
+    /// Whether the server denied access with a Kiss-o'-Death response, so it is no longer queried.
+    pub fn is_demobilized(&self) -> bool {
+        self.demobilized
+    }
+
+    /// Records the outcome of a query, backing off or demobilizing after a Kiss-o'-Death response.
+    fn record(
+        &mut self,
+        response: &Response,
+        sent: i64,
+        received: i64,
+    ) -> Result<Sample, SntpError> {
+        self.stratum = Some(response.stratum);
+        let sample = response.to_sample(sent, received);
+        match &sample {
+            Err(SntpError::KissOfDeath(KissCode::Rate)) => {
+                self.poll = (self.poll + 1).min(MAX_POLL)
+            }
+            Err(SntpError::KissOfDeath(code)) if code.demobilizes() => self.demobilized = true,
+            _ => {}
+        }
+        sample
+    }
+
+    /// Queries the server once, and returns the response with the local times at which the
+    /// request was sent and the response received.
+    pub fn query(&self) -> Result<(Response, i64, i64), SntpError> {
+        let socket = self.binding.socket(&self.address)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        #[cfg(all(target_os = "linux", feature = "timestamping"))]
+        if timestamping::enable(&socket, self.hardware_timestamps) {
+            return timestamping::query(&socket);
+        }
+
+        let sent = now()?;
+        let (_, transmit) = NtpTimestamp::from_nanos(sent);
+        socket.send(&packet::request(transmit))?;
+        let mut buffer = [0; 1024];
+        let len = socket.recv(&mut buffer)?;
+        let received = now()?;
+        Ok((response(&buffer[..len], transmit)?, sent, received))
+    }
+}
+
+impl ClockSource for SntpServer {
+    type Error = SntpError;
+
+    fn sample(&mut self) -> Result<Sample, SntpError> {
+        if self.demobilized {
+            return Err(SntpError::Demobilized);
+        }
+        let (response, sent, received) = self.query()?;
+        self.record(&response, sent, received)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            kind: SourceKind::Network,
+            stratum: self.stratum,
+        }
+    }
+}
+
+/// The outcome of querying a server.
+#[derive(Debug)]
+pub struct ServerDiagnostic {
+    pub address: SocketAddr,
+    pub sample: Result<Sample, SntpError>,
+    /// Whether the server agrees with the chosen interval, if it was sampled and there is one.
+    pub verdict: Option<Verdict>,
+}
+
+#[derive(Debug)]
+pub struct Synchronization {
+    /// The interval of offsets from the local clock that the most servers agree on.
+    pub result: Result<Option<Interval>, MarzulloError>,
+    /// The servers in the order they were given.
+    pub servers: Vec<ServerDiagnostic>,
+}
+
+/// Queries every server in turn, waiting up to `timeout` for each, and selects over their offsets.
+/// Servers are identified in the bounds by their index, so at most 256 are queried.
+pub fn synchronize(addresses: &[SocketAddr], timeout: Duration) -> Synchronization {
+    let addresses = &addresses[..addresses.len().min(256)];
+    let samples = addresses
+        .iter()
+        .map(|&address| SntpServer::new(address).with_timeout(timeout).sample())
+        .collect();
+    select(addresses, samples)
+}
+
+/// Queries every server in turn, with its own timeout and binding, and selects over their offsets.
+/// Servers are identified in the bounds by their index, so at most 256 are queried.
+pub fn synchronize_servers(servers: &mut [SntpServer]) -> Synchronization {
+    let len = servers.len().min(256);
+    let servers = &mut servers[..len];
+    let addresses: Vec<_> = servers.iter().map(SntpServer::address).collect();
+    let samples = servers.iter_mut().map(ClockSource::sample).collect();
+    select(&addresses, samples)
+}
+
+/// Selects over the samples of the servers, in the same order.
+fn select(addresses: &[SocketAddr], samples: Vec<Result<Sample, SntpError>>) -> Synchronization {
+    let mut servers = Vec::with_capacity(addresses.len());
+    let mut source_bounds = Vec::with_capacity(addresses.len() * 2);
+    for (source, (&address, sample)) in addresses.iter().zip(samples).enumerate() {
+        let sample = sample.and_then(|sample| {
+            source_bounds.extend(sample.bounds(source as u8)?);
+            Ok(sample)
+        });
+        servers.push(ServerDiagnostic {
+            address,
+            sample,
+            verdict: None,
+        });
+    }
+
+    let result = Interval::try_from_source_bounds(source_bounds.clone());
+    if let Ok(Some(interval)) = &result {
+        for verdict in interval.verdicts(&source_bounds) {
+            servers[verdict.source as usize].verdict = Some(verdict.verdict);
+        }
+    }
+    Synchronization { result, servers }
+}
+
+impl SourceBound {
+    /// Returns the bounds of the offset of a server from its response to a request sent at `sent`
+    /// and received at `received`.
+    pub fn try_from_sntp(
+        source: u8,
+        response: &Response,
+        sent: i64,
+        received: i64,
+    ) -> Result<[SourceBound; 2], SntpError> {
+        Ok(response.to_sample(sent, received)?.bounds(source)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Serves one request with a clock `offset` nanoseconds ahead of the local clock.
+    pub(super) fn serve(offset: i64) -> SocketAddr {
+        respond(1, 1, *b"GPS\0", offset)
+    }
+
+    /// Serves `requests` requests with the given stratum and reference id.
+    fn respond(requests: usize, stratum: u8, reference_id: [u8; 4], offset: i64) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            for _ in 0..requests {
+                let mut request = [0; packet::PACKET_LEN];
+                let (_, client) = socket.recv_from(&mut request).unwrap();
+                let (_, time) = NtpTimestamp::from_nanos(now().unwrap() + offset);
+                let mut response = [0; packet::PACKET_LEN];
+                response[0] = 0x24;
+                response[1] = stratum;
+                response[3] = -20_i8 as u8;
+                response[12..16].copy_from_slice(&reference_id);
+                response[24..32].copy_from_slice(&request[40..]);
+                response[32..40].copy_from_slice(&time.to_bits().to_be_bytes());
+                response[40..].copy_from_slice(&time.to_bits().to_be_bytes());
+                socket.send_to(&response, client).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn test_binding() {
+        let binding = Binding {
+            local_address: Some([127, 0, 0, 1].into()),
+            interface: None,
+        };
+        let mut servers = [SntpServer::new(serve(0)).with_binding(binding)];
+        assert!(synchronize_servers(&mut servers).servers[0].sample.is_ok());
+
+        let mut addresses: Vec<SocketAddr> = ["[::1]:123", "127.0.0.1:123", "[::2]:123"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+        IpPreference::PreferIpv6.apply(&mut addresses);
+        assert_eq!(addresses[2], "127.0.0.1:123".parse().unwrap());
+        IpPreference::Ipv4Only.apply(&mut addresses);
+        assert_eq!(addresses, ["127.0.0.1:123".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_kiss_of_death() {
+        let mut server = SntpServer::new(respond(2, 0, *b"RATE", 0));
+        for poll in [128, 256] {
+            assert!(matches!(
+                server.sample(),
+                Err(SntpError::KissOfDeath(KissCode::Rate))
+            ));
+            assert_eq!(server.poll_interval(), Duration::from_secs(poll));
+        }
+
+        let mut server = SntpServer::new(respond(1, 0, *b"DENY", 0));
+        assert!(matches!(
+            server.sample(),
+            Err(SntpError::KissOfDeath(KissCode::Deny))
+        ));
+        assert!(server.is_demobilized());
+        assert!(matches!(server.sample(), Err(SntpError::Demobilized)));
+    }
+
+    #[test]
+    fn test_synchronize() {
+        let addresses = [
+            serve(5_000_000_000),
+            serve(5_000_000_000),
+            serve(-60_000_000_000),
+        ];
+        let synchronization = synchronize(&addresses, DEFAULT_TIMEOUT);
+        let interval = synchronization.result.unwrap().unwrap();
+        assert_eq!(interval.sources_true(), 2);
+        assert!(interval.lower_bound() <= 5_000_000_000 && interval.upper_bound() >= 5_000_000_000);
+        let verdicts: Vec<_> = synchronization
+            .servers
+            .iter()
+            .map(|server| server.verdict)
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                Some(Verdict::Truechimer),
+                Some(Verdict::Truechimer),
+                Some(Verdict::Falseticker)
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! The NTP packet of RFC 5905, as far as a client needs it.
+
+use super::SntpError;
+use crate::interop::ntp::{NtpShort, NtpTimestamp};
+use crate::source::Sample;
+
+pub const PACKET_LEN: usize = 48;
+
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+/// The leap indicator of a server whose clock is not synchronized.
+const LEAP_ALARM: u8 = 3;
+
+/// Returns a client request, with `transmit` as its transmit timestamp. The server echoes it as the
+/// origin timestamp of its response.
+pub fn request(transmit: NtpTimestamp) -> [u8; PACKET_LEN] {
+    let mut packet = [0; PACKET_LEN];
+    packet[0] = VERSION << 3 | MODE_CLIENT;
+    packet[40..].copy_from_slice(&transmit.to_bits().to_be_bytes());
+    packet
+}
+
+/// The code of a Kiss-o'-Death response, a stratum 0 response whose reference id is an ASCII code
+/// rather than a clock.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum KissCode {
+    /// The client is polling too often, and should back off.
+    Rate,
+    /// The server denies access to the client, which should stop polling it.
+    Deny,
+    /// The server restricts access to the client, which should stop polling it.
+    Restricted,
+    Other([u8; 4]),
+}
+
+impl KissCode {
+    pub fn from_reference_id(reference_id: [u8; 4]) -> KissCode {
+        match &reference_id {
+            b"RATE" => KissCode::Rate,
+            b"DENY" => KissCode::Deny,
+            b"RSTR" => KissCode::Restricted,
+            _ => KissCode::Other(reference_id),
+        }
+    }
+
+    /// Whether the client should stop polling the server.
+    pub fn demobilizes(self) -> bool {
+        matches!(self, KissCode::Deny | KissCode::Restricted)
+    }
+}
+
+impl core::fmt::Display for KissCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            KissCode::Rate => write!(f, "RATE"),
+            KissCode::Deny => write!(f, "DENY"),
+            KissCode::Restricted => write!(f, "RSTR"),
+            KissCode::Other(code) => write!(f, "{}", String::from_utf8_lossy(code)),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Response {
+    pub leap: u8,
+    pub version: u8,
+    pub stratum: u8,
+    /// The precision of the server clock, as a power of two of seconds.
+    pub precision: i8,
+    pub root_delay: NtpShort,
+    pub root_dispersion: NtpShort,
+    pub reference_id: [u8; 4],
+    pub origin: NtpTimestamp,
+    pub receive: NtpTimestamp,
+    pub transmit: NtpTimestamp,
+}
+
+fn timestamp(bytes: &[u8]) -> NtpTimestamp {
+    NtpTimestamp::from_bits(u64::from_be_bytes(bytes[..8].try_into().unwrap()))
+}
+
+fn short(bytes: &[u8]) -> NtpShort {
+    NtpShort::from_bits(u32::from_be_bytes(bytes[..4].try_into().unwrap()))
+}
+
+impl Response {
+    /// Parses the response of a server, ignoring any extension fields.
+    pub fn parse(packet: &[u8]) -> Result<Response, SntpError> {
+        if packet.len() < PACKET_LEN || packet[0] & 0x7 != MODE_SERVER {
+            return Err(SntpError::Malformed);
+        }
+        Ok(Response {
+            leap: packet[0] >> 6,
+            version: packet[0] >> 3 & 0x7,
+            stratum: packet[1],
+            precision: packet[3] as i8,
+            root_delay: short(&packet[4..]),
+            root_dispersion: short(&packet[8..]),
+            reference_id: packet[12..16].try_into().unwrap(),
+            origin: timestamp(&packet[24..]),
+            receive: timestamp(&packet[32..]),
+            transmit: timestamp(&packet[40..]),
+        })
+    }
+
+    /// Returns the Kiss-o'-Death code of a stratum 0 response.
+    pub fn kiss_code(&self) -> Option<KissCode> {
+        (self.stratum == 0).then(|| KissCode::from_reference_id(self.reference_id))
+    }
+
+    /// Derives a sample from the response to a request sent at `sent` and received at `received`,
+    /// in nanoseconds since the Unix epoch. The uncertainty includes the root distance of the
+    /// server and its precision, which also covers the truncation of its timestamps. The era of the
+    /// timestamps of the server is the one closest to `sent`, so that responses across the 2036
+    /// rollover are not 136 years off.
+    pub fn to_sample(&self, sent: i64, received: i64) -> Result<Sample, SntpError> {
+        if let Some(code) = self.kiss_code() {
+            return Err(SntpError::KissOfDeath(code));
+        }
+        if self.leap == LEAP_ALARM {
+            return Err(SntpError::Unsynchronized);
+        }
+        let precision = match self.precision {
+            precision @ -63..=-1 => 1_000_000_000_u64.div_ceil(1 << -precision) as i64,
+            precision @ 0..=32 => 1_000_000_000_i64 << precision,
+            _ => return Err(SntpError::Malformed),
+        };
+        let dispersion = (self.root_delay.to_nanos() as u64).div_ceil(2) as i64
+            + self.root_dispersion.to_nanos()
+            + precision;
+        Ok(Sample::from_timestamps(
+            sent,
+            self.receive.to_nanos_near(sent)?,
+            self.transmit.to_nanos_near(sent)?,
+            received,
+            dispersion,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interop::ntp::UNIX_EPOCH_SECONDS;
+
+    #[test]
+    fn test_response() {
+        let sent = 1_700_000_000_000_000_000;
+        let mut packet = request(NtpTimestamp::from_nanos(sent).1);
+        assert_eq!(packet[0], 0x23);
+        // A stratum 2 server, 1s ahead, that took 1ms to respond:
+        packet[0] = 0x24;
+        packet[1] = 2;
+        packet[3] = -20_i8 as u8;
+        packet.copy_within(40.., 24);
+        let receive = NtpTimestamp::from_nanos(sent + 1_010_000_000).1;
+        let transmit = NtpTimestamp::from_nanos(sent + 1_011_000_000).1;
+        packet[32..40].copy_from_slice(&receive.to_bits().to_be_bytes());
+        packet[40..].copy_from_slice(&transmit.to_bits().to_be_bytes());
+
+        let response = Response::parse(&packet).unwrap();
+        assert_eq!((response.version, response.stratum), (4, 2));
+        let sample = response.to_sample(sent, sent + 21_000_000).unwrap();
+        assert!((sample.offset - 1_000_000_000).abs() <= 1);
+        assert_eq!(sample.uncertainty, 10_000_000 + 954);
+
+        packet[0] = 0xe4;
+        assert!(matches!(
+            Response::parse(&packet).unwrap().to_sample(sent, sent),
+            Err(SntpError::Unsynchronized)
+        ));
+        packet[1] = 0;
+        packet[12..16].copy_from_slice(b"RATE");
+        assert!(matches!(
+            Response::parse(&packet).unwrap().to_sample(sent, sent),
+            Err(SntpError::KissOfDeath(KissCode::Rate))
+        ));
+        assert!(matches!(
+            Response::parse(&packet[..40]),
+            Err(SntpError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn test_response_across_era_rollover() {
+        // 2036-02-07T06:28:16Z, when the seconds of NTP timestamps wrap to zero:
+        let rollover = ((1_i64 << 32) - UNIX_EPOCH_SECONDS) * 1_000_000_000;
+        // Sent 2s before the rollover, to a server 3s ahead, which is already in era 1:
+        let sent = rollover - 2_000_000_000;
+        let mut packet = request(NtpTimestamp::from_nanos(sent).1);
+        packet[0] = 0x24;
+        packet[1] = 1;
+        packet[3] = -20_i8 as u8;
+        let (era, receive) = NtpTimestamp::from_nanos(sent + 3_001_000_000);
+        assert_eq!((era, receive.seconds), (1, 1));
+        packet[32..40].copy_from_slice(&receive.to_bits().to_be_bytes());
+        packet[40..].copy_from_slice(&receive.to_bits().to_be_bytes());
+        let sample = Response::parse(&packet)
+            .unwrap()
+            .to_sample(sent, sent + 2_000_000)
+            .unwrap();
+        assert!((sample.offset - 3_000_000_000).abs() <= 1);
+
+        // And the other way around, for a server still in era 0 after the local clock rolled over:
+        let sent = rollover + 2_000_000_000;
+        let (era, transmit) = NtpTimestamp::from_nanos(sent - 3_000_000_000);
+        assert_eq!(era, 0);
+        packet[32..40].copy_from_slice(&transmit.to_bits().to_be_bytes());
+        packet[40..].copy_from_slice(&transmit.to_bits().to_be_bytes());
+        let sample = Response::parse(&packet)
+            .unwrap()
+            .to_sample(sent, sent)
+            .unwrap();
+        assert!((sample.offset + 3_000_000_000).abs() <= 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Pools of servers behind one hostname, such as `pool.ntp.org`. A pool resolves its name to many
+//! addresses, and draws a few of them for every poll, rotating through the rest on later polls.
+//! Addresses that failed to respond are not drawn again, and the number drawn from one name is
+//! capped, so that a single misbehaving pool cannot make up most of the sources.
+
+use std::collections::HashSet;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use super::{synchronize, IpPreference, SntpError, Synchronization};
+
+pub struct Pool {
+    /// The hostname and port, e.g. `pool.ntp.org:123`.
+    name: String,
+    max_sources: usize,
+    ip_preference: IpPreference,
+    addresses: Vec<SocketAddr>,
+    /// The index of the first address of the next draw.
+    next: usize,
+    dead: HashSet<SocketAddr>,
+}
+
+impl Pool {
+    /// Creates a pool of the servers behind `name`, drawing at most `max_sources` of them at a time.
+    /// The name is only resolved by `resolve`.
+    pub fn new(name: impl Into<String>, max_sources: usize) -> Pool {
+        Pool {
+            name: name.into(),
+            max_sources,
+            ip_preference: IpPreference::Any,
+            addresses: Vec::new(),
+            next: 0,
+            dead: HashSet::new(),
+        }
+    }
+
+    /// Sets the address families to draw, and in which order, from the next resolution on.
+    pub fn with_ip_preference(mut self, ip_preference: IpPreference) -> Pool {
+        self.ip_preference = ip_preference;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Resolves the name again. Addresses that were dead stay dead, as long as the name still
+    /// resolves to them.
+    pub fn resolve(&mut self) -> io::Result<()> {
+        let mut addresses: Vec<SocketAddr> = Vec::new();
+        for address in self.name.to_socket_addrs()? {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        self.ip_preference.apply(&mut addresses);
+        self.dead.retain(|address| addresses.contains(address));
+        self.addresses = addresses;
+        self.next = 0;
+        Ok(())
+    }
+
+    /// Returns the addresses that are not dead.
+    pub fn live(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.addresses
+            .iter()
+            .copied()
+            .filter(|address| !self.dead.contains(address))
+    }
+
+    /// Draws up to `max_sources` live addresses, starting after the last address of the previous
+    /// draw.
+    pub fn draw(&mut self) -> Vec<SocketAddr> {
+        let (len, start) = (self.addresses.len(), self.next);
+        let mut drawn = Vec::with_capacity(self.max_sources.min(len));
+        for offset in 0..len {
+            if drawn.len() == self.max_sources {
+                break;
+            }
+            let index = (start + offset) % len;
+            let address = self.addresses[index];
+            if !self.dead.contains(&address) {
+                drawn.push(address);
+                self.next = (index + 1) % len;
+            }
+        }
+        drawn
+    }
+
+    /// Marks an address as dead, so that it is not drawn again.
+    pub fn mark_dead(&mut self, address: SocketAddr) {
+        if self.addresses.contains(&address) {
+            self.dead.insert(address);
+        }
+    }
+}
+
+/// Draws servers from every pool, queries them, and selects over their offsets, as `synchronize`
+/// does. Servers that could not be reached, or that denied access, are marked dead in their pool.
+pub fn synchronize_pools(pools: &mut [Pool], timeout: Duration) -> Synchronization {
+    let drawn: Vec<_> = pools
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(index, pool)| pool.draw().into_iter().map(move |address| (index, address)))
+        .collect();
+    let addresses: Vec<_> = drawn.iter().map(|&(_, address)| address).collect();
+    let synchronization = synchronize(&addresses, timeout);
+    for (&(index, address), server) in drawn.iter().zip(&synchronization.servers) {
+        match server.sample {
+            Err(SntpError::Io(_)) => pools[index].mark_dead(address),
+            Err(SntpError::KissOfDeath(code)) if code.demobilizes() => {
+                pools[index].mark_dead(address)
+            }
+            _ => {}
+        }
+    }
+    synchronization
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_rotation() {
+        let mut pool = Pool::new("127.0.0.1:123", 2);
+        pool.resolve().unwrap();
+        assert_eq!(pool.draw(), ["127.0.0.1:123".parse().unwrap()]);
+
+        pool.addresses = (1..=4)
+            .map(|port| SocketAddr::from(([127, 0, 0, 1], port)))
+            .collect();
+        let ports = |drawn: Vec<SocketAddr>| -> Vec<u16> {
+            drawn.iter().map(|address| address.port()).collect()
+        };
+        assert_eq!(ports(pool.draw()), [1, 2]);
+        assert_eq!(ports(pool.draw()), [3, 4]);
+        pool.mark_dead(SocketAddr::from(([127, 0, 0, 1], 1)));
+        assert_eq!(ports(pool.draw()), [2, 3]);
+        assert_eq!(ports(pool.draw()), [4, 2]);
+        assert_eq!(pool.live().count(), 3);
+    }
+}
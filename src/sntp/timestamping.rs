@@ -0,0 +1,140 @@
+//! Kernel and hardware timestamps of NTP packets on Linux, through `SO_TIMESTAMPING`. Packets are
+//! timestamped in the driver (or the NIC) rather than after the scheduler wakes the client, which
+//! removes most of the local delay from the round trip. Timestamps that the kernel does not report
+//! are taken in userspace instead.
+//!
+//! Hardware timestamps are in the time of the NIC's clock, so they are only meaningful if that clock
+//! is synchronized to the system clock (e.g. by `phc2sys`), and hardware timestamping is enabled on
+//! the interface.
+
+use std::io;
+use std::mem;
+use std::net::UdpSocket;
+use std::os::fd::AsRawFd;
+use std::ptr;
+
+use super::packet::{self, Response};
+use super::{now, response, SntpError};
+use crate::interop::ntp::NtpTimestamp;
+
+/// Enables timestamping on `socket`, and returns whether the kernel supports it.
+pub(super) fn enable(socket: &UdpSocket, hardware: bool) -> bool {
+    let mut flags = libc::SOF_TIMESTAMPING_SOFTWARE
+        | libc::SOF_TIMESTAMPING_RX_SOFTWARE
+        | libc::SOF_TIMESTAMPING_TX_SOFTWARE
+        | libc::SOF_TIMESTAMPING_OPT_TSONLY;
+    if hardware {
+        flags |= libc::SOF_TIMESTAMPING_RAW_HARDWARE
+            | libc::SOF_TIMESTAMPING_RX_HARDWARE
+            | libc::SOF_TIMESTAMPING_TX_HARDWARE;
+    }
+    // SAFETY: `flags` outlives the call, and its size is passed along.
+    unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_TIMESTAMPING,
+            ptr::addr_of!(flags).cast(),
+            mem::size_of_val(&flags) as libc::socklen_t,
+        ) == 0
+    }
+}
+
+/// Converts a timestamp, which is zero if it was not taken.
+// `time_t` and `c_long` are only 64 bits on some platforms.
+#[allow(clippy::useless_conversion)]
+fn timespec_nanos(timespec: &libc::timespec) -> Option<i64> {
+    if timespec.tv_sec == 0 && timespec.tv_nsec == 0 {
+        return None;
+    }
+    i64::from(timespec.tv_sec)
+        .checked_mul(1_000_000_000)?
+        .checked_add(i64::from(timespec.tv_nsec))
+}
+
+/// Receives a message, and returns its length and its timestamp, preferring the hardware timestamp.
+fn receive(
+    socket: &UdpSocket,
+    buffer: &mut [u8],
+    flags: libc::c_int,
+) -> io::Result<(usize, Option<i64>)> {
+    let mut iov = libc::iovec {
+        iov_base: buffer.as_mut_ptr().cast(),
+        iov_len: buffer.len(),
+    };
+    // Aligned for `cmsghdr`:
+    let mut control = [0_u64; 32];
+    // SAFETY: `msghdr` is plain data, and the buffers it points to outlive the call. The control
+    // messages are only read within the length reported by the kernel, through the CMSG macros.
+    unsafe {
+        let mut message: libc::msghdr = mem::zeroed();
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = mem::size_of_val(&control) as _;
+        let len = libc::recvmsg(socket.as_raw_fd(), &mut message, flags);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut timestamp = None;
+        let mut header = libc::CMSG_FIRSTHDR(&message);
+        while !header.is_null() {
+            if (*header).cmsg_level == libc::SOL_SOCKET
+                && (*header).cmsg_type == libc::SCM_TIMESTAMPING
+            {
+                // The software, legacy, and hardware timestamps:
+                let timestamps: [libc::timespec; 3] =
+                    ptr::read_unaligned(libc::CMSG_DATA(header).cast());
+                timestamp = timespec_nanos(&timestamps[2]).or(timespec_nanos(&timestamps[0]));
+            }
+            header = libc::CMSG_NXTHDR(&message, header);
+        }
+        Ok((len as usize, timestamp))
+    }
+}
+
+/// Queries the server that `socket` is connected to, on which timestamping is enabled.
+pub(super) fn query(socket: &UdpSocket) -> Result<(Response, i64, i64), SntpError> {
+    let sent = now()?;
+    let (_, transmit) = NtpTimestamp::from_nanos(sent);
+    socket.send(&packet::request(transmit))?;
+    let mut buffer = [0; 1024];
+    let (len, received) = receive(socket, &mut buffer, 0)?;
+    let received = match received {
+        Some(received) => received,
+        None => now()?,
+    };
+    // By the time the response is received, the timestamp of the request is in the error queue:
+    let sent = receive(socket, &mut [], libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT)
+        .ok()
+        .and_then(|(_, sent)| sent)
+        .unwrap_or(sent);
+    Ok((response(&buffer[..len], transmit)?, sent, received))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sntp::tests::serve;
+    use crate::sntp::SntpServer;
+    use crate::source::ClockSource;
+
+    #[test]
+    fn test_kernel_timestamps() {
+        let server = serve(0);
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(server).unwrap();
+        if !enable(&socket, false) {
+            // Timestamping is unavailable, e.g. in a sandbox.
+            return;
+        }
+        let (response, sent, received) = query(&socket).unwrap();
+        assert!(sent <= received);
+        let sample = response.to_sample(sent, received).unwrap();
+        assert!(sample.offset.abs() <= sample.uncertainty);
+
+        // Transparently, through the server:
+        assert!(SntpServer::new(serve(0)).sample().is_ok());
+    }
+}
@@ -0,0 +1,173 @@
+//! An async SNTP client on tokio. Servers are queried concurrently, up to a limit, with requests
+//! spaced out so that a long server list does not burst, and queries that time out are retried.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ::tokio::net::UdpSocket;
+use ::tokio::sync::{Mutex, Semaphore};
+use ::tokio::task::JoinSet;
+use ::tokio::time::{self, Instant};
+
+use super::packet::{self, Response};
+use super::{now, response, Binding, SntpError, SntpServer, Synchronization, DEFAULT_TIMEOUT};
+use crate::interop::ntp::NtpTimestamp;
+use crate::source::{AsyncClockSource, Sample, SourceKind, SourceMetadata};
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ClientOptions {
+    /// The time to wait for each response.
+    pub timeout: Duration,
+    /// The number of times a query is retried after it times out or fails to send.
+    pub retries: u32,
+    /// The maximum number of queries in flight.
+    pub max_in_flight: usize,
+    /// The minimum time between two requests, to any server.
+    pub min_interval: Duration,
+    pub binding: Binding,
+}
+
+impl Default for ClientOptions {
+    fn default() -> ClientOptions {
+        ClientOptions {
+            timeout: DEFAULT_TIMEOUT,
+            retries: 2,
+            max_in_flight: 16,
+            min_interval: Duration::from_millis(10),
+            binding: Binding::default(),
+        }
+    }
+}
+
+/// Queries `server` once, and returns the response with the local times at which the request was
+/// sent and the response received.
+pub async fn query(
+    server: SocketAddr,
+    timeout: Duration,
+) -> Result<(Response, i64, i64), SntpError> {
+    query_with(server, timeout, &Binding::default()).await
+}
+
+async fn query_with(
+    server: SocketAddr,
+    timeout: Duration,
+    binding: &Binding,
+) -> Result<(Response, i64, i64), SntpError> {
+    let socket = binding.socket(&server)?;
+    socket.set_nonblocking(true)?;
+    let socket = UdpSocket::from_std(socket)?;
+
+    let sent = now()?;
+    let (_, transmit) = NtpTimestamp::from_nanos(sent);
+    socket.send(&packet::request(transmit)).await?;
+    let mut buffer = [0; 1024];
+    let len = time::timeout(timeout, socket.recv(&mut buffer))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+    let received = now()?;
+    Ok((response(&buffer[..len], transmit)?, sent, received))
+}
+
+async fn sample(
+    server: SocketAddr,
+    options: &ClientOptions,
+    next_request: &Mutex<Instant>,
+) -> Result<Sample, SntpError> {
+    let mut retries = 0;
+    loop {
+        let mut next = next_request.lock().await;
+        let slot = Instant::now().max(*next);
+        *next = slot + options.min_interval;
+        drop(next);
+        time::sleep_until(slot).await;
+
+        match query_with(server, options.timeout, &options.binding).await {
+            Err(SntpError::Io(_)) if retries < options.retries => retries += 1,
+            result => {
+                let (response, sent, received) = result?;
+                return response.to_sample(sent, received);
+            }
+        }
+    }
+}
+
+impl AsyncClockSource for SntpServer {
+    type Error = SntpError;
+
+    async fn sample(&mut self) -> Result<Sample, SntpError> {
+        if self.demobilized {
+            return Err(SntpError::Demobilized);
+        }
+        let (response, sent, received) =
+            query_with(self.address, self.timeout, &self.binding).await?;
+        self.record(&response, sent, received)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            kind: SourceKind::Network,
+            stratum: self.stratum,
+        }
+    }
+}
+
+/// Queries every server concurrently, and selects over their offsets. Servers are identified in
+/// the bounds by their index, so at most 256 are queried.
+pub async fn synchronize(addresses: &[SocketAddr], options: &ClientOptions) -> Synchronization {
+    let addresses = &addresses[..addresses.len().min(256)];
+    let in_flight = Arc::new(Semaphore::new(options.max_in_flight.max(1)));
+    let next_request = Arc::new(Mutex::new(Instant::now()));
+    let mut queries = JoinSet::new();
+    for (index, &address) in addresses.iter().enumerate() {
+        let (in_flight, next_request, options) =
+            (in_flight.clone(), next_request.clone(), options.clone());
+        queries.spawn(async move {
+            let _permit = in_flight.acquire_owned().await.unwrap();
+            (index, sample(address, &options, &next_request).await)
+        });
+    }
+
+    let mut samples: Vec<_> = addresses.iter().map(|_| None).collect();
+    while let Some(query) = queries.join_next().await {
+        let (index, sample) = query.expect("queries do not panic");
+        samples[index] = Some(sample);
+    }
+    super::select(addresses, samples.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sntp::tests::serve;
+    use crate::Verdict;
+
+    #[::tokio::test]
+    async fn test_synchronize() {
+        // A server that never responds:
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addresses = [
+            serve(5_000_000_000),
+            silent.local_addr().unwrap(),
+            serve(5_000_000_000),
+            serve(-60_000_000_000),
+        ];
+        let options = ClientOptions {
+            timeout: Duration::from_millis(100),
+            retries: 1,
+            max_in_flight: 2,
+            ..ClientOptions::default()
+        };
+        let synchronization = synchronize(&addresses, &options).await;
+        assert_eq!(synchronization.result.unwrap().unwrap().sources_true(), 2);
+        let servers = &synchronization.servers;
+        assert!(matches!(servers[1].sample, Err(SntpError::Io(_))));
+        assert_eq!(servers[2].verdict, Some(Verdict::Truechimer));
+        assert_eq!(servers[3].verdict, Some(Verdict::Falseticker));
+    }
+}
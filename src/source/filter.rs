@@ -0,0 +1,239 @@
+//! The clock filter of NTP (RFC 5905, section 10). Single samples are far too noisy to select over:
+//! a few of them are delayed by queues on the way, which widens and shifts them. The filter keeps
+//! the last 8 samples of a source, and passes on only the one with the smallest uncertainty, which
+//! is the one with the smallest round trip, together with the jitter of the offsets it kept.
+//!
+//! The uncertainty of a sample grows with its age at the frequency tolerance of the local clock,
+//! `PHI` by default, so that an old sample with a short round trip is eventually replaced by newer
+//! ones.
+
+use alloc::vec::Vec;
+
+use super::{ClockSource, Sample, SourceMetadata};
+
+/// The number of samples kept per source.
+pub const FILTER_STAGES: usize = 8;
+/// The frequency tolerance of the local clock, in parts per million: the uncertainty of a sample
+/// grows by 15ns for every millisecond of its age.
+pub const PHI: i64 = 15;
+/// The dispersion of an empty stage, 16s.
+pub const MAX_DISPERSION: i64 = 16_000_000_000;
+
+/// The best sample of a clock filter.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FilteredSample {
+    /// The sample with the smallest uncertainty, aged to the time of the latest sample, and widened
+    /// by `jitter`.
+    pub sample: Sample,
+    /// The root mean square of the offsets of the other samples from that of the best sample.
+    pub jitter: i64,
+    /// The uncertainties of all stages from the best to the worst, weighted by `1 / 2^(i + 1)`,
+    /// with empty stages at `MAX_DISPERSION`.
+    pub dispersion: i64,
+}
+
+/// A shift register of the last `FILTER_STAGES` samples of a source, the latest first.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ClockFilter {
+    stages: [Option<Sample>; FILTER_STAGES],
+    tolerance: i64,
+}
+
+impl Default for ClockFilter {
+    fn default() -> ClockFilter {
+        ClockFilter {
+            stages: [None; FILTER_STAGES],
+            tolerance: PHI,
+        }
+    }
+}
+
+impl ClockFilter {
+    pub fn new() -> ClockFilter {
+        ClockFilter::default()
+    }
+
+    /// Sets the frequency tolerance of the local clock, in parts per million, at which samples age.
+    pub fn with_tolerance(mut self, tolerance: i64) -> ClockFilter {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Shifts in a new sample, and returns the best sample of the filter.
+    pub fn add(&mut self, sample: Sample) -> FilteredSample {
+        self.stages.rotate_right(1);
+        self.stages[0] = Some(sample);
+        self.best(sample.local_time)
+            .expect("the filter holds a sample")
+    }
+
+    /// Returns the best sample of the filter at the local time `now`, or `None` if it is empty.
+    pub fn best(&self, now: i64) -> Option<FilteredSample> {
+        let mut samples: [Option<(i64, Sample)>; FILTER_STAGES] = self.stages.map(|stage| {
+            stage.map(|sample| (sample.aged(now, self.tolerance).uncertainty, sample))
+        });
+        // Sorted by uncertainty, with empty stages last:
+        samples.sort_by_key(|stage| match stage {
+            Some((uncertainty, _)) => (false, *uncertainty),
+            None => (true, 0),
+        });
+        let (uncertainty, best) = samples[0]?;
+
+        let mut dispersion = 0_i64;
+        for (i, stage) in samples.iter().enumerate() {
+            let stage = stage.map_or(MAX_DISPERSION, |(uncertainty, _)| uncertainty);
+            dispersion = dispersion.saturating_add(stage >> (i + 1));
+        }
+        let others = samples[1..].iter().flatten().count();
+        let jitter = if others == 0 {
+            0
+        } else {
+            let squares: u128 = samples[1..]
+                .iter()
+                .flatten()
+                .map(|(_, sample)| (sample.offset.abs_diff(best.offset) as u128).pow(2))
+                .sum();
+            i64::try_from((squares / others as u128).isqrt()).unwrap_or(i64::MAX)
+        };
+        Some(FilteredSample {
+            sample: Sample {
+                local_time: best.local_time,
+                offset: best.offset,
+                uncertainty: uncertainty.saturating_add(jitter),
+            },
+            jitter,
+            dispersion,
+        })
+    }
+
+    /// Estimates the frequency offset of the source from the local clock, in parts per million, as
+    /// the slope of the least-squares line through the offsets of the samples over their local
+    /// times. A positive frequency means the source runs faster than the local clock. Returns `None`
+    /// if there are fewer than 2 samples, or they were all taken at the same time.
+    ///
+    /// The offsets can be corrected for drift with it, and a source whose frequency differs from
+    /// that of the others is likely slewing.
+    pub fn frequency(&self) -> Option<f64> {
+        let samples: Vec<&Sample> = self.stages.iter().flatten().collect();
+        let origin = samples.first()?.local_time as i128;
+        let n = samples.len() as i128;
+        let (mut sum_t, mut sum_offset, mut sum_tt, mut sum_t_offset) = (0_i128, 0, 0, 0);
+        for sample in &samples {
+            let (t, offset) = (sample.local_time as i128 - origin, sample.offset as i128);
+            sum_t += t;
+            sum_offset += offset;
+            sum_tt += t * t;
+            sum_t_offset += t * offset;
+        }
+        let variance = n * sum_tt - sum_t * sum_t;
+        if variance == 0 {
+            return None;
+        }
+        let covariance = n * sum_t_offset - sum_t * sum_offset;
+        Some(covariance as f64 / variance as f64 * 1e6)
+    }
+
+    /// Empties the filter, e.g. after the source was unreachable or its clock stepped.
+    pub fn clear(&mut self) {
+        self.stages = [None; FILTER_STAGES];
+    }
+}
+
+/// A source whose samples are passed through a `ClockFilter`, so that a `SourceRegistry` selects
+/// over the filtered samples.
+pub struct Filtered<S> {
+    source: S,
+    filter: ClockFilter,
+}
+
+impl<S: ClockSource> Filtered<S> {
+    pub fn new(source: S) -> Filtered<S> {
+        Filtered {
+            source,
+            filter: ClockFilter::new(),
+        }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn filter(&self) -> &ClockFilter {
+        &self.filter
+    }
+}
+
+impl<S: ClockSource> ClockSource for Filtered<S> {
+    type Error = S::Error;
+
+    fn sample(&mut self) -> Result<Sample, S::Error> {
+        let sample = self.source.sample()?;
+        Ok(self.filter.add(sample).sample)
+    }
+
+    fn id(&self) -> &str {
+        self.source.id()
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        self.source.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MILLISECOND: i64 = 1_000_000;
+
+    fn sample(local_time: i64, offset: i64, uncertainty: i64) -> Sample {
+        Sample {
+            local_time,
+            offset,
+            uncertainty,
+        }
+    }
+
+    #[test]
+    fn test_clock_filter() {
+        let mut filter = ClockFilter::new();
+        let only = filter.add(sample(0, 500, 100));
+        assert_eq!((only.sample, only.jitter), (sample(0, 500, 100), 0));
+        assert_eq!(
+            only.dispersion,
+            50 + (2..=8).map(|i| MAX_DISPERSION >> i).sum::<i64>()
+        );
+
+        // A delayed sample does not displace the best one, but adds to the jitter:
+        let filtered = filter.add(sample(MILLISECOND, 2_500, 4_000));
+        assert_eq!(filtered.sample.offset, 500);
+        assert_eq!(filtered.jitter, 2_000);
+        assert_eq!(filtered.sample.uncertainty, 100 + 15 + 2_000);
+
+        // Once the best sample is shifted out, the next best one is used, aged by 7ms:
+        for i in 2..=FILTER_STAGES as i64 {
+            filter.add(sample(i * MILLISECOND, 2_500, 5_000));
+        }
+        let filtered = filter.best(FILTER_STAGES as i64 * MILLISECOND).unwrap();
+        assert_eq!(filtered.sample, sample(MILLISECOND, 2_500, 4_000 + 15 * 7));
+        assert_eq!(filtered.jitter, 0);
+
+        filter.clear();
+        assert_eq!(filter.best(0), None);
+    }
+
+    #[test]
+    fn test_frequency() {
+        let mut filter = ClockFilter::new();
+        filter.add(sample(0, 0, 100));
+        assert_eq!(filter.frequency(), None);
+        // Gaining 20µs per second, 20ppm, with some noise:
+        for (second, noise) in [(1, 3), (2, -2), (3, 0), (4, 1)] {
+            filter.add(sample(second * 1_000_000_000, second * 20_000 + noise, 100));
+        }
+        let frequency = filter.frequency().unwrap();
+        assert!((frequency - 20.0).abs() < 0.01, "{}", frequency);
+    }
+}
@@ -0,0 +1,207 @@
+//! Clock sources: anything that can be sampled for the offset of a remote or reference clock from
+//! the local clock, with an uncertainty. Selecting over offsets rather than absolute times makes
+//! samples taken at different instants comparable.
+
+pub mod filter;
+#[cfg(all(target_os = "linux", feature = "pps"))]
+pub mod pps;
+mod registry;
+#[cfg(all(unix, feature = "shm"))]
+pub mod shm;
+#[cfg(feature = "std")]
+mod table;
+
+pub use filter::{ClockFilter, Filtered, FilteredSample};
+pub use registry::{sample_concurrently, FalsetickerPolicy, Health, Poll, SourceRegistry};
+#[cfg(feature = "std")]
+pub use table::{Snapshot, SourceHandle, SourceTable};
+
+use core::future::Future;
+
+use crate::interop::ConversionError;
+use crate::SourceBound;
+
+/// A measurement of a clock against the local clock, in nanoseconds.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sample {
+    /// The local time of the measurement, in nanoseconds since the Unix epoch.
+    pub local_time: i64,
+    /// The time of the source minus the local time.
+    pub offset: i64,
+    /// The maximum error of `offset`, in either direction.
+    pub uncertainty: i64,
+}
+
+impl Sample {
+    /// Derives a sample from a request sent at `t0` and received by the server at `t1`, and the
+    /// response sent by the server at `t2` and received at `t3`, as in NTP and Cristian's
+    /// algorithm. The offset is `θ = ((t1 - t0) + (t2 - t3)) / 2`, and the uncertainty is half the
+    /// round trip `δ = (t3 - t0) - (t2 - t1)` plus `dispersion`, for the error of the clocks
+    /// themselves. The bounds of the sample contain `[t2 - t3, t1 - t0]`.
+    pub fn from_timestamps(
+        t0: i64,
+        t1: i64,
+        t2: i64,
+        t3: i64,
+        dispersion: i64,
+    ) -> Result<Sample, ConversionError> {
+        let (t0, t1, t2, t3) = (t0 as i128, t1 as i128, t2 as i128, t3 as i128);
+        let delay = (t3 - t0) - (t2 - t1);
+        if delay < 0 || dispersion < 0 {
+            return Err(ConversionError::NegativeDuration);
+        }
+        // `θ` and `δ` have the same parity, so rounding `θ` down and `δ / 2` up only widens the
+        // lower bound, by at most 1ns:
+        let offset = ((t1 - t0) + (t2 - t3)).div_euclid(2);
+        let uncertainty = (delay as u128).div_ceil(2) as i128 + dispersion as i128;
+        let (Ok(offset), Ok(uncertainty)) = (i64::try_from(offset), i64::try_from(uncertainty))
+        else {
+            return Err(ConversionError::OutOfRange);
+        };
+        Ok(Sample {
+            local_time: t3 as i64,
+            offset,
+            uncertainty,
+        })
+    }
+
+    /// Returns the sample as of the local time `now`, its uncertainty grown by `tolerance` parts
+    /// per million of its age: the local clock may have drifted that much since the measurement.
+    pub fn aged(&self, now: i64, tolerance: i64) -> Sample {
+        let age = now.saturating_sub(self.local_time).max(0) as i128;
+        let growth = (age * tolerance.max(0) as i128 / 1_000_000).min(i64::MAX as i128) as i64;
+        Sample {
+            uncertainty: self.uncertainty.saturating_add(growth),
+            ..*self
+        }
+    }
+
+    /// Returns the bounds of the offset, `[offset - uncertainty, offset + uncertainty]`.
+    pub fn bounds(&self, source: u8) -> Result<[SourceBound; 2], ConversionError> {
+        if self.uncertainty < 0 {
+            return Err(ConversionError::NegativeDuration);
+        }
+        Ok(SourceBound::pair(
+            source,
+            self.offset
+                .checked_sub(self.uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+            self.offset
+                .checked_add(self.uncertainty)
+                .ok_or(ConversionError::OutOfRange)?,
+        ))
+    }
+}
+
+/// The delay and dispersion a source accumulated from its reference clock, as the root delay and
+/// root dispersion of NTP, in nanoseconds. Both are zero for a reference clock.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Root {
+    /// The round trip from the source to its reference clock.
+    pub delay: i64,
+    /// The maximum error the source accumulated from its reference clock.
+    pub dispersion: i64,
+}
+
+impl Root {
+    /// Returns the maximum error the root adds to the offset of a sample of the source, half the
+    /// root delay plus the root dispersion.
+    pub fn distance(&self) -> i64 {
+        ((self.delay.max(0) as u64).div_ceil(2) as i64).saturating_add(self.dispersion.max(0))
+    }
+}
+
+/// What kind of clock a source is.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SourceKind {
+    /// A server queried over the network, e.g. NTP or Roughtime.
+    Network,
+    /// A reference clock attached to the machine, e.g. GPS or PTP hardware.
+    Refclock,
+    #[default]
+    Other,
+}
+
+/// A description of a source, for diagnostics and policies that treat kinds of sources differently.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceMetadata {
+    pub kind: SourceKind,
+    /// The distance of the source from a reference clock, as in NTP, if known.
+    pub stratum: Option<u8>,
+}
+
+pub trait ClockSource {
+    type Error;
+
+    /// Takes a new sample of the source.
+    fn sample(&mut self) -> Result<Sample, Self::Error>;
+
+    /// Returns a name that identifies the source, e.g. its address or device.
+    fn id(&self) -> &str;
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata::default()
+    }
+}
+
+/// A clock source that is sampled asynchronously, on any executor.
+pub trait AsyncClockSource {
+    type Error;
+
+    /// Takes a new sample of the source.
+    fn sample(&mut self) -> impl Future<Output = Result<Sample, Self::Error>>;
+
+    /// Returns a name that identifies the source, e.g. its address or device.
+    fn id(&self) -> &str;
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_bounds() {
+        let sample = Sample {
+            local_time: 0,
+            offset: -5,
+            uncertainty: 2,
+        };
+        let [lower, upper] = sample.bounds(3).unwrap();
+        assert_eq!((lower.value(), upper.value()), (-7, -3));
+        assert_eq!(
+            Sample {
+                uncertainty: -1,
+                ..sample
+            }
+            .bounds(3),
+            Err(ConversionError::NegativeDuration)
+        );
+    }
+
+    #[test]
+    fn test_sample_from_timestamps() {
+        // A round trip of 25ns, 3ns of which at the server:
+        let sample = Sample::from_timestamps(100, 115, 118, 125, 2).unwrap();
+        assert_eq!(sample.local_time, 125);
+        assert_eq!(sample.offset, 4);
+        assert_eq!(sample.uncertainty, 13);
+        let [lower, upper] = sample.bounds(0).unwrap();
+        assert!(lower.value() <= 118 - 125 - 2 && upper.value() >= 115 - 100 + 2);
+        assert_eq!(
+            Sample::from_timestamps(100, 115, 141, 125, 0),
+            Err(ConversionError::NegativeDuration)
+        );
+    }
+}
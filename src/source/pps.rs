@@ -0,0 +1,239 @@
+//! Pulse-per-second sources, through the RFC 2783 PPS API of Linux (`/dev/pps*`). The kernel
+//! timestamps the assert edge of every pulse, which marks the start of a second of the reference
+//! clock to within a microsecond or so.
+//!
+//! A pulse does not say which second it starts, so the offset is taken to the nearest second: the
+//! local clock must already be within half a second of the reference, e.g. from NTP or the time
+//! sentences of the same GPS receiver. A pulse is only used if it directly follows the previous
+//! one, a second earlier, so that missed and spurious pulses are rejected.
+
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+
+use super::{ClockSource, Sample, SourceKind, SourceMetadata};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// The default maximum deviation of the interval between two pulses from one second.
+pub const DEFAULT_TOLERANCE: i64 = 1_000_000;
+/// The default uncertainty of a pulse.
+pub const DEFAULT_JITTER: i64 = 1_000;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsKtime {
+    sec: i64,
+    nsec: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsKinfo {
+    assert_sequence: u32,
+    clear_sequence: u32,
+    assert_tu: PpsKtime,
+    clear_tu: PpsKtime,
+    current_mode: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsFdata {
+    info: PpsKinfo,
+    timeout: PpsKtime,
+}
+
+/// `_IOWR('p', 0xa4, struct pps_fdata *)`: the size is that of a pointer, as in `linux/pps.h`.
+const PPS_FETCH: libc::c_ulong = 3 << 30
+    | (size_of::<*const PpsFdata>() as libc::c_ulong) << 16
+    | (b'p' as libc::c_ulong) << 8
+    | 0xa4;
+
+/// The assert edge of a pulse.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct PpsEdge {
+    /// The number of pulses seen by the kernel, which wraps.
+    pub sequence: u32,
+    /// The local time of the edge, in nanoseconds since the Unix epoch.
+    pub time: i64,
+}
+
+#[derive(Debug)]
+pub enum PpsError {
+    Io(io::Error),
+    /// There is no new pulse since the last sample.
+    NoPulse,
+    /// The pulse does not follow the previous one a second later, because a pulse was missed, or
+    /// the pulse is spurious. It can only be used once the next pulse confirms it.
+    Unverified,
+}
+
+impl core::fmt::Display for PpsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            PpsError::Io(error) => write!(f, "{}", error),
+            PpsError::NoPulse => write!(f, "no new PPS pulse"),
+            PpsError::Unverified => write!(f, "PPS pulse does not follow the previous pulse"),
+        }
+    }
+}
+
+impl std::error::Error for PpsError {}
+
+impl From<io::Error> for PpsError {
+    fn from(error: io::Error) -> PpsError {
+        PpsError::Io(error)
+    }
+}
+
+/// Verifies every pulse against the previous one, and converts it into a sample.
+#[derive(Debug, Clone)]
+pub struct PulseFilter {
+    last: Option<PpsEdge>,
+    /// The maximum deviation of the interval between two pulses from one second.
+    pub tolerance: i64,
+    /// The uncertainty of a pulse.
+    pub jitter: i64,
+}
+
+impl Default for PulseFilter {
+    fn default() -> PulseFilter {
+        PulseFilter {
+            last: None,
+            tolerance: DEFAULT_TOLERANCE,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+}
+
+impl PulseFilter {
+    pub fn accept(&mut self, edge: PpsEdge) -> Result<Sample, PpsError> {
+        let last = self.last.replace(edge);
+        let Some(last) = last else {
+            return Err(PpsError::Unverified);
+        };
+        if edge.sequence == last.sequence {
+            return Err(PpsError::NoPulse);
+        }
+        let interval = edge.time.saturating_sub(last.time);
+        if edge.sequence != last.sequence.wrapping_add(1)
+            || (interval - NANOS_PER_SECOND).abs() > self.tolerance
+        {
+            return Err(PpsError::Unverified);
+        }
+        let subsecond = edge.time.rem_euclid(NANOS_PER_SECOND);
+        Ok(Sample {
+            local_time: edge.time,
+            offset: if subsecond < NANOS_PER_SECOND / 2 {
+                -subsecond
+            } else {
+                NANOS_PER_SECOND - subsecond
+            },
+            uncertainty: self.jitter,
+        })
+    }
+}
+
+/// A PPS device.
+pub struct PpsSource {
+    device: File,
+    id: String,
+    filter: PulseFilter,
+}
+
+impl PpsSource {
+    /// Opens a PPS device, e.g. `/dev/pps0`.
+    pub fn open(path: &str) -> io::Result<PpsSource> {
+        Ok(PpsSource {
+            device: File::open(path)?,
+            id: path.to_owned(),
+            filter: PulseFilter::default(),
+        })
+    }
+
+    pub fn with_filter(mut self, filter: PulseFilter) -> PpsSource {
+        self.filter = filter;
+        self
+    }
+
+    /// Returns the last assert edge, without waiting for a new one.
+    pub fn fetch(&self) -> io::Result<PpsEdge> {
+        let mut data = PpsFdata::default();
+        // SAFETY: PPS_FETCH reads and writes a `pps_fdata`, which `data` is laid out as. A zero
+        // timeout returns immediately.
+        if unsafe { libc::ioctl(self.device.as_raw_fd(), PPS_FETCH as _, &mut data) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let assert = data.info.assert_tu;
+        Ok(PpsEdge {
+            sequence: data.info.assert_sequence,
+            time: assert
+                .sec
+                .checked_mul(NANOS_PER_SECOND)
+                .and_then(|nanos| nanos.checked_add(assert.nsec as i64))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?,
+        })
+    }
+}
+
+impl ClockSource for PpsSource {
+    type Error = PpsError;
+
+    fn sample(&mut self) -> Result<Sample, PpsError> {
+        let edge = self.fetch()?;
+        self.filter.accept(edge)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            kind: SourceKind::Refclock,
+            stratum: Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: i64 = 1_700_000_000 * NANOS_PER_SECOND;
+
+    fn edge(sequence: u32, time: i64) -> PpsEdge {
+        PpsEdge { sequence, time }
+    }
+
+    #[test]
+    fn test_pulse_filter() {
+        let mut filter = PulseFilter::default();
+        assert!(matches!(
+            filter.accept(edge(1, SECOND + 2_000)),
+            Err(PpsError::Unverified)
+        ));
+        let sample = filter
+            .accept(edge(2, SECOND + NANOS_PER_SECOND + 2_500))
+            .unwrap();
+        assert_eq!(
+            (sample.offset, sample.uncertainty),
+            (-2_500, DEFAULT_JITTER)
+        );
+        assert!(matches!(
+            filter.accept(edge(2, SECOND + NANOS_PER_SECOND + 2_500)),
+            Err(PpsError::NoPulse)
+        ));
+        // A missed pulse, after which the next one is used again:
+        assert!(matches!(
+            filter.accept(edge(4, SECOND + 3 * NANOS_PER_SECOND - 1_000)),
+            Err(PpsError::Unverified)
+        ));
+        let sample = filter
+            .accept(edge(5, SECOND + 4 * NANOS_PER_SECOND - 1_500))
+            .unwrap();
+        assert_eq!(sample.offset, 1_500);
+    }
+}
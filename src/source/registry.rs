@@ -0,0 +1,572 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::future::{poll_fn, Future};
+use core::task;
+
+use super::{AsyncClockSource, ClockSource, Sample, SourceMetadata};
+use crate::interop::ConversionError;
+use crate::{Engine, Interval, MarzulloError, SourceEvent};
+
+type SourceError = Box<dyn core::error::Error>;
+
+/// A `ClockSource` with its error type erased, so that sources of different types can be
+/// registered together.
+trait DynSource {
+    fn sample(&mut self) -> Result<Sample, SourceError>;
+    fn id(&self) -> &str;
+    fn metadata(&self) -> SourceMetadata;
+}
+
+impl<S: ClockSource> DynSource for S
+where
+    S::Error: core::error::Error + 'static,
+{
+    fn sample(&mut self) -> Result<Sample, SourceError> {
+        ClockSource::sample(self).map_err(|error| Box::new(error) as SourceError)
+    }
+
+    fn id(&self) -> &str {
+        ClockSource::id(self)
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        ClockSource::metadata(self)
+    }
+}
+
+/// The outcome of sampling every source of a `SourceRegistry`, or of `sample_concurrently`.
+#[derive(Debug)]
+pub struct Poll<E = SourceError> {
+    /// The sample or error of every source, by its index.
+    pub samples: Vec<Result<Sample, E>>,
+    /// The selection over the offsets of the sources that were sampled.
+    pub result: Result<Option<Interval>, MarzulloError>,
+}
+
+/// The number of polls in a row a source may fail before it is unreachable, by default, as the
+/// reach register of NTP.
+pub const DEFAULT_UNREACHABLE_AFTER: u32 = 8;
+
+struct Entry {
+    source: Box<dyn DynSource>,
+    /// How long the latest sample is used for, if it is used beyond the poll that took it.
+    ttl: Option<i64>,
+    latest: Option<Sample>,
+    missed: u32,
+    reachable: bool,
+    health: Health,
+}
+
+/// Whether a source agreed with the recent selections of a `SourceRegistry`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Health {
+    /// The source fell outside the chosen interval in this many selections in a row, possibly
+    /// none.
+    Healthy { strikes: u32 },
+    /// The source fell outside the chosen interval too often, and is excluded from selection. It is
+    /// probed against the chosen interval every so many polls, and rehabilitated as soon as it
+    /// agrees again.
+    Falseticker { polls: u32 },
+}
+
+/// When a source becomes a falseticker, and how often it is probed for rehabilitation.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FalsetickerPolicy {
+    /// The number of selections in a row a source may fall outside the chosen interval.
+    pub strikes: u32,
+    /// The number of polls between probes of a falseticker.
+    pub probe_interval: u32,
+}
+
+/// A set of clock sources that are sampled together, and selected over by their offsets. Every
+/// source is identified in the bounds by the index it was registered with.
+///
+/// A source with a time to live is still selected over when it fails to be sampled, with its
+/// latest sample, until that sample is older than its time to live. Ages are relative to the
+/// latest sample of any source. Changes of the state of the sources are reported to the observers
+/// of the engine, as `SourceEvent`s.
+pub struct SourceRegistry {
+    sources: Vec<Entry>,
+    engine: Engine,
+    unreachable_after: u32,
+    falsetickers: Option<FalsetickerPolicy>,
+    now: i64,
+}
+
+impl Default for SourceRegistry {
+    fn default() -> SourceRegistry {
+        SourceRegistry {
+            sources: Vec::new(),
+            engine: Engine::new(),
+            unreachable_after: DEFAULT_UNREACHABLE_AFTER,
+            falsetickers: None,
+            now: i64::MIN,
+        }
+    }
+}
+
+impl SourceRegistry {
+    pub fn new() -> SourceRegistry {
+        SourceRegistry::default()
+    }
+
+    /// Sets the number of polls in a row a source may fail before it is unreachable.
+    pub fn with_unreachable_after(mut self, polls: u32) -> SourceRegistry {
+        self.unreachable_after = polls.max(1);
+        self
+    }
+
+    /// Excludes sources from selection once they fell outside the chosen interval in `strikes`
+    /// selections in a row, until a probe finds them within it again. Without a policy, the
+    /// health of every source is tracked, but no source is excluded.
+    pub fn with_falseticker_policy(mut self, policy: FalsetickerPolicy) -> SourceRegistry {
+        self.falsetickers = Some(policy);
+        self
+    }
+
+    /// Registers a source, and returns its index.
+    ///
+    /// # Panics
+    ///
+    /// If 256 sources are already registered.
+    pub fn register<S>(&mut self, source: S) -> u8
+    where
+        S: ClockSource + 'static,
+        S::Error: core::error::Error + 'static,
+    {
+        let index = u8::try_from(self.sources.len()).expect("at most 256 sources");
+        self.sources.push(Entry {
+            source: Box::new(source),
+            ttl: None,
+            latest: None,
+            missed: 0,
+            reachable: false,
+            health: Health::Healthy { strikes: 0 },
+        });
+        index
+    }
+
+    /// Sets the time to live of the samples of a source, in nanoseconds, or `None` to only select
+    /// over the source when it was sampled in the same poll.
+    ///
+    /// # Panics
+    ///
+    /// If there is no source with the given index.
+    pub fn set_ttl(&mut self, index: u8, ttl: Option<i64>) {
+        self.sources[index as usize].ttl = ttl;
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Returns the id and metadata of the source with the given index.
+    pub fn source(&self, index: u8) -> Option<(&str, SourceMetadata)> {
+        let entry = self.sources.get(index as usize)?;
+        Some((entry.source.id(), entry.source.metadata()))
+    }
+
+    /// Returns whether the source with the given index was sampled within the last polls.
+    pub fn is_reachable(&self, index: u8) -> bool {
+        self.sources
+            .get(index as usize)
+            .is_some_and(|entry| entry.reachable)
+    }
+
+    /// Returns the health of the source with the given index.
+    pub fn health(&self, index: u8) -> Option<Health> {
+        Some(self.sources.get(index as usize)?.health)
+    }
+
+    /// Returns the engine that runs the selections, to register observers.
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    /// Samples every source, and selects over the offsets of those that could be sampled, and
+    /// those with a time to live whose latest sample has not expired.
+    pub fn poll(&mut self) -> Poll {
+        let samples: Vec<_> = self
+            .sources
+            .iter_mut()
+            .map(|entry| entry.source.sample())
+            .collect();
+        for (index, (entry, sample)) in self.sources.iter_mut().zip(&samples).enumerate() {
+            let source = index as u8;
+            match sample {
+                Ok(sample) => {
+                    self.now = self.now.max(sample.local_time);
+                    entry.latest = Some(*sample);
+                    entry.missed = 0;
+                    if !entry.reachable {
+                        entry.reachable = true;
+                        self.engine.notify(SourceEvent::Reachable { source });
+                    }
+                }
+                Err(_) => {
+                    entry.missed = entry.missed.saturating_add(1);
+                    if entry.ttl.is_none() {
+                        entry.latest = None;
+                    }
+                    if entry.reachable && entry.missed >= self.unreachable_after {
+                        entry.reachable = false;
+                        self.engine.notify(SourceEvent::Unreachable {
+                            source,
+                            missed: entry.missed,
+                        });
+                    }
+                }
+            }
+        }
+        for (index, entry) in self.sources.iter_mut().enumerate() {
+            if let (Some(latest), Some(ttl)) = (entry.latest, entry.ttl) {
+                if self.now.saturating_sub(latest.local_time) > ttl {
+                    entry.latest = None;
+                    self.engine.notify(SourceEvent::Expired {
+                        source: index as u8,
+                    });
+                }
+            }
+        }
+
+        let mut source_bounds = Vec::with_capacity(samples.len() * 2);
+        let samples = samples
+            .into_iter()
+            .zip(&self.sources)
+            .enumerate()
+            .map(|(index, (sample, entry))| {
+                if let (Some(latest), Health::Healthy { .. }) = (entry.latest, entry.health) {
+                    source_bounds.extend(latest.bounds(index as u8)?);
+                }
+                sample
+            })
+            .collect();
+        let result = self.engine.select(source_bounds);
+        if let Ok(Some(interval)) = &result {
+            self.update_health(interval);
+        }
+        Poll { samples, result }
+    }
+
+    /// Counts the strikes of every source against the chosen interval, and excludes or
+    /// rehabilitates sources by the falseticker policy.
+    fn update_health(&mut self, interval: &Interval) {
+        for (index, entry) in self.sources.iter_mut().enumerate() {
+            let Some(latest) = entry.latest else {
+                continue;
+            };
+            let source = index as u8;
+            let agrees = latest.offset.saturating_sub(latest.uncertainty) <= interval.lower_bound()
+                && latest.offset.saturating_add(latest.uncertainty) >= interval.upper_bound();
+            entry.health = match (entry.health, self.falsetickers) {
+                (Health::Healthy { .. }, _) if agrees => Health::Healthy { strikes: 0 },
+                (Health::Healthy { strikes }, Some(policy)) if strikes + 1 >= policy.strikes => {
+                    self.engine.notify(SourceEvent::Falseticker {
+                        source,
+                        strikes: strikes + 1,
+                    });
+                    Health::Falseticker { polls: 0 }
+                }
+                (Health::Healthy { strikes }, _) => Health::Healthy {
+                    strikes: strikes.saturating_add(1),
+                },
+                (Health::Falseticker { polls }, Some(policy))
+                    if polls + 1 >= policy.probe_interval =>
+                {
+                    if agrees {
+                        self.engine.notify(SourceEvent::Rehabilitated { source });
+                        Health::Healthy { strikes: 0 }
+                    } else {
+                        Health::Falseticker { polls: 0 }
+                    }
+                }
+                (Health::Falseticker { polls }, Some(_)) => {
+                    Health::Falseticker { polls: polls + 1 }
+                }
+                // The policy was removed:
+                (Health::Falseticker { .. }, None) => Health::Healthy { strikes: 0 },
+            };
+        }
+    }
+}
+
+fn select<E: From<ConversionError>>(
+    engine: &mut Engine,
+    samples: Vec<Result<Sample, E>>,
+) -> Poll<E> {
+    let mut source_bounds = Vec::with_capacity(samples.len() * 2);
+    let samples = samples
+        .into_iter()
+        .enumerate()
+        .map(|(index, sample)| {
+            let sample = sample?;
+            source_bounds.extend(sample.bounds(index as u8)?);
+            Ok(sample)
+        })
+        .collect();
+    Poll {
+        samples,
+        result: engine.select(source_bounds),
+    }
+}
+
+/// Samples every source concurrently, without spawning, and selects over the offsets of those that
+/// could be sampled with `engine`. Sources are identified in the bounds by their index.
+///
+/// # Panics
+///
+/// If there are more than 256 sources.
+pub async fn sample_concurrently<S>(sources: &mut [S], engine: &mut Engine) -> Poll<S::Error>
+where
+    S: AsyncClockSource,
+    S::Error: From<ConversionError>,
+{
+    assert!(sources.len() <= 256, "at most 256 sources");
+    let mut pending: Vec<_> = sources
+        .iter_mut()
+        .map(|source| Box::pin(source.sample()))
+        .collect();
+    let mut samples: Vec<_> = pending.iter().map(|_| None).collect();
+    poll_fn(|context| {
+        let mut ready = true;
+        for (future, sample) in pending.iter_mut().zip(&mut samples) {
+            if sample.is_none() {
+                match future.as_mut().poll(context) {
+                    task::Poll::Ready(result) => *sample = Some(result),
+                    task::Poll::Pending => ready = false,
+                }
+            }
+        }
+        if ready {
+            task::Poll::Ready(())
+        } else {
+            task::Poll::Pending
+        }
+    })
+    .await;
+    drop(pending);
+    select(engine, samples.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceKind;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+    use core::pin::pin;
+
+    struct Mock {
+        samples: Vec<Result<Sample, ConversionError>>,
+    }
+
+    impl ClockSource for Mock {
+        type Error = ConversionError;
+
+        fn sample(&mut self) -> Result<Sample, ConversionError> {
+            self.samples.pop().unwrap()
+        }
+
+        fn id(&self) -> &str {
+            "mock"
+        }
+    }
+
+    fn mock(offset: i64) -> Mock {
+        Mock {
+            samples: Vec::from([Ok(Sample {
+                local_time: 0,
+                offset,
+                uncertainty: 10,
+            })]),
+        }
+    }
+
+    #[test]
+    fn test_registry_poll() {
+        let mut registry = SourceRegistry::new();
+        assert_eq!(registry.register(mock(0)), 0);
+        assert_eq!(registry.register(mock(15)), 1);
+        assert_eq!(registry.register(mock(1_000)), 2);
+        registry.register(Mock {
+            samples: Vec::from([Err(ConversionError::OutOfRange)]),
+        });
+        assert_eq!(
+            registry.source(3),
+            Some(("mock", SourceMetadata::default()))
+        );
+        assert_eq!(registry.source(3).unwrap().1.kind, SourceKind::Other);
+
+        let poll = registry.poll();
+        assert_eq!(poll.samples.len(), 4);
+        assert!(poll.samples[3].is_err());
+        let interval = poll.result.unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (5, 10));
+        assert_eq!(interval.sources_true(), 2);
+    }
+
+    struct Recorder(Rc<RefCell<Vec<SourceEvent>>>);
+
+    impl crate::Observer for Recorder {
+        fn on_source_event(&mut self, event: &SourceEvent) {
+            self.0.borrow_mut().push(*event);
+        }
+    }
+
+    #[test]
+    fn test_registry_expiry() {
+        let at = |local_time: i64| Sample {
+            local_time,
+            offset: 0,
+            uncertainty: 10,
+        };
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = SourceRegistry::new().with_unreachable_after(2);
+        registry
+            .engine_mut()
+            .add_observer(Recorder(Rc::clone(&events)));
+        // Samples are popped from the back:
+        registry.register(Mock {
+            samples: Vec::from([
+                Err(ConversionError::OutOfRange),
+                Err(ConversionError::OutOfRange),
+                Ok(at(0)),
+            ]),
+        });
+        registry.register(Mock {
+            samples: Vec::from([Ok(at(1_200)), Ok(at(600)), Ok(at(0))]),
+        });
+        registry.set_ttl(0, Some(1_000));
+
+        registry.poll();
+        // The latest sample of source 0 is still used, 600ns later:
+        let poll = registry.poll();
+        assert!(poll.samples[0].is_err());
+        assert_eq!(poll.result.unwrap().unwrap().sources_true(), 2);
+        assert!(registry.is_reachable(0));
+
+        let poll = registry.poll();
+        assert_eq!(poll.result.unwrap().unwrap().sources_true(), 1);
+        assert!(!registry.is_reachable(0));
+        assert_eq!(
+            *events.borrow(),
+            [
+                SourceEvent::Reachable { source: 0 },
+                SourceEvent::Reachable { source: 1 },
+                SourceEvent::Unreachable {
+                    source: 0,
+                    missed: 2
+                },
+                SourceEvent::Expired { source: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_falseticker_policy() {
+        // Offsets by poll, which are popped from the back:
+        let offsets = |offsets: [i64; 6]| Mock {
+            samples: offsets
+                .into_iter()
+                .rev()
+                .map(|offset| {
+                    Ok(Sample {
+                        local_time: 0,
+                        offset,
+                        uncertainty: 10,
+                    })
+                })
+                .collect(),
+        };
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = SourceRegistry::new().with_falseticker_policy(FalsetickerPolicy {
+            strikes: 2,
+            probe_interval: 2,
+        });
+        registry
+            .engine_mut()
+            .add_observer(Recorder(Rc::clone(&events)));
+        registry.register(offsets([0; 6]));
+        registry.register(offsets([0; 6]));
+        registry.register(offsets([1_000, 1_000, 1_000, 1_000, 1_000, 0]));
+
+        registry.poll();
+        assert_eq!(registry.health(2), Some(Health::Healthy { strikes: 1 }));
+        registry.poll();
+        assert_eq!(registry.health(2), Some(Health::Falseticker { polls: 0 }));
+        // Excluded, and still a falseticker after the first probe:
+        for _ in 0..3 {
+            let poll = registry.poll();
+            assert_eq!(poll.result.unwrap().unwrap().sources_false(), 0);
+        }
+        assert_eq!(registry.health(2), Some(Health::Falseticker { polls: 1 }));
+        registry.poll();
+        assert_eq!(registry.health(2), Some(Health::Healthy { strikes: 0 }));
+        assert_eq!(
+            events.borrow()[3..],
+            [
+                SourceEvent::Falseticker {
+                    source: 2,
+                    strikes: 2
+                },
+                SourceEvent::Rehabilitated { source: 2 },
+            ]
+        );
+    }
+
+    /// Yields once before each sample, as a source waiting on I/O would.
+    struct AsyncMock {
+        offset: i64,
+        yielded: bool,
+    }
+
+    impl AsyncClockSource for AsyncMock {
+        type Error = ConversionError;
+
+        async fn sample(&mut self) -> Result<Sample, ConversionError> {
+            poll_fn(|context| {
+                if self.yielded {
+                    return task::Poll::Ready(());
+                }
+                self.yielded = true;
+                context.waker().wake_by_ref();
+                task::Poll::Pending
+            })
+            .await;
+            Ok(Sample {
+                local_time: 0,
+                offset: self.offset,
+                uncertainty: 10,
+            })
+        }
+
+        fn id(&self) -> &str {
+            "async mock"
+        }
+    }
+
+    #[test]
+    fn test_sample_concurrently() {
+        let mut sources = [0, 15, 1_000].map(|offset| AsyncMock {
+            offset,
+            yielded: false,
+        });
+        let mut engine = Engine::new();
+        let mut future = pin!(sample_concurrently(&mut sources, &mut engine));
+        let mut context = task::Context::from_waker(task::Waker::noop());
+        let poll = loop {
+            if let task::Poll::Ready(poll) = future.as_mut().poll(&mut context) {
+                break poll;
+            }
+        };
+        assert_eq!(poll.samples.len(), 3);
+        let interval = poll.result.unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (5, 10));
+    }
+}
@@ -0,0 +1,233 @@
+//! The shared-memory refclock segment of ntpd, chrony, and gpsd. A refclock driver such as gpsd
+//! writes the time of the reference clock and the local time at which it was taken into a System V
+//! shared-memory segment with the key `0x4e545030 + unit`, and the time daemon reads it.
+//!
+//! The reader follows the protocol of the segment: a sample is only used if it is marked valid and
+//! the writer did not update it while it was being read, and it is marked invalid once read so that
+//! it is not used twice.
+
+use std::io;
+use std::ptr::{self, addr_of, addr_of_mut};
+
+use super::{ClockSource, Sample, SourceKind, SourceMetadata};
+
+/// The key of the segment of unit 0.
+pub const SHM_KEY: i32 = 0x4e54_5030;
+
+/// The layout of the segment.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShmTime {
+    /// 0 if only `valid` protects the sample, 1 if `count` does as well.
+    pub mode: libc::c_int,
+    pub count: libc::c_int,
+    pub clock_timestamp_sec: libc::time_t,
+    pub clock_timestamp_usec: libc::c_int,
+    pub receive_timestamp_sec: libc::time_t,
+    pub receive_timestamp_usec: libc::c_int,
+    pub leap: libc::c_int,
+    /// The precision of the reference clock, as a power of two of seconds.
+    pub precision: libc::c_int,
+    pub nsamples: libc::c_int,
+    pub valid: libc::c_int,
+    pub clock_timestamp_nsec: libc::c_uint,
+    pub receive_timestamp_nsec: libc::c_uint,
+    pub dummy: [libc::c_int; 8],
+}
+
+#[derive(Debug)]
+pub enum ShmError {
+    /// There is no new sample since the last read.
+    NoSample,
+    /// The writer updated the sample while it was being read.
+    Interrupted,
+    /// The timestamps are out of the range of the crate's time domain.
+    OutOfRange,
+}
+
+impl core::fmt::Display for ShmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ShmError::NoSample => write!(f, "no new SHM sample"),
+            ShmError::Interrupted => write!(f, "SHM sample was updated while being read"),
+            ShmError::OutOfRange => write!(f, "SHM timestamps out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ShmError {}
+
+// `time_t` is only 64 bits on some platforms.
+#[allow(clippy::useless_conversion)]
+fn timestamp_nanos(sec: libc::time_t, usec: libc::c_int, nsec: libc::c_uint) -> Option<i64> {
+    // Writers that only fill in microseconds leave the nanoseconds at zero, or consistent with
+    // the microseconds:
+    let subsecond = if nsec as i64 / 1_000 == usec as i64 {
+        nsec as i64
+    } else {
+        usec as i64 * 1_000
+    };
+    i64::from(sec)
+        .checked_mul(1_000_000_000)?
+        .checked_add(subsecond)
+}
+
+impl ShmTime {
+    /// Converts the timestamps of the segment into a sample, with an uncertainty of the stated
+    /// precision of the reference clock.
+    pub fn to_sample(&self) -> Result<Sample, ShmError> {
+        let clock = timestamp_nanos(
+            self.clock_timestamp_sec,
+            self.clock_timestamp_usec,
+            self.clock_timestamp_nsec,
+        );
+        let receive = timestamp_nanos(
+            self.receive_timestamp_sec,
+            self.receive_timestamp_usec,
+            self.receive_timestamp_nsec,
+        );
+        let (Some(clock), Some(receive)) = (clock, receive) else {
+            return Err(ShmError::OutOfRange);
+        };
+        // 2^precision seconds, rounded up to the nanosecond:
+        let uncertainty = match self.precision {
+            precision @ -63..=-1 => 1_000_000_000_u64.div_ceil(1 << -precision) as i64,
+            precision @ 0..=32 => 1_000_000_000_i64 << precision,
+            _ => return Err(ShmError::OutOfRange),
+        };
+        Ok(Sample {
+            local_time: receive,
+            offset: clock.checked_sub(receive).ok_or(ShmError::OutOfRange)?,
+            uncertainty,
+        })
+    }
+}
+
+/// An attached refclock segment.
+pub struct ShmRefclock {
+    segment: *mut ShmTime,
+    id: String,
+}
+
+impl ShmRefclock {
+    /// Attaches the existing segment of `unit`, as created by the refclock driver.
+    pub fn attach(unit: i32) -> io::Result<ShmRefclock> {
+        // SAFETY: shmget and shmat have no memory-safety preconditions. The segment is at least as
+        // large as `ShmTime`, or shmget fails.
+        unsafe {
+            let id = libc::shmget(SHM_KEY + unit, size_of::<ShmTime>(), 0);
+            if id < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let segment = libc::shmat(id, ptr::null(), 0);
+            if segment as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(ShmRefclock {
+                segment: segment.cast(),
+                id: format!("SHM({})", unit),
+            })
+        }
+    }
+
+    /// Reads the sample in the segment, and marks it as read.
+    pub fn read(&mut self) -> Result<ShmTime, ShmError> {
+        let segment = self.segment;
+        // SAFETY: the segment is attached for the lifetime of `self`. It is shared with another
+        // process, so every access is volatile.
+        unsafe {
+            let count = ptr::read_volatile(addr_of!((*segment).count));
+            let sample = ptr::read_volatile(segment);
+            if sample.valid == 0 {
+                return Err(ShmError::NoSample);
+            }
+            if sample.mode == 1 && ptr::read_volatile(addr_of!((*segment).count)) != count {
+                return Err(ShmError::Interrupted);
+            }
+            ptr::write_volatile(addr_of_mut!((*segment).valid), 0);
+            Ok(sample)
+        }
+    }
+}
+
+impl Drop for ShmRefclock {
+    fn drop(&mut self) {
+        // SAFETY: the segment was attached by `attach`, and is only detached here.
+        unsafe {
+            libc::shmdt(self.segment.cast());
+        }
+    }
+}
+
+impl ClockSource for ShmRefclock {
+    type Error = ShmError;
+
+    fn sample(&mut self) -> Result<Sample, ShmError> {
+        self.read()?.to_sample()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            kind: SourceKind::Refclock,
+            stratum: Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment() -> ShmTime {
+        ShmTime {
+            mode: 1,
+            count: 7,
+            clock_timestamp_sec: 1_700_000_000,
+            clock_timestamp_usec: 250,
+            clock_timestamp_nsec: 250_100,
+            receive_timestamp_sec: 1_700_000_000,
+            receive_timestamp_usec: 200,
+            receive_timestamp_nsec: 0,
+            precision: -20,
+            valid: 1,
+            ..ShmTime::default()
+        }
+    }
+
+    #[test]
+    fn test_shm_sample() {
+        let sample = segment().to_sample().unwrap();
+        assert_eq!(sample.local_time, 1_700_000_000_000_200_000);
+        assert_eq!(sample.offset, 50_100);
+        // 2^-20 seconds:
+        assert_eq!(sample.uncertainty, 954);
+    }
+
+    #[test]
+    fn test_shm_refclock() {
+        // A private segment, attached as the refclock driver would create it:
+        let unit = 0x100 + std::process::id() as i32 % 0x1000;
+        let id = unsafe {
+            libc::shmget(
+                SHM_KEY + unit,
+                size_of::<ShmTime>(),
+                libc::IPC_CREAT | libc::IPC_EXCL | 0o600,
+            )
+        };
+        if id < 0 {
+            // System V IPC is unavailable, e.g. in a sandbox.
+            return;
+        }
+        let mut refclock = ShmRefclock::attach(unit).unwrap();
+        assert_eq!(refclock.id(), format!("SHM({})", unit));
+        unsafe { ptr::write_volatile(refclock.segment, segment()) };
+        assert_eq!(refclock.sample().unwrap().offset, 50_100);
+        assert!(matches!(refclock.sample(), Err(ShmError::NoSample)));
+        drop(refclock);
+        unsafe { libc::shmctl(id, libc::IPC_RMID, ptr::null_mut()) };
+    }
+}
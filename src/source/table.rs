@@ -0,0 +1,166 @@
+//! A table of the latest sample of every source, shared between threads: sampler threads or tasks
+//! each update their source through a `SourceHandle`, while the selection loop takes a `Snapshot`
+//! of all of them at once.
+//!
+//! The table only holds samples, so a sampler that panicked while updating it leaves it consistent,
+//! and the lock is used regardless of poisoning.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::vec::Vec;
+
+use super::Sample;
+use crate::{Engine, Interval, MarzulloError, SourceBound};
+
+#[derive(Debug, Default)]
+struct Samples {
+    samples: BTreeMap<u8, Sample>,
+    generation: u64,
+}
+
+/// The latest sample of every source, by its index. Clones share the same table.
+#[derive(Debug, Default, Clone)]
+pub struct SourceTable {
+    samples: Arc<RwLock<Samples>>,
+}
+
+/// Updates the sample of one source of a `SourceTable`, e.g. from the thread that samples it.
+#[derive(Debug, Clone)]
+pub struct SourceHandle {
+    source: u8,
+    samples: Arc<RwLock<Samples>>,
+}
+
+/// The samples of a `SourceTable` at one instant.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Snapshot {
+    /// The number of updates of the table before the snapshot, so that a selection loop can skip
+    /// a snapshot that did not change since the last one.
+    pub generation: u64,
+    /// The latest sample of every source that has one, by its index.
+    pub samples: Vec<(u8, Sample)>,
+}
+
+fn write(samples: &RwLock<Samples>) -> RwLockWriteGuard<'_, Samples> {
+    samples.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn read(samples: &RwLock<Samples>) -> RwLockReadGuard<'_, Samples> {
+    samples.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl SourceTable {
+    pub fn new() -> SourceTable {
+        SourceTable::default()
+    }
+
+    /// Returns a handle that updates the sample of `source`.
+    pub fn handle(&self, source: u8) -> SourceHandle {
+        SourceHandle {
+            source,
+            samples: Arc::clone(&self.samples),
+        }
+    }
+
+    /// Returns the samples of every source, all as of the same instant.
+    pub fn snapshot(&self) -> Snapshot {
+        let samples = read(&self.samples);
+        Snapshot {
+            generation: samples.generation,
+            samples: samples
+                .samples
+                .iter()
+                .map(|(&source, &sample)| (source, sample))
+                .collect(),
+        }
+    }
+
+    /// Drops the samples of every source, e.g. after the local clock was set.
+    pub fn clear(&self) {
+        let mut samples = write(&self.samples);
+        samples.samples.clear();
+        samples.generation += 1;
+    }
+}
+
+impl SourceHandle {
+    pub fn source(&self) -> u8 {
+        self.source
+    }
+
+    /// Replaces the sample of the source.
+    pub fn update(&self, sample: Sample) {
+        let mut samples = write(&self.samples);
+        samples.samples.insert(self.source, sample);
+        samples.generation += 1;
+    }
+
+    /// Drops the sample of the source, e.g. when it can no longer be sampled, so that it is no
+    /// longer selected over.
+    pub fn remove(&self) {
+        let mut samples = write(&self.samples);
+        if samples.samples.remove(&self.source).is_some() {
+            samples.generation += 1;
+        }
+    }
+}
+
+impl Snapshot {
+    /// Returns the bounds of every sample, without those whose bounds cannot be computed.
+    pub fn source_bounds(&self) -> Vec<SourceBound> {
+        self.samples
+            .iter()
+            .filter_map(|&(source, sample)| sample.bounds(source).ok())
+            .flatten()
+            .collect()
+    }
+
+    /// Selects over the bounds of every sample with `engine`.
+    pub fn select(&self, engine: &mut Engine) -> Result<Option<Interval>, MarzulloError> {
+        engine.select(self.source_bounds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_source_table() {
+        let table = SourceTable::new();
+        assert_eq!(table.snapshot(), Snapshot::default());
+        let samplers: Vec<_> = (0..4)
+            .map(|source| {
+                let handle = table.handle(source);
+                thread::spawn(move || {
+                    for offset in 0..100 {
+                        handle.update(Sample {
+                            local_time: offset,
+                            offset: offset * 10 + handle.source() as i64,
+                            uncertainty: 50,
+                        });
+                    }
+                })
+            })
+            .collect();
+        for sampler in samplers {
+            sampler.join().unwrap();
+        }
+
+        let snapshot = table.snapshot();
+        assert_eq!(snapshot.generation, 400);
+        let offsets: Vec<_> = snapshot.samples.iter().map(|(_, s)| s.offset).collect();
+        assert_eq!(offsets, [990, 991, 992, 993]);
+        let interval = snapshot.select(&mut Engine::new()).unwrap().unwrap();
+        assert_eq!(
+            (interval.lower_bound(), interval.upper_bound()),
+            (943, 1_040)
+        );
+
+        table.handle(2).remove();
+        table.handle(2).remove();
+        let snapshot = table.snapshot();
+        assert_eq!((snapshot.generation, snapshot.samples.len()), (401, 3));
+    }
+}
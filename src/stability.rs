@@ -0,0 +1,207 @@
+//! How stable the agreed time is over successive selections: the jitter and the wander of the
+//! midpoints of the chosen intervals, as NTP reports them, and their Allan deviation, which
+//! separates the noise of the selection from the drift of the local clock by its averaging time.
+//!
+//! The Allan deviation is that of the overlapping estimator over the midpoints as phase data, at
+//! multiples of the mean time between selections, which should therefore be regular.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::Interval;
+
+/// The default number of selections to keep.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Returns the root of the mean of `sum` over `count`, to within 2⁻¹⁶ for small sums.
+fn root_mean(sum: u128, count: u128) -> f64 {
+    // The extra precision of 16 bits below the point, as long as the sum leaves room for them:
+    let shift = if sum < 1 << 90 { 32 } else { 0 };
+    ((sum << shift) / count).isqrt() as f64 / (1_u64 << (shift / 2)) as f64
+}
+
+/// The stability of the agreed time over the recorded selections.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StabilityReport {
+    /// The root mean square of the differences between successive midpoints, in nanoseconds.
+    pub jitter: i64,
+    /// The root mean square of the differences between successive frequencies, the slopes between
+    /// successive midpoints, in parts per million.
+    pub wander: f64,
+    /// The Allan deviation, a fractional frequency, by every averaging time in nanoseconds for
+    /// which there are enough selections.
+    pub deviations: Vec<(i64, f64)>,
+}
+
+/// Records the midpoint of every chosen interval, up to a capacity, the oldest dropped first.
+#[derive(Debug, Clone)]
+pub struct Stability {
+    capacity: usize,
+    /// The averaging times, as multiples of the time between selections.
+    taus: Vec<usize>,
+    /// The local time and midpoint of every selection.
+    midpoints: VecDeque<(i64, i64)>,
+}
+
+impl Default for Stability {
+    fn default() -> Stability {
+        Stability::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Stability {
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Stability {
+        assert!(capacity > 0);
+        Stability {
+            capacity,
+            taus: Vec::from([1, 2, 4, 8, 16, 32, 64]),
+            midpoints: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Computes the Allan deviation at these multiples of the time between selections, rather
+    /// than at the powers of 2 up to 64.
+    pub fn with_taus(mut self, taus: Vec<usize>) -> Stability {
+        self.taus = taus;
+        self
+    }
+
+    /// Records the interval chosen at the local time `time`. Intervals must be recorded in the
+    /// order of their times.
+    pub fn record(&mut self, time: i64, interval: &Interval) {
+        let midpoint = (interval.lower_bound() as i128 + interval.width() as i128 / 2) as i64;
+        if self.midpoints.len() == self.capacity {
+            self.midpoints.pop_front();
+        }
+        self.midpoints.push_back((time, midpoint));
+    }
+
+    pub fn len(&self) -> usize {
+        self.midpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.midpoints.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.midpoints.clear();
+    }
+
+    /// Returns the jitter of the midpoints, or `None` with fewer than 2 of them.
+    pub fn jitter(&self) -> Option<i64> {
+        let differences = self.differences();
+        if differences.is_empty() {
+            return None;
+        }
+        let squares = differences.iter().fold(0_u128, |sum, &(_, difference)| {
+            sum.saturating_add(difference.unsigned_abs().pow(2))
+        });
+        Some(root_mean(squares, differences.len() as u128) as i64)
+    }
+
+    /// Returns the wander of the midpoints, or `None` with fewer than 3 of them.
+    pub fn wander(&self) -> Option<f64> {
+        // The frequencies in parts per trillion, of which the wander is in parts per million:
+        let frequencies: Vec<i128> = self
+            .differences()
+            .iter()
+            .map(|&(elapsed, difference)| difference * 1_000_000_000_000 / elapsed.max(1))
+            .collect();
+        let squares = frequencies.windows(2).fold(0_u128, |sum, pair| {
+            sum.saturating_add(pair[1].abs_diff(pair[0]).saturating_pow(2))
+        });
+        let count = frequencies
+            .len()
+            .checked_sub(1)
+            .filter(|&count| count > 0)?;
+        Some(root_mean(squares, count as u128) / 1_000_000.0)
+    }
+
+    /// Returns the overlapping Allan deviation at `n` times the mean time between selections, with
+    /// that averaging time in nanoseconds, or `None` with fewer than `2n + 1` selections.
+    pub fn allan_deviation(&self, n: usize) -> Option<(i64, f64)> {
+        let count = self.midpoints.len().checked_sub(2 * n)?;
+        if n == 0 || count == 0 {
+            return None;
+        }
+        let (first, _) = self.midpoints[0];
+        let (last, _) = self.midpoints[self.midpoints.len() - 1];
+        let tau = (last - first) / (self.midpoints.len() as i64 - 1) * n as i64;
+        if tau <= 0 {
+            return None;
+        }
+        let phase = |index: usize| self.midpoints[index].1 as i128;
+        let squares = (0..count).fold(0_u128, |sum, i| {
+            let difference = phase(i + 2 * n) - 2 * phase(i + n) + phase(i);
+            sum.saturating_add(difference.unsigned_abs().saturating_pow(2))
+        });
+        Some((tau, root_mean(squares, 2 * count as u128) / tau as f64))
+    }
+
+    /// Returns the jitter, the wander, and the Allan deviations at the configured averaging times,
+    /// or `None` with fewer than 3 selections.
+    pub fn report(&self) -> Option<StabilityReport> {
+        Some(StabilityReport {
+            jitter: self.jitter()?,
+            wander: self.wander()?,
+            deviations: self
+                .taus
+                .iter()
+                .filter_map(|&n| self.allan_deviation(n))
+                .collect(),
+        })
+    }
+
+    /// Returns the time elapsed and the change of the midpoint between every two successive
+    /// selections.
+    fn differences(&self) -> Vec<(i128, i128)> {
+        self.midpoints
+            .iter()
+            .zip(self.midpoints.iter().skip(1))
+            .map(|(&(t0, x0), &(t1, x1))| (t1 as i128 - t0 as i128, x1 as i128 - x0 as i128))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+
+    #[test]
+    fn test_stability() {
+        const SECOND: i64 = 1_000_000_000;
+        let mut stability = Stability::new(64);
+        assert_eq!(stability.report(), None);
+        // Midpoints alternating by 10ns around a drift of 1ppm, every second:
+        for i in 0..100 {
+            let midpoint = i * 1_000 + if i % 2 == 0 { 0 } else { 10 };
+            let interval = Interval::try_from_source_bounds(Vec::from(SourceBound::pair(
+                0,
+                midpoint - 50,
+                midpoint + 50,
+            )))
+            .unwrap()
+            .unwrap();
+            stability.record(i * SECOND, &interval);
+        }
+        assert_eq!(stability.len(), 64);
+
+        let report = stability.report().unwrap();
+        assert_eq!(report.jitter, 1_000);
+        // The frequencies alternate between 1.01ppm and 0.99ppm:
+        assert!((report.wander - 0.02).abs() < 1e-9);
+        // The drift cancels out of the second differences of 20ns, for sqrt(20² / 2) = 14.14ns:
+        let (tau, deviation) = report.deviations[0];
+        assert_eq!(tau, SECOND);
+        assert!((deviation - 14.142e-9).abs() < 1e-12);
+        // At even multiples, the alternation averages out as well:
+        assert_eq!(report.deviations[1], (2 * SECOND, 0.0));
+        assert_eq!(report.deviations.len(), 5);
+    }
+}
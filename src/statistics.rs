@@ -0,0 +1,132 @@
+//! Summary statistics of the intervals of the sources of one selection: of their midpoints, as the
+//! offsets the sources claim, and of their widths, as their uncertainty. Pre-filters reject
+//! sources by these, and reports show them next to the chosen interval.
+
+use alloc::vec::Vec;
+
+use crate::verdict::source_intervals;
+use crate::SourceBound;
+
+/// The mean, median, standard deviation, and median absolute deviation of a set of values, in
+/// the unit of the values, each rounded to the nearest integer.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Summary {
+    pub mean: i64,
+    /// The middle value, or the mean of the two middle values of an even number of values.
+    pub median: i64,
+    /// The population standard deviation.
+    pub stddev: i64,
+    /// The median of the absolute deviations from the median, which, unlike the standard
+    /// deviation, a minority of outliers cannot inflate.
+    pub mad: i64,
+}
+
+/// The summaries of the midpoints and widths of the intervals of the sources.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceStatistics {
+    /// The number of sources with both bounds.
+    pub sources: usize,
+    pub midpoints: Summary,
+    pub widths: Summary,
+}
+
+/// Returns twice the median of sorted values, which is an integer.
+fn twice_median(sorted: &[i128]) -> i128 {
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        return 2 * sorted[middle];
+    }
+    sorted[middle - 1] + sorted[middle]
+}
+
+/// Divides, rounding to the nearest integer, half away from zero.
+fn divide(numerator: i128, denominator: i128) -> i128 {
+    (numerator + numerator.signum() * denominator / 2) / denominator
+}
+
+/// Summarizes the values, or returns `None` if there are none.
+pub fn summarize(values: &[i64]) -> Option<Summary> {
+    if values.is_empty() {
+        return None;
+    }
+    let count = values.len() as i128;
+    let mut sorted: Vec<i128> = values.iter().map(|&value| value as i128).collect();
+    sorted.sort_unstable();
+    let sum: i128 = sorted.iter().sum();
+    let median = twice_median(&sorted);
+    // The squares of the deviations from the exact mean, scaled by the count to stay integers:
+    let squares = sorted.iter().fold(0_u128, |squares, &value| {
+        squares.saturating_add((value * count - sum).unsigned_abs().saturating_pow(2))
+    });
+    let variance = squares / (count * count * count) as u128;
+    let root = variance.isqrt();
+    // Rounded to the nearest integer, as `(root + 0.5)² = root² + root + 0.25`:
+    let stddev = if variance - root * root > root {
+        root + 1
+    } else {
+        root
+    };
+    // The deviations from the exact median, doubled like it, so their median is quadrupled:
+    let mut deviations: Vec<i128> = sorted
+        .iter()
+        .map(|&value| (2 * value - median).abs())
+        .collect();
+    deviations.sort_unstable();
+    Some(Summary {
+        mean: divide(sum, count) as i64,
+        median: divide(median, 2) as i64,
+        stddev: i64::try_from(stddev).unwrap_or(i64::MAX),
+        mad: i64::try_from(divide(twice_median(&deviations), 4)).unwrap_or(i64::MAX),
+    })
+}
+
+/// Summarizes the midpoints and widths of the sources with both bounds, or returns `None` if there
+/// are none.
+pub fn source_statistics(source_bounds: &[SourceBound]) -> Option<SourceStatistics> {
+    let sources = source_intervals(source_bounds);
+    let midpoints: Vec<i64> = sources
+        .iter()
+        .map(|&(_, lower, upper)| (lower as i128 + upper as i128).div_euclid(2) as i64)
+        .collect();
+    let widths: Vec<i64> = sources
+        .iter()
+        .map(|&(_, lower, upper)| i64::try_from(upper.abs_diff(lower)).unwrap_or(i64::MAX))
+        .collect();
+    Some(SourceStatistics {
+        sources: sources.len(),
+        midpoints: summarize(&midpoints)?,
+        widths: summarize(&widths)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_source_statistics() {
+        assert_eq!(source_statistics(&[]), None);
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 8, 12), (1, 9, 13), (2, 10, 14), (3, 40, 60)]);
+        let statistics = source_statistics(&source_bounds).unwrap();
+        assert_eq!(statistics.sources, 4);
+        // Of the midpoints 10, 11, 12, and 50, the outlier pulls the mean and standard deviation,
+        // but hardly the median and its absolute deviation:
+        assert_eq!(
+            statistics.midpoints,
+            Summary {
+                mean: 21,
+                median: 12,
+                stddev: 17,
+                mad: 1,
+            }
+        );
+        assert_eq!(statistics.widths.median, 4);
+        assert_eq!(summarize(&[-3, -4]).unwrap().median, -4);
+    }
+}
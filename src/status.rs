@@ -0,0 +1,194 @@
+//! One well-defined state of synchronization, from the quorum of the chosen interval, its width,
+//! and the health of the sources, so that callers can gate behavior on it, e.g. refuse writes
+//! while unsynchronized.
+
+use crate::pipeline::SelectionOutcome;
+use crate::Interval;
+
+/// Why the local clock is synchronized, but less well than it should be.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Degradation {
+    /// The chosen interval is wider than the maximum.
+    Wide { width: u64, max_width: u64 },
+    /// Fewer sources were selected over than the minimum, so that fewer faults are tolerated.
+    FewSources { sources: u8, min_sources: u8 },
+    /// Some sources do not overlap the chosen interval.
+    Falsetickers { sources: u8 },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SynchronizationStatus {
+    /// A quorum of the sources agrees, and nothing degrades the agreement.
+    Synchronized,
+    /// A quorum of the sources agrees, but less well than it should.
+    Degraded { reason: Degradation },
+    /// There are no sources, or no quorum of them agrees.
+    Unsynchronized,
+    /// The sources no longer agree, and the estimate is extrapolated from the local time `since`.
+    Holdover { since: i64 },
+}
+
+impl SynchronizationStatus {
+    /// Returns whether the local clock may be trusted: it is synchronized, if degraded.
+    pub fn is_synchronized(&self) -> bool {
+        matches!(
+            self,
+            SynchronizationStatus::Synchronized | SynchronizationStatus::Degraded { .. }
+        )
+    }
+}
+
+/// The thresholds by which a selection is synchronized, degraded, or unsynchronized.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct StatusPolicy {
+    /// The sources that must overlap the chosen interval, or `None` for a majority.
+    quorum: Option<u8>,
+    max_width: Option<u64>,
+    min_sources: u8,
+}
+
+impl Default for StatusPolicy {
+    fn default() -> StatusPolicy {
+        StatusPolicy::new()
+    }
+}
+
+impl StatusPolicy {
+    /// Requires a majority of the sources to agree, and 3 sources to not be degraded, the fewest
+    /// to outvote a falseticker.
+    pub fn new() -> StatusPolicy {
+        StatusPolicy {
+            quorum: None,
+            max_width: None,
+            min_sources: 3,
+        }
+    }
+
+    /// Requires `quorum` sources to agree rather than a majority, e.g. the `2f + 1` of a
+    /// `ByzantineQuorum`.
+    pub fn with_quorum(mut self, quorum: u8) -> StatusPolicy {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Degrades chosen intervals wider than `max_width`.
+    pub fn with_max_width(mut self, max_width: u64) -> StatusPolicy {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Degrades selections over fewer than `min_sources` sources.
+    pub fn with_min_sources(mut self, min_sources: u8) -> StatusPolicy {
+        self.min_sources = min_sources;
+        self
+    }
+
+    /// Returns the status of a chosen interval, or of no interval.
+    pub fn status(&self, interval: Option<&Interval>) -> SynchronizationStatus {
+        let Some(interval) = interval else {
+            return SynchronizationStatus::Unsynchronized;
+        };
+        let sources = interval.sources_true.saturating_add(interval.sources_false);
+        let quorum = match self.quorum {
+            Some(quorum) => interval.sources_true >= quorum,
+            None => interval.sources_true as usize * 2 > sources as usize,
+        };
+        if !quorum {
+            return SynchronizationStatus::Unsynchronized;
+        }
+        let width = interval.width();
+        let reason = match self.max_width {
+            Some(max_width) if width > max_width => Degradation::Wide { width, max_width },
+            _ if sources < self.min_sources => Degradation::FewSources {
+                sources,
+                min_sources: self.min_sources,
+            },
+            _ if interval.sources_false > 0 => Degradation::Falsetickers {
+                sources: interval.sources_false,
+            },
+            _ => return SynchronizationStatus::Synchronized,
+        };
+        SynchronizationStatus::Degraded { reason }
+    }
+
+    /// Returns the status of a `Pipeline` run, which holds over if the outcome does.
+    pub fn outcome_status(&self, outcome: &SelectionOutcome) -> SynchronizationStatus {
+        if let Some(holdover) = outcome.holdover {
+            return SynchronizationStatus::Holdover {
+                since: holdover.since,
+            };
+        }
+        self.status(outcome.interval.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Holdover;
+    use crate::testing::bounds;
+
+    fn select(sources: &[(u8, i64, i64)]) -> Interval {
+        Interval::try_from_source_bounds(bounds(sources))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_status() {
+        let policy = StatusPolicy::new().with_max_width(10);
+        assert_eq!(policy.status(None), SynchronizationStatus::Unsynchronized);
+
+        let interval = select(&[(0, 0, 10), (1, 2, 12), (2, 4, 14)]);
+        assert_eq!(
+            policy.status(Some(&interval)),
+            SynchronizationStatus::Synchronized
+        );
+        let interval = select(&[(0, 0, 10), (1, 2, 12), (2, 4, 14), (3, 50, 60)]);
+        assert_eq!(
+            policy.status(Some(&interval)),
+            SynchronizationStatus::Degraded {
+                reason: Degradation::Falsetickers { sources: 1 },
+            }
+        );
+        let interval = select(&[(0, 0, 100), (1, 20, 120)]);
+        let status = policy.status(Some(&interval));
+        assert_eq!(
+            status,
+            SynchronizationStatus::Degraded {
+                reason: Degradation::Wide {
+                    width: 80,
+                    max_width: 10,
+                },
+            }
+        );
+        assert!(status.is_synchronized());
+
+        // Two of four sources are no majority, but a quorum of 2:
+        let interval = select(&[(0, 0, 10), (1, 2, 12), (2, 40, 50), (3, 70, 80)]);
+        assert_eq!(
+            policy.status(Some(&interval)),
+            SynchronizationStatus::Unsynchronized
+        );
+        assert!(policy
+            .with_quorum(2)
+            .status(Some(&interval))
+            .is_synchronized());
+
+        let outcome = SelectionOutcome {
+            holdover: Some(Holdover {
+                since: 5,
+                duration: 10,
+            }),
+            ..SelectionOutcome::default()
+        };
+        assert_eq!(
+            policy.outcome_status(&outcome),
+            SynchronizationStatus::Holdover { since: 5 }
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! An SVG rendering of a selection for incident reports and documentation: one bar per source,
+//! colored by verdict, the chosen interval highlighted across all rows, and a histogram of how many
+//! sources overlap each region below them.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::scale::{bounded_values, Scale};
+use crate::{Report, Verdict};
+
+const WIDTH: f64 = 640.0;
+const MARGIN: f64 = 80.0;
+const ROW_HEIGHT: f64 = 20.0;
+const HISTOGRAM_HEIGHT: f64 = 80.0;
+
+const TRUECHIMER_COLOR: &str = "#4c78a8";
+const FALSETICKER_COLOR: &str = "#e45756";
+const CHOSEN_COLOR: &str = "#54a24b";
+
+impl Scale {
+    fn x(&self, value: i64) -> f64 {
+        MARGIN + self.fraction(value) * (WIDTH - 2.0 * MARGIN)
+    }
+}
+
+impl Report<i64> {
+    /// Renders the report as a standalone SVG document. Unbounded sides are clamped to the range of
+    /// the bounded values.
+    pub fn svg(&self) -> String {
+        let rows = self.verdicts.len() as f64;
+        let height = (rows + 2.0) * ROW_HEIGHT + HISTOGRAM_HEIGHT;
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="12">"#,
+            w = WIDTH,
+            h = height
+        );
+        let Some(interval) = &self.interval else {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}">no sources</text>"#,
+                MARGIN, ROW_HEIGHT
+            );
+            svg.push_str("</svg>\n");
+            return svg;
+        };
+
+        let values = bounded_values(self);
+        let scale = Scale::new(&values);
+
+        let (chosen_x, chosen_end) = (scale.x(interval.lower_bound), scale.x(interval.upper_bound));
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="0" width="{:.1}" height="{:.1}" fill="{}" fill-opacity="0.2"/>"#,
+            chosen_x,
+            (chosen_end - chosen_x).max(1.0),
+            (rows + 1.0) * ROW_HEIGHT,
+            CHOSEN_COLOR
+        );
+
+        for (row, verdict) in self.verdicts.iter().enumerate() {
+            let y = row as f64 * ROW_HEIGHT + 4.0;
+            let (x, end) = (scale.x(verdict.lower), scale.x(verdict.upper));
+            let color = match verdict.verdict {
+                Verdict::Truechimer => TRUECHIMER_COLOR,
+                Verdict::Falseticker => FALSETICKER_COLOR,
+            };
+            let _ = writeln!(
+                svg,
+                r#"<text x="4" y="{:.1}">source {}</text>"#,
+                y + 12.0,
+                verdict.source
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}..={} {:?}</title></rect>"#,
+                x,
+                y,
+                (end - x).max(1.0),
+                ROW_HEIGHT - 8.0,
+                color,
+                verdict.lower,
+                verdict.upper,
+                verdict.verdict
+            );
+        }
+
+        // The number of sources overlapping each region between consecutive bounded values:
+        let base = (rows + 2.0) * ROW_HEIGHT + HISTOGRAM_HEIGHT;
+        let sources = self.verdicts.len().max(1) as f64;
+        for region in values.windows(2) {
+            let overlap = self
+                .verdicts
+                .iter()
+                .filter(|verdict| verdict.lower <= region[0] && verdict.upper >= region[1])
+                .count();
+            let (x, end) = (scale.x(region[0]), scale.x(region[1]));
+            let bar = overlap as f64 / sources * (HISTOGRAM_HEIGHT - ROW_HEIGHT);
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}..={}: {} sources</title></rect>"#,
+                x,
+                base - bar,
+                end - x,
+                bar,
+                TRUECHIMER_COLOR,
+                region[0],
+                region[1],
+                overlap
+            );
+        }
+
+        let axis = (rows + 1.0) * ROW_HEIGHT + 12.0;
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            scale.x(scale.min),
+            axis,
+            scale.min
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            scale.x(scale.max),
+            axis,
+            scale.max
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" fill="{}">{}..={}</text>"#,
+            (chosen_x + chosen_end) / 2.0,
+            axis,
+            CHOSEN_COLOR,
+            interval.lower_bound,
+            interval.upper_bound
+        );
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceBound;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_svg() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 10, 12));
+        source_bounds.extend(SourceBound::pair(2, 14, 15));
+        let svg = Report::try_from_source_bounds(source_bounds).unwrap().svg();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches(FALSETICKER_COLOR).count(), 1);
+        // Sources 0 and 1 overlap in 11..=12:
+        assert!(svg.contains("<title>11..=12: 2 sources</title>"));
+        assert!(svg.contains(">11..=12</text>"));
+
+        let svg = Report::<i64>::try_from_source_bounds(Vec::new())
+            .unwrap()
+            .svg();
+        assert!(svg.contains("no sources"));
+    }
+}
@@ -0,0 +1,168 @@
+//! Sources with a payload of metadata each, e.g. an address, name, stratum, or tags, that is
+//! carried through pre-filtering and selection to every verdict, rather than mapping the index of
+//! a source back to a table of one's own.
+
+use alloc::vec::Vec;
+
+use crate::prefilter::{Exclusion, PreFilter};
+use crate::verdict::SourceVerdict;
+use crate::{Interval, MarzulloError, SourceBound, Value};
+
+/// The verdict of a source, with its metadata.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TaggedVerdict<'a, M, T = i64> {
+    pub metadata: &'a M,
+    pub verdict: SourceVerdict<T>,
+}
+
+/// The chosen interval, with the verdict of every source that was not excluded, ordered by source.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TaggedSelection<'a, M, T = i64> {
+    pub interval: Interval<T>,
+    pub verdicts: Vec<TaggedVerdict<'a, M, T>>,
+}
+
+/// The intervals of sources and their metadata, indexed by the order they were added in.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TaggedSources<M, T = i64> {
+    metadata: Vec<M>,
+    /// The bounds of the sources that no pre-filter excluded.
+    source_bounds: Vec<SourceBound<T>>,
+}
+
+impl<M, T: Value> Default for TaggedSources<M, T> {
+    fn default() -> TaggedSources<M, T> {
+        TaggedSources::new()
+    }
+}
+
+impl<M, T: Value> TaggedSources<M, T> {
+    pub fn new() -> TaggedSources<M, T> {
+        TaggedSources {
+            metadata: Vec::new(),
+            source_bounds: Vec::new(),
+        }
+    }
+
+    /// Adds a source with the interval `[lower, upper]`, and returns its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are 256 sources already.
+    pub fn push(&mut self, metadata: M, lower: T, upper: T) -> u8 {
+        let source = u8::try_from(self.metadata.len()).expect("at most 256 sources");
+        self.metadata.push(metadata);
+        self.source_bounds
+            .extend(SourceBound::pair(source, lower, upper));
+        source
+    }
+
+    /// Adds a source, as `push`.
+    pub fn with_source(mut self, metadata: M, lower: T, upper: T) -> TaggedSources<M, T> {
+        self.push(metadata, lower, upper);
+        self
+    }
+
+    pub fn metadata(&self, source: u8) -> Option<&M> {
+        self.metadata.get(source as usize)
+    }
+
+    /// The bounds of the sources that no pre-filter excluded.
+    pub fn source_bounds(&self) -> &[SourceBound<T>] {
+        &self.source_bounds
+    }
+
+    /// Returns the verdict of every source that was not excluded relative to `interval`, e.g. one
+    /// chosen from `source_bounds` by another algorithm, with its metadata.
+    pub fn verdicts(&self, interval: &Interval<T>) -> Vec<TaggedVerdict<'_, M, T>> {
+        interval
+            .verdicts(&self.source_bounds)
+            .into_iter()
+            .map(|verdict| TaggedVerdict {
+                metadata: &self.metadata[verdict.source as usize],
+                verdict,
+            })
+            .collect()
+    }
+
+    /// Selects over the sources that were not excluded, as `Interval::try_from_source_bounds`,
+    /// and returns the verdicts with their metadata.
+    pub fn select(&self) -> Result<Option<TaggedSelection<'_, M, T>>, MarzulloError<T>> {
+        let Some(interval) = Interval::try_from_source_bounds(self.source_bounds.clone())? else {
+            return Ok(None);
+        };
+        Ok(Some(TaggedSelection {
+            verdicts: self.verdicts(&interval),
+            interval,
+        }))
+    }
+}
+
+impl<M> TaggedSources<M> {
+    /// Runs the bounds of the sources that were not excluded through `prefilter`, which may also
+    /// widen them, and returns the sources it excluded, with their metadata.
+    pub fn prefilter<P: PreFilter + ?Sized>(&mut self, prefilter: &mut P) -> Vec<(&M, Exclusion)> {
+        let source_bounds = core::mem::take(&mut self.source_bounds);
+        let excluded;
+        (self.source_bounds, excluded) = prefilter.prefilter(source_bounds);
+        excluded
+            .into_iter()
+            .map(|excluded| (&self.metadata[excluded.source as usize], excluded.reason))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefilter::MaxWidth;
+    use crate::Verdict;
+
+    #[derive(PartialEq, Eq, Debug, Clone)]
+    struct Server {
+        name: &'static str,
+        stratum: u8,
+    }
+
+    #[test]
+    fn test_tagged_sources() {
+        let server = |name, stratum| Server { name, stratum };
+        let mut sources = TaggedSources::new()
+            .with_source(server("a", 1), 8, 12)
+            .with_source(server("b", 2), 9, 13)
+            .with_source(server("c", 2), -1_000, 1_000)
+            .with_source(server("d", 3), 40, 60);
+        assert_eq!(sources.metadata(2).map(|server| server.name), Some("c"));
+
+        let excluded = sources.prefilter(&mut MaxWidth::new(100));
+        assert_eq!(
+            excluded,
+            [(
+                &server("c", 2),
+                Exclusion::TooWide {
+                    width: 2_000,
+                    max_width: 100,
+                }
+            )]
+        );
+        let selection = sources.select().unwrap().unwrap();
+        assert_eq!(
+            (
+                selection.interval.lower_bound(),
+                selection.interval.upper_bound()
+            ),
+            (9, 12)
+        );
+        let falsetickers: Vec<&str> = selection
+            .verdicts
+            .iter()
+            .filter(|tagged| tagged.verdict.verdict == Verdict::Falseticker)
+            .map(|tagged| tagged.metadata.name)
+            .collect();
+        assert_eq!(falsetickers, ["d"]);
+        assert_eq!(selection.verdicts.len(), 3);
+        assert_eq!(selection.verdicts[0].metadata.stratum, 1);
+
+        assert!(TaggedSources::<Server>::new().select().unwrap().is_none());
+    }
+}
@@ -0,0 +1,234 @@
+//! Test support for checking the algorithm (and ports of it) exhaustively over small inputs.
+//!
+//! [`Configurations`] enumerates every possible set of source intervals for a small number of
+//! sources over a small value domain, and [`brute_force`] is a reference implementation that
+//! computes the expected interval without a sweep.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::ordering::{BoundOrder, LowerFirst, UpperFirst};
+use crate::{BoundType, Interval, SourceBound, Value};
+
+/// An iterator over all configurations of `sources` intervals whose bounds lie in `domain`,
+/// i.e. every combination of `[lower, upper]` pairs with `lower <= upper` for each source.
+/// Each configuration is yielded as the source bounds to feed to the algorithm.
+#[derive(Debug, Clone)]
+pub struct Configurations {
+    intervals: Vec<(i64, i64)>,
+    /// An index into `intervals` for every source, advanced like an odometer.
+    cursor: Vec<usize>,
+    done: bool,
+}
+
+impl Configurations {
+    pub fn new(sources: usize, domain: RangeInclusive<i64>) -> Configurations {
+        let mut intervals = Vec::new();
+        for lower in domain.clone() {
+            for upper in lower..=*domain.end() {
+                intervals.push((lower, upper));
+            }
+        }
+
+        Configurations {
+            done: intervals.is_empty() && sources > 0,
+            intervals,
+            cursor: vec![0; sources],
+        }
+    }
+}
+
+impl Iterator for Configurations {
+    type Item = Vec<SourceBound>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut source_bounds = Vec::with_capacity(self.cursor.len() * 2);
+        for (source, &idx) in self.cursor.iter().enumerate() {
+            let (lower, upper) = self.intervals[idx];
+            source_bounds.extend(SourceBound::pair(source as u8, lower, upper));
+        }
+
+        // Advance the odometer, finishing once every digit has wrapped around:
+        self.done = true;
+        for idx in self.cursor.iter_mut() {
+            *idx += 1;
+            if *idx < self.intervals.len() {
+                self.done = false;
+                break;
+            }
+            *idx = 0;
+        }
+
+        Some(source_bounds)
+    }
+}
+
+/// Returns the lower and upper bound of every `(source, lower, upper)`, for test fixtures.
+pub fn bounds<T: Value>(sources: &[(u8, T, T)]) -> Vec<SourceBound<T>> {
+    sources
+        .iter()
+        .flat_map(|(source, lower, upper)| SourceBound::pair(*source, lower.clone(), upper.clone()))
+        .collect()
+}
+
+/// Computes the smallest interval consistent with the largest number of sources by checking the
+/// coverage of every candidate `[lower, upper]` formed from the bound values. Of the smallest
+/// candidates, the leftmost is chosen, matching the tie-break of the sweep.
+pub fn brute_force<T: Value>(source_bounds: &[SourceBound<T>]) -> Option<Interval<T>> {
+    reference(
+        source_bounds,
+        |(lower, upper), candidate_lower, candidate_upper| {
+            lower <= candidate_lower && upper >= candidate_upper
+        },
+    )
+}
+
+/// Like [`brute_force`], for the sweep of `UpperFirst`: intervals which only touch do not overlap,
+/// so a source covers a candidate of no width only if it strictly contains it, or has no width at
+/// the same value.
+pub fn brute_force_upper_first<T: Value>(source_bounds: &[SourceBound<T>]) -> Option<Interval<T>> {
+    reference(
+        source_bounds,
+        |(lower, upper), candidate_lower, candidate_upper| {
+            if candidate_lower < candidate_upper {
+                lower <= candidate_lower && upper >= candidate_upper
+            } else {
+                (lower < candidate_lower && upper > candidate_upper)
+                    || (lower == candidate_lower && upper == candidate_upper)
+            }
+        },
+    )
+}
+
+fn reference<T: Value>(
+    source_bounds: &[SourceBound<T>],
+    covers: impl Fn((&T, &T), &T, &T) -> bool,
+) -> Option<Interval<T>> {
+    let sources = source_bounds.len() / 2;
+    if sources == 0 {
+        return None;
+    }
+
+    let mut intervals = vec![(None, None); sources];
+    for bound in source_bounds {
+        let (lower, upper) = &mut intervals[bound.source as usize];
+        match bound.bound_type {
+            BoundType::Lower => *lower = Some(&bound.value),
+            BoundType::Upper => *upper = Some(&bound.value),
+        }
+    }
+    let intervals: Vec<(&T, &T)> = intervals
+        .into_iter()
+        .map(|(lower, upper)| (lower.expect("lower bound"), upper.expect("upper bound")))
+        .collect();
+
+    let mut best: Option<(usize, &T, &T)> = None;
+    for &(candidate_lower, _) in &intervals {
+        for &(_, candidate_upper) in &intervals {
+            if candidate_lower > candidate_upper {
+                continue;
+            }
+
+            let count = intervals
+                .iter()
+                .filter(|&&interval| covers(interval, candidate_lower, candidate_upper))
+                .count();
+            let better = match best {
+                None => true,
+                Some((best_count, best_lower, best_upper)) => {
+                    let width = T::width(candidate_lower, candidate_upper);
+                    let best_width = T::width(best_lower, best_upper);
+                    count > best_count
+                        || (count == best_count
+                            && (width < best_width
+                                || (width == best_width && candidate_lower < best_lower)))
+                }
+            };
+            if better {
+                best = Some((count, candidate_lower, candidate_upper));
+            }
+        }
+    }
+
+    let (count, lower_bound, upper_bound) = best.expect("at least one source");
+    Some(Interval {
+        lower_bound: lower_bound.clone(),
+        upper_bound: upper_bound.clone(),
+        sources_true: count as u8,
+        sources_false: (sources - count) as u8,
+    })
+}
+
+/// Runs the algorithm over every configuration of `sources` intervals in `domain`, and panics on
+/// the first configuration where it disagrees with [`brute_force`].
+pub fn model_check(sources: usize, domain: RangeInclusive<i64>) {
+    check::<LowerFirst>(sources, domain, brute_force);
+}
+
+/// Like [`model_check`], with the bounds of equal values sorted by `UpperFirst`, against
+/// [`brute_force_upper_first`].
+pub fn model_check_upper_first(sources: usize, domain: RangeInclusive<i64>) {
+    check::<UpperFirst>(sources, domain, brute_force_upper_first);
+}
+
+fn check<O: BoundOrder<i64>>(
+    sources: usize,
+    domain: RangeInclusive<i64>,
+    reference: fn(&[SourceBound<i64>]) -> Option<Interval<i64>>,
+) {
+    for source_bounds in Configurations::new(sources, domain) {
+        let expected = reference(&source_bounds);
+        let actual = Interval::try_from_source_bounds_ordered::<O>(source_bounds.clone())
+            .unwrap_or_else(|e| {
+                panic!("unexpected error {} for {:?}", e, source_bounds);
+            });
+        let summary = |interval: Option<Interval<i64>>| {
+            interval.map(|ivl| {
+                (
+                    ivl.lower_bound,
+                    ivl.upper_bound,
+                    ivl.sources_true,
+                    ivl.sources_false,
+                )
+            })
+        };
+        assert_eq!(
+            summary(actual),
+            summary(expected),
+            "disagreement with the brute-force reference for {:?}",
+            source_bounds
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configurations() {
+        // There are 3 intervals over 0..=1 ([0,0], [0,1], [1,1]), hence 3^2 configurations:
+        assert_eq!(Configurations::new(2, 0..=1).count(), 9);
+        assert_eq!(Configurations::new(0, 0..=1).count(), 1);
+        assert!(Configurations::new(2, 0..=1).all(|c| c.len() == 4));
+    }
+
+    #[test]
+    fn test_model_check_small_configurations() {
+        for sources in 0..=3 {
+            model_check(sources, 0..=4);
+            model_check_upper_first(sources, 0..=4);
+        }
+    }
+
+    #[test]
+    fn test_model_check_four_sources() {
+        model_check(4, 0..=6);
+        model_check_upper_first(4, 0..=6);
+    }
+}
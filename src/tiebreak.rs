@@ -0,0 +1,143 @@
+//! Scores that rank the candidates overlapped by equally many sources, instead of the narrowest
+//! candidate winning, e.g. to prefer the candidates a GPS source overlaps, or those near a prior.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::ordering::LowerFirst;
+use crate::trace::Sweep;
+use crate::{BoundType, Interval, MarzulloError, SourceBound, Value};
+
+/// An interval between two bounds of the sweep, with the sources that overlap it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CandidateInterval<T = i64> {
+    pub lower: T,
+    pub upper: T,
+    /// The sources overlapping the interval, in ascending order.
+    pub sources: Vec<u8>,
+}
+
+impl<T: Value> CandidateInterval<T> {
+    pub fn width(&self) -> T::Width {
+        T::width(&self.lower, &self.upper)
+    }
+
+    pub fn contains_source(&self, source: u8) -> bool {
+        self.sources.binary_search(&source).is_ok()
+    }
+}
+
+/// The score of the built-in tie-break: the narrower candidate is better.
+pub fn narrowest<T: Value>(candidate: &CandidateInterval<T>) -> Reverse<T::Width> {
+    Reverse(candidate.width())
+}
+
+/// A built-in score by name, e.g. for the `Options` of a selection.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Score {
+    /// `narrowest`.
+    #[default]
+    Narrowest,
+    /// Prefers the candidates the source overlaps, e.g. a GPS source, and of those the narrowest.
+    Overlapping(u8),
+}
+
+/// Ranks the candidates by a score, and keeps the sources that overlap the sweep.
+pub(crate) struct Scorer<F, S> {
+    score: F,
+    open: BTreeSet<u8>,
+    /// The score of the candidate.
+    best: Option<S>,
+}
+
+impl<F, S> Scorer<F, S> {
+    pub(crate) fn new(score: F) -> Scorer<F, S> {
+        Scorer {
+            score,
+            open: BTreeSet::new(),
+            best: None,
+        }
+    }
+
+    fn interval<T: Clone>(&self, lower: &T, upper: &T) -> CandidateInterval<T> {
+        CandidateInterval {
+            lower: lower.clone(),
+            upper: upper.clone(),
+            sources: self.open.iter().copied().collect(),
+        }
+    }
+}
+
+impl<T: Value, F: Fn(&CandidateInterval<T>) -> S, S: Ord> Sweep<T> for Scorer<F, S> {
+    fn count(&mut self, bound: &SourceBound<T>, _count: usize) {
+        match bound.bound_type {
+            BoundType::Lower => self.open.insert(bound.source),
+            BoundType::Upper => self.open.remove(&bound.source),
+        };
+    }
+
+    fn candidate(&mut self, lower: &T, upper: &T, _count: usize) {
+        self.best = Some((self.score)(&self.interval(lower, upper)));
+    }
+
+    fn replaces(&mut self, lower: &T, upper: &T, _count: usize, _candidate: &Interval<T>) -> bool {
+        let score = (self.score)(&self.interval(lower, upper));
+        if self.best.as_ref().is_some_and(|best| score <= *best) {
+            return false;
+        }
+        self.best = Some(score);
+        true
+    }
+}
+
+impl<T: Value> Interval<T> {
+    /// Selects like `try_from_source_bounds`, except that of the candidates overlapped by equally
+    /// many sources, the one with the highest `score` is chosen, and of equal scores the first.
+    /// With `narrowest`, this is the same selection.
+    pub fn try_from_source_bounds_scored<F, S>(
+        source_bounds: Vec<SourceBound<T>>,
+        score: F,
+    ) -> Result<Option<Interval<T>>, MarzulloError<T>>
+    where
+        F: Fn(&CandidateInterval<T>) -> S,
+        S: Ord,
+    {
+        Interval::observed::<LowerFirst, _>(source_bounds, Scorer::new(score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_scored() {
+        // Two disjoint pairs of sources, the wider one with the GPS source 3:
+        let source_bounds: Vec<SourceBound> =
+            bounds(&[(0, 0, 10), (1, 5, 12), (2, 20, 30), (3, 21, 29)]);
+        let bounds = |interval: Interval| (interval.lower_bound(), interval.upper_bound());
+        let interval = Interval::try_from_source_bounds_scored(source_bounds.clone(), narrowest)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bounds(interval), (5, 10));
+
+        let gps =
+            |candidate: &CandidateInterval| (candidate.contains_source(3), narrowest(candidate));
+        let interval = Interval::try_from_source_bounds_scored(source_bounds.clone(), gps)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bounds(interval.clone()), (21, 29));
+        assert_eq!(interval.sources_true(), 2);
+
+        // Near a prior at 100, the later candidate wins:
+        let prior = |candidate: &CandidateInterval| Reverse(candidate.lower.abs_diff(100));
+        let interval = Interval::try_from_source_bounds_scored(source_bounds, prior)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bounds(interval), (21, 29));
+    }
+}
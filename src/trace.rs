@@ -0,0 +1,155 @@
+//! A step-by-step trace of the sweep of a selection, for debugging surprising selections and for
+//! teaching the algorithm. Tracing is opt-in through `Interval::try_from_source_bounds_traced`; the
+//! other selections compile the hooks of the sweep away.
+
+use alloc::vec::Vec;
+
+use crate::{BoundType, Interval, SourceBound, Value};
+
+/// A decision of the sweep, in the order it was taken.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SweepStep<T = i64> {
+    /// A bound changed the number of sources overlapping the values after it to `count`.
+    Count {
+        value: T,
+        bound_type: BoundType,
+        source: u8,
+        count: usize,
+    },
+    /// An interval overlapped by more sources than any before it became the candidate.
+    Candidate { lower: T, upper: T, count: usize },
+    /// An interval overlapped by as many sources as the candidate was compared to it, and
+    /// `replaced` it if it was narrower, or scored higher in a scored selection.
+    TieBreak {
+        lower: T,
+        upper: T,
+        count: usize,
+        replaced: bool,
+    },
+}
+
+/// The hooks of the sweep, which do nothing unless a selection is visited or traced.
+pub(crate) trait Sweep<T: Value> {
+    fn count(&mut self, bound: &SourceBound<T>, count: usize);
+
+    fn candidate(&mut self, _lower: &T, _upper: &T, _count: usize) {}
+
+    /// Returns whether an interval overlapped by as many sources as the candidate replaces it: by
+    /// default, if it is narrower.
+    fn replaces(&mut self, lower: &T, upper: &T, _count: usize, candidate: &Interval<T>) -> bool {
+        T::width(lower, upper) < candidate.width()
+    }
+
+    fn tie_break(&mut self, _lower: &T, _upper: &T, _count: usize, _replaced: bool) {}
+}
+
+impl<T: Value, S: Sweep<T>> Sweep<T> for &mut S {
+    fn count(&mut self, bound: &SourceBound<T>, count: usize) {
+        (**self).count(bound, count);
+    }
+
+    fn candidate(&mut self, lower: &T, upper: &T, count: usize) {
+        (**self).candidate(lower, upper, count);
+    }
+
+    fn replaces(&mut self, lower: &T, upper: &T, count: usize, candidate: &Interval<T>) -> bool {
+        (**self).replaces(lower, upper, count, candidate)
+    }
+
+    fn tie_break(&mut self, lower: &T, upper: &T, count: usize, replaced: bool) {
+        (**self).tie_break(lower, upper, count, replaced);
+    }
+}
+
+/// Calls a visitor of `Interval::try_from_source_bounds_with_visitor` at every bound.
+pub(crate) struct Visitor<V>(pub V);
+
+impl<T: Value, V: FnMut(&T, BoundType, u8, usize)> Sweep<T> for Visitor<V> {
+    fn count(&mut self, bound: &SourceBound<T>, count: usize) {
+        (self.0)(&bound.value, bound.bound_type, bound.source, count);
+    }
+}
+
+impl<T: Value> Sweep<T> for Vec<SweepStep<T>> {
+    fn count(&mut self, bound: &SourceBound<T>, count: usize) {
+        self.push(SweepStep::Count {
+            value: bound.value.clone(),
+            bound_type: bound.bound_type,
+            source: bound.source,
+            count,
+        });
+    }
+
+    fn candidate(&mut self, lower: &T, upper: &T, count: usize) {
+        self.push(SweepStep::Candidate {
+            lower: lower.clone(),
+            upper: upper.clone(),
+            count,
+        });
+    }
+
+    fn tie_break(&mut self, lower: &T, upper: &T, count: usize, replaced: bool) {
+        self.push(SweepStep::TieBreak {
+            lower: lower.clone(),
+            upper: upper.clone(),
+            count,
+            replaced,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_trace() {
+        let source_bounds: Vec<SourceBound> = bounds(&[(0, 0, 10), (1, 20, 25)]);
+        let (result, steps) = Interval::try_from_source_bounds_traced(source_bounds);
+        let interval = result.unwrap().unwrap();
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (20, 25));
+        assert_eq!(
+            steps,
+            [
+                SweepStep::Count {
+                    value: 0,
+                    bound_type: BoundType::Lower,
+                    source: 0,
+                    count: 1,
+                },
+                SweepStep::Candidate {
+                    lower: 0,
+                    upper: 10,
+                    count: 1,
+                },
+                SweepStep::Count {
+                    value: 10,
+                    bound_type: BoundType::Upper,
+                    source: 0,
+                    count: 0,
+                },
+                SweepStep::Count {
+                    value: 20,
+                    bound_type: BoundType::Lower,
+                    source: 1,
+                    count: 1,
+                },
+                SweepStep::TieBreak {
+                    lower: 20,
+                    upper: 25,
+                    count: 1,
+                    replaced: true,
+                },
+                SweepStep::Count {
+                    value: 25,
+                    bound_type: BoundType::Upper,
+                    source: 1,
+                    count: 0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! A monotonic clock from the time stamp counter of x86-64 processors. Reading the TSC takes a few
+//! nanoseconds and does not enter the kernel, so it can timestamp every ping of a sample window, and
+//! its rate is stable enough to act as a jitter-free local reference.
+//!
+//! The TSC only counts time if it is invariant (it runs at a constant rate in all power states), so
+//! other processors fall back to `CLOCK_MONOTONIC`. The TSC is calibrated against
+//! `CLOCK_MONOTONIC`, and reports nanoseconds from the same origin.
+
+use core::arch::x86_64::{__cpuid, _rdtsc};
+use std::time::{Duration, SystemTime};
+
+use crate::clock::Time;
+use crate::interop::std_time::system_time_to_nanos;
+
+/// The time to calibrate the TSC for, by default.
+pub const DEFAULT_CALIBRATION: Duration = Duration::from_millis(10);
+
+fn clock_monotonic() -> u64 {
+    let mut timespec = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `timespec` outlives the call. CLOCK_MONOTONIC is always supported.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut timespec) };
+    timespec.tv_sec as u64 * 1_000_000_000 + timespec.tv_nsec as u64
+}
+
+/// A calibrated TSC.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TscClock {
+    base_ticks: u64,
+    base_nanos: u64,
+    /// Nanoseconds per tick, as a fixed-point number with 32 fractional bits.
+    scale: u64,
+}
+
+impl TscClock {
+    /// Whether the processor has an invariant TSC.
+    pub fn is_invariant() -> bool {
+        // Leaf 0x80000007 is only read if it exists:
+        __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & 1 << 8 != 0
+    }
+
+    /// Reads the TSC.
+    pub fn ticks() -> u64 {
+        // SAFETY: RDTSC is available on all x86-64 processors.
+        unsafe { _rdtsc() }
+    }
+
+    /// Calibrates the TSC against `CLOCK_MONOTONIC` for `duration`, or returns `None` if the TSC is
+    /// not invariant.
+    pub fn calibrate(duration: Duration) -> Option<TscClock> {
+        if !TscClock::is_invariant() {
+            return None;
+        }
+        let (base_ticks, base_nanos) = (TscClock::ticks(), clock_monotonic());
+        std::thread::sleep(duration);
+        let (ticks, nanos) = (TscClock::ticks(), clock_monotonic());
+        let ticks = ticks.checked_sub(base_ticks).filter(|&ticks| ticks > 0)?;
+        let scale = (((nanos - base_nanos) as u128) << 32) / ticks as u128;
+        Some(TscClock {
+            base_ticks,
+            base_nanos,
+            scale: u64::try_from(scale).ok()?,
+        })
+    }
+
+    /// Returns the nanoseconds of `CLOCK_MONOTONIC`, as extrapolated from the TSC.
+    pub fn nanos(&self) -> u64 {
+        let ticks = TscClock::ticks().saturating_sub(self.base_ticks);
+        self.base_nanos + ((ticks as u128 * self.scale as u128) >> 32) as u64
+    }
+}
+
+/// The fastest monotonic clock available: the TSC if it is invariant, or `CLOCK_MONOTONIC`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Monotonic {
+    Tsc(TscClock),
+    ClockMonotonic,
+}
+
+impl Default for Monotonic {
+    /// Calibrates the TSC for `DEFAULT_CALIBRATION`, if it can be used.
+    fn default() -> Monotonic {
+        Monotonic::new(DEFAULT_CALIBRATION)
+    }
+}
+
+impl Monotonic {
+    pub fn new(calibration: Duration) -> Monotonic {
+        match TscClock::calibrate(calibration) {
+            Some(tsc) => Monotonic::Tsc(tsc),
+            None => Monotonic::ClockMonotonic,
+        }
+    }
+
+    /// Returns the nanoseconds since an arbitrary origin, that of `CLOCK_MONOTONIC`.
+    pub fn nanos(&self) -> u64 {
+        match self {
+            Monotonic::Tsc(tsc) => tsc.nanos(),
+            Monotonic::ClockMonotonic => clock_monotonic(),
+        }
+    }
+}
+
+/// The monotonic clock of `Monotonic` with the system realtime clock, for a `ClockTracker`.
+impl Time for Monotonic {
+    fn monotonic(&self) -> u64 {
+        self.nanos()
+    }
+
+    fn realtime(&self) -> i64 {
+        system_time_to_nanos(SystemTime::now()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic() {
+        for monotonic in [Monotonic::default(), Monotonic::ClockMonotonic] {
+            let start = monotonic.nanos();
+            std::thread::sleep(Duration::from_millis(20));
+            let elapsed = monotonic.nanos() - start;
+            // Allow for the scheduler, and for the error of a short calibration:
+            assert!((19_000_000..200_000_000).contains(&elapsed), "{}", elapsed);
+            assert!(monotonic.nanos().abs_diff(clock_monotonic()) < 5_000_000);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Newtypes for time values in a specific unit, so that sources reporting in different units
+//! cannot be mixed by accident. Each unit is its own `Value`, so the bounds of a selection must
+//! all share one unit, and converting between units is explicit:
+//!
+//! ```compile_fail
+//! use marzullo::units::{Micros, Nanos};
+//! use marzullo::SourceBound;
+//!
+//! let mut source_bounds = Vec::new();
+//! source_bounds.extend(SourceBound::pair(0, Nanos(1_000), Nanos(3_000)));
+//! source_bounds.extend(SourceBound::pair(1, Micros(2), Micros(4)));
+//! ```
+
+use crate::value::saturating_distance;
+use crate::{Unbounded, Value};
+
+macro_rules! define_units {
+    ($($(#[$meta:meta])* $name:ident,)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Clone, Copy)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+            #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+            #[cfg_attr(feature = "serde", serde(transparent))]
+            pub struct $name(pub i64);
+
+            impl $name {
+                pub fn get(self) -> i64 {
+                    self.0
+                }
+            }
+
+            /// The width is a count of the same unit.
+            impl Value for $name {
+                type Width = u64;
+
+                fn width(lower: &Self, upper: &Self) -> Self::Width {
+                    upper.0.abs_diff(lower.0)
+                }
+
+                fn width_to_f64(width: &Self::Width) -> Option<f64> {
+                    Some(*width as f64)
+                }
+
+                fn signed_distance(from: &Self, to: &Self) -> Option<i64> {
+                    Some(saturating_distance(to.0.abs_diff(from.0) as u128, to < from))
+                }
+            }
+
+            impl Unbounded for $name {
+                fn unbounded_lower() -> Self {
+                    $name(i64::MIN)
+                }
+
+                fn unbounded_upper() -> Self {
+                    $name(i64::MAX)
+                }
+            }
+        )*
+    };
+}
+
+define_units!(
+    /// Nanoseconds, the unit of the crate's `i64` time domain.
+    Nanos,
+    Micros,
+    Millis,
+);
+
+impl Nanos {
+    /// Converts to microseconds, rounding towards negative infinity.
+    pub fn to_micros_floor(self) -> Micros {
+        Micros(self.0.div_euclid(1_000))
+    }
+
+    /// Converts to microseconds, rounding towards positive infinity.
+    pub fn to_micros_ceil(self) -> Micros {
+        Micros(self.0.div_euclid(1_000) + (self.0.rem_euclid(1_000) != 0) as i64)
+    }
+
+    /// Converts to milliseconds, rounding towards negative infinity.
+    pub fn to_millis_floor(self) -> Millis {
+        Millis(self.0.div_euclid(1_000_000))
+    }
+
+    /// Converts to milliseconds, rounding towards positive infinity.
+    pub fn to_millis_ceil(self) -> Millis {
+        Millis(self.0.div_euclid(1_000_000) + (self.0.rem_euclid(1_000_000) != 0) as i64)
+    }
+}
+
+impl Micros {
+    /// Converts to nanoseconds, or `None` on overflow.
+    pub fn to_nanos(self) -> Option<Nanos> {
+        self.0.checked_mul(1_000).map(Nanos)
+    }
+
+    /// Converts to milliseconds, rounding towards negative infinity.
+    pub fn to_millis_floor(self) -> Millis {
+        Millis(self.0.div_euclid(1_000))
+    }
+
+    /// Converts to milliseconds, rounding towards positive infinity.
+    pub fn to_millis_ceil(self) -> Millis {
+        Millis(self.0.div_euclid(1_000) + (self.0.rem_euclid(1_000) != 0) as i64)
+    }
+}
+
+impl Millis {
+    /// Converts to nanoseconds, or `None` on overflow.
+    pub fn to_nanos(self) -> Option<Nanos> {
+        self.0.checked_mul(1_000_000).map(Nanos)
+    }
+
+    /// Converts to microseconds, or `None` on overflow.
+    pub fn to_micros(self) -> Option<Micros> {
+        self.0.checked_mul(1_000).map(Micros)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interval, SourceBound};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_unit_conversions() {
+        assert_eq!(Micros(3).to_nanos(), Some(Nanos(3_000)));
+        assert_eq!(Micros(i64::MAX).to_nanos(), None);
+        assert_eq!(Millis(-2).to_micros(), Some(Micros(-2_000)));
+        assert_eq!(Nanos(1_500).to_micros_floor(), Micros(1));
+        assert_eq!(Nanos(1_500).to_micros_ceil(), Micros(2));
+        assert_eq!(Nanos(-1_500).to_micros_floor(), Micros(-2));
+        assert_eq!(Nanos(-1_500).to_micros_ceil(), Micros(-1));
+        assert_eq!(Nanos(2_000_000).to_millis_ceil(), Millis(2));
+    }
+
+    #[test]
+    fn test_marzullo_interval_with_units() {
+        // A source reporting in microseconds is converted explicitly before joining the others:
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, Nanos(11_000), Nanos(13_000)));
+        source_bounds.extend(SourceBound::pair(1, Nanos(10_000), Nanos(12_000)));
+        source_bounds.extend(SourceBound::pair(
+            2,
+            Micros(8).to_nanos().unwrap(),
+            Micros(12).to_nanos().unwrap(),
+        ));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), Nanos(11_000));
+        assert_eq!(interval.upper_bound(), Nanos(12_000));
+        assert_eq!(interval.width(), 1_000);
+        assert_eq!(interval.sources_true(), 3);
+    }
+}
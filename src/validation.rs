@@ -0,0 +1,161 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{BoundType, SourceBound, Value};
+
+/// A problem with a set of source bounds that would make the result of the algorithm meaningless.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ValidationIssue<T = i64> {
+    /// Every source contributes exactly two bounds, so the number of bounds must be even.
+    OddBoundCount {
+        count: usize,
+    },
+    MissingLowerBound {
+        source: u8,
+    },
+    MissingUpperBound {
+        source: u8,
+    },
+    DuplicateBound {
+        source: u8,
+        bound_type: BoundType,
+    },
+    /// The lower bound of the source is greater than its upper bound.
+    InvertedBounds {
+        source: u8,
+        lower: T,
+        upper: T,
+    },
+}
+
+impl<T: Value> core::fmt::Display for ValidationIssue<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ValidationIssue::OddBoundCount { count } => {
+                write!(f, "expected an even number of bounds, got {}", count)
+            }
+            ValidationIssue::MissingLowerBound { source } => {
+                write!(f, "source {} has no lower bound", source)
+            }
+            ValidationIssue::MissingUpperBound { source } => {
+                write!(f, "source {} has no upper bound", source)
+            }
+            ValidationIssue::DuplicateBound { source, bound_type } => {
+                write!(
+                    f,
+                    "source {} has more than one {:?} bound",
+                    source, bound_type
+                )
+            }
+            ValidationIssue::InvertedBounds {
+                source,
+                lower,
+                upper,
+            } => write!(
+                f,
+                "source {} has a lower bound {:?} greater than its upper bound {:?}",
+                source, lower, upper
+            ),
+        }
+    }
+}
+
+/// Checks that every source contributes exactly one lower bound and one upper bound, and that
+/// each lower bound is less than or equal to its upper bound. All issues are reported at once,
+/// ordered by source, so that ingestion layers can reject bad data before running the algorithm.
+pub fn validate_source_bounds<T: Value>(
+    source_bounds: &[SourceBound<T>],
+) -> Result<(), Vec<ValidationIssue<T>>> {
+    let mut issues = Vec::new();
+    if !source_bounds.len().is_multiple_of(2) {
+        issues.push(ValidationIssue::OddBoundCount {
+            count: source_bounds.len(),
+        });
+    }
+
+    let mut sources: BTreeMap<u8, (Vec<&T>, Vec<&T>)> = BTreeMap::new();
+    for bound in source_bounds {
+        let (lowers, uppers) = sources.entry(bound.source).or_default();
+        match bound.bound_type {
+            BoundType::Lower => lowers.push(&bound.value),
+            BoundType::Upper => uppers.push(&bound.value),
+        }
+    }
+
+    for (source, (lowers, uppers)) in sources {
+        match (lowers.as_slice(), uppers.as_slice()) {
+            ([lower], [upper]) => {
+                if lower > upper {
+                    issues.push(ValidationIssue::InvertedBounds {
+                        source,
+                        lower: (*lower).clone(),
+                        upper: (*upper).clone(),
+                    });
+                }
+            }
+            _ => {
+                for (values, bound_type) in
+                    [(&lowers, BoundType::Lower), (&uppers, BoundType::Upper)]
+                {
+                    if values.is_empty() {
+                        issues.push(match bound_type {
+                            BoundType::Lower => ValidationIssue::MissingLowerBound { source },
+                            BoundType::Upper => ValidationIssue::MissingUpperBound { source },
+                        });
+                    } else if values.len() > 1 {
+                        issues.push(ValidationIssue::DuplicateBound { source, bound_type });
+                    }
+                }
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_source_bounds() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, 1, 3));
+        source_bounds.extend(SourceBound::pair_optional(1, None, Some(2)));
+        assert_eq!(validate_source_bounds(&source_bounds), Ok(()));
+        assert_eq!(validate_source_bounds::<i64>(&[]), Ok(()));
+
+        let source_bounds = vec![
+            SourceBound::lower(0, 5),
+            SourceBound::upper(0, 4),
+            SourceBound::lower(1, 1),
+            SourceBound::lower(1, 2),
+            SourceBound::upper(1, 3),
+            SourceBound::lower(2, 0),
+            SourceBound::upper(3, 0),
+        ];
+        assert_eq!(
+            validate_source_bounds(&source_bounds),
+            Err(vec![
+                ValidationIssue::OddBoundCount { count: 7 },
+                ValidationIssue::InvertedBounds {
+                    source: 0,
+                    lower: 5,
+                    upper: 4
+                },
+                ValidationIssue::DuplicateBound {
+                    source: 1,
+                    bound_type: BoundType::Lower
+                },
+                ValidationIssue::MissingUpperBound { source: 2 },
+                ValidationIssue::MissingLowerBound { source: 3 },
+            ])
+        );
+    }
+}
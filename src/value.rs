@@ -0,0 +1,409 @@
+use core::fmt;
+
+/// A value that source bounds can take. The algorithm only needs to order values and to compare
+/// the widths of candidate intervals when breaking a tie for best overlap.
+pub trait Value: Ord + Clone + fmt::Debug {
+    /// The width of an interval. This is typically an unsigned counterpart of the value type, so
+    /// that the width of an interval spanning the whole domain does not overflow.
+    type Width: Ord + Clone + fmt::Debug;
+
+    /// Returns the width of `[lower, upper]`, where `lower <= upper`.
+    fn width(lower: &Self, upper: &Self) -> Self::Width;
+
+    /// Approximates a width as a floating-point number, for reporting it as a metric. Returns
+    /// `None` by default, for value types without a meaningful approximation.
+    fn width_to_f64(_width: &Self::Width) -> Option<f64> {
+        None
+    }
+
+    /// Returns the signed distance from `from` to `to` in the unit of the values, saturating at
+    /// the bounds of `i64`. Returns `None` by default, for value types without a unit to count,
+    /// like `Ordered` keys.
+    fn signed_distance(_from: &Self, _to: &Self) -> Option<i64> {
+        None
+    }
+}
+
+/// Returns a width as a distance, negative if `negative`, saturating at the bounds of `i64`.
+pub(crate) fn saturating_distance(width: u128, negative: bool) -> i64 {
+    let width = u64::try_from(width).unwrap_or(u64::MAX);
+    if negative {
+        0_i64.saturating_sub_unsigned(width)
+    } else {
+        0_i64.saturating_add_unsigned(width)
+    }
+}
+
+/// A value type of any ordered keys without arithmetic, e.g. log positions as `(term, index)`
+/// tuples, or names. Nothing but the order of the keys is used, so widths cannot be told apart: of
+/// intervals with equal overlap, the lowest one is chosen rather than the smallest.
+///
+/// Integer keys such as sequence numbers need no wrapper, as they are values already.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ordered<K>(pub K);
+
+impl<K: Ord + Clone + fmt::Debug> Value for Ordered<K> {
+    type Width = ();
+
+    fn width(_lower: &Self, _upper: &Self) {}
+}
+
+/// A value type with sentinels for sources that can only bound the value on one side.
+pub trait Unbounded: Value {
+    /// The sentinel value of a lower bound for a source that cannot bound the value from below.
+    fn unbounded_lower() -> Self;
+    /// The sentinel value of an upper bound for a source that cannot bound the value from above.
+    fn unbounded_upper() -> Self;
+}
+
+macro_rules! impl_value_for_integers {
+    ($($ty:ty => $width:ty),*) => {
+        $(
+            impl Value for $ty {
+                type Width = $width;
+
+                fn width(lower: &Self, upper: &Self) -> Self::Width {
+                    upper.abs_diff(*lower)
+                }
+
+                fn width_to_f64(width: &Self::Width) -> Option<f64> {
+                    Some(*width as f64)
+                }
+
+                fn signed_distance(from: &Self, to: &Self) -> Option<i64> {
+                    Some(saturating_distance(to.abs_diff(*from) as u128, to < from))
+                }
+            }
+
+            impl Unbounded for $ty {
+                fn unbounded_lower() -> Self {
+                    <$ty>::MIN
+                }
+
+                fn unbounded_upper() -> Self {
+                    <$ty>::MAX
+                }
+            }
+        )*
+    };
+}
+
+impl_value_for_integers!(
+    i8 => u8,
+    i16 => u16,
+    i32 => u32,
+    i64 => u64,
+    i128 => u128,
+    isize => usize,
+    u8 => u8,
+    u16 => u16,
+    u32 => u32,
+    u64 => u64,
+    u128 => u128,
+    usize => usize
+);
+
+/// Arbitrary-precision bounds, for exact arithmetic on very large or very precise values. There
+/// are no sentinels for unbounded sources, as the domain itself is unbounded.
+#[cfg(feature = "num-bigint")]
+impl Value for num_bigint::BigInt {
+    type Width = num_bigint::BigUint;
+
+    fn width(lower: &Self, upper: &Self) -> Self::Width {
+        (upper - lower).into_parts().1
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl Value for num_bigint::BigUint {
+    type Width = num_bigint::BigUint;
+
+    fn width(lower: &Self, upper: &Self) -> Self::Width {
+        upper - lower
+    }
+}
+
+/// Exact decimal bounds. The width saturates at `Decimal::MAX` where the difference cannot be
+/// represented, so intervals spanning the `Unbounded` sentinels compare as equally wide.
+#[cfg(feature = "rust_decimal")]
+impl Value for rust_decimal::Decimal {
+    type Width = rust_decimal::Decimal;
+
+    fn width(lower: &Self, upper: &Self) -> Self::Width {
+        upper
+            .checked_sub(*lower)
+            .unwrap_or(rust_decimal::Decimal::MAX)
+    }
+
+    fn width_to_f64(width: &Self::Width) -> Option<f64> {
+        rust_decimal::prelude::ToPrimitive::to_f64(width)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl Unbounded for rust_decimal::Decimal {
+    fn unbounded_lower() -> Self {
+        rust_decimal::Decimal::MIN
+    }
+
+    fn unbounded_upper() -> Self {
+        rust_decimal::Decimal::MAX
+    }
+}
+
+/// Unit-safe bounds on physical quantities, for fusing sensor ranges such as temperatures or
+/// distances. Only integer storage types are ordered, and uom stores quantities in the base unit
+/// of the dimension (e.g. kelvin, meter), so the base unit is also the resolution of the bounds.
+/// The width of an interval is a quantity of the same dimension, saturating where the difference
+/// cannot be represented.
+#[cfg(feature = "uom")]
+macro_rules! impl_value_for_quantities {
+    ($($ty:ty),*) => {
+        $(
+            impl<D, U> Value for uom::si::Quantity<D, U, $ty>
+            where
+                D: uom::si::Dimension + ?Sized,
+                U: uom::si::Units<$ty> + ?Sized,
+            {
+                type Width = uom::si::Quantity<D, U, $ty>;
+
+                fn width(lower: &Self, upper: &Self) -> Self::Width {
+                    uom::si::Quantity {
+                        dimension: core::marker::PhantomData,
+                        units: core::marker::PhantomData,
+                        value: upper.value.saturating_sub(lower.value),
+                    }
+                }
+
+                fn width_to_f64(width: &Self::Width) -> Option<f64> {
+                    Some(width.value as f64)
+                }
+            }
+
+            impl<D, U> Unbounded for uom::si::Quantity<D, U, $ty>
+            where
+                D: uom::si::Dimension + ?Sized,
+                U: uom::si::Units<$ty> + ?Sized,
+            {
+                fn unbounded_lower() -> Self {
+                    uom::si::Quantity {
+                        dimension: core::marker::PhantomData,
+                        units: core::marker::PhantomData,
+                        value: <$ty>::MIN,
+                    }
+                }
+
+                fn unbounded_upper() -> Self {
+                    uom::si::Quantity {
+                        dimension: core::marker::PhantomData,
+                        units: core::marker::PhantomData,
+                        value: <$ty>::MAX,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "uom")]
+impl_value_for_quantities!(i32, i64, i128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interval, SourceBound};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_integer_widths_do_not_overflow() {
+        assert_eq!(i64::width(&i64::MIN, &i64::MAX), u64::MAX);
+        assert_eq!(i128::width(&i128::MIN, &i128::MAX), u128::MAX);
+        assert_eq!(u128::width(&0, &u128::MAX), u128::MAX);
+        assert_eq!(i8::width(&-3, &4), 7);
+    }
+
+    #[test]
+    fn test_marzullo_interval_with_u128_nanoseconds() {
+        // Unix-epoch nanoseconds in the year 2300, past the range of i64:
+        let base: u128 = 10_413_792_000 * 1_000_000_000;
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, base + 11, base + 13));
+        source_bounds.extend(SourceBound::pair(1, base + 10, base + 12));
+        source_bounds.extend(SourceBound::pair(2, base + 8, base + 12));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), base + 11);
+        assert_eq!(interval.upper_bound(), base + 12);
+        assert_eq!(interval.width(), 1);
+        assert_eq!(interval.sources_true(), 3);
+
+        // The tie-break compares widths that span the whole domain without overflowing:
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, None));
+        source_bounds.extend(SourceBound::pair_optional(1, Some(base), None));
+        source_bounds.extend(SourceBound::pair_optional(2, Some(base + 5), None));
+        source_bounds.extend(SourceBound::pair(3, 1u128, 2));
+        source_bounds.extend(SourceBound::pair(4, base + 5, base + 7));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), base + 5);
+        assert_eq!(interval.upper_bound(), base + 7);
+        assert_eq!(interval.sources_true(), 4);
+        assert_eq!(interval.sources_false(), 1);
+    }
+
+    #[test]
+    fn test_marzullo_interval_with_ordered_keys() {
+        // The log sequence numbers that replicas report as durable, each as the range between its
+        // last checkpoint and its last flush. Replica 3 has fallen behind:
+        let mut source_bounds: Vec<SourceBound<u64>> = Vec::new();
+        for (replica, (checkpoint, flushed)) in
+            [(1_000, 1_480), (1_100, 1_500), (1_200, 1_450), (300, 600)]
+                .into_iter()
+                .enumerate()
+        {
+            source_bounds.extend(SourceBound::pair(replica as u8, checkpoint, flushed));
+        }
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (interval.lower_bound(), interval.upper_bound()),
+            (1_200, 1_450)
+        );
+        assert_eq!(interval.sources_true(), 3);
+
+        // Log positions as `(term, index)`, ordered without arithmetic. Two ranges of replicas
+        // agree equally, of which the lower one is chosen, although the other is narrower:
+        let position = |term: u32, index: u64| Ordered((term, index));
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, position(1, 10), position(1, 90)));
+        source_bounds.extend(SourceBound::pair(1, position(1, 50), position(2, 5)));
+        source_bounds.extend(SourceBound::pair(2, position(2, 1), position(2, 3)));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), position(1, 50));
+        assert_eq!(interval.upper_bound(), position(1, 90));
+        assert_eq!(interval.sources_true(), 2);
+    }
+
+    #[cfg(feature = "num-bigint")]
+    #[test]
+    fn test_marzullo_interval_with_big_integers() {
+        use num_bigint::{BigInt, BigUint};
+
+        // Offsets of 10^40, far beyond any primitive integer:
+        let base = BigInt::from(10u8).pow(40);
+        let mut source_bounds = Vec::new();
+        for (source, (lower, upper)) in [(11, 13), (10, 12), (8, 12)].into_iter().enumerate() {
+            source_bounds.extend(SourceBound::pair(
+                source as u8,
+                -&base + lower,
+                -&base + upper,
+            ));
+        }
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), -&base + 11);
+        assert_eq!(interval.upper_bound(), -&base + 12);
+        assert_eq!(interval.width(), BigUint::from(1u8));
+        assert_eq!(interval.sources_true(), 3);
+
+        // The smaller of two intervals with equal overlap is chosen:
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, BigInt::from(7), BigInt::from(9)));
+        source_bounds.extend(SourceBound::pair(1, BigInt::from(7), &base * 2));
+        source_bounds.extend(SourceBound::pair(2, base.clone(), &base + 1));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), base);
+        assert_eq!(interval.width(), BigUint::from(1u8));
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_marzullo_interval_with_decimals() {
+        use rust_decimal::Decimal;
+
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, decimal("7.10"), decimal("9.10")));
+        source_bounds.extend(SourceBound::pair(1, decimal("7.10"), decimal("12.00")));
+        source_bounds.extend(SourceBound::pair(2, decimal("10.005"), decimal("11.005")));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), decimal("10.005"));
+        assert_eq!(interval.upper_bound(), decimal("11.005"));
+        assert_eq!(interval.width(), decimal("1"));
+        assert_eq!(interval.sources_true(), 2);
+
+        // Intervals with the same value but a different scale are equally wide:
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, decimal("1.0"), decimal("2.0")));
+        source_bounds.extend(SourceBound::pair(1, decimal("3.00"), decimal("4.00")));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), decimal("1"));
+
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair_optional(0, None, None));
+        source_bounds.extend(SourceBound::pair_optional(1, Some(decimal("0.5")), None));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), decimal("0.5"));
+        assert!(interval.is_upper_unbounded());
+        assert_eq!(interval.width(), Decimal::MAX - decimal("0.5"));
+        assert_eq!(Decimal::width(&Decimal::MIN, &Decimal::MAX), Decimal::MAX);
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn test_marzullo_interval_with_quantities() {
+        use uom::si::i64::{Length, ThermodynamicTemperature};
+        use uom::si::length::{kilometer, meter};
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        // Two of three temperature sensors agree the temperature is in [301K, 302K]:
+        let kelvins = ThermodynamicTemperature::new::<kelvin>;
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(0, kelvins(300), kelvins(302)));
+        source_bounds.extend(SourceBound::pair(1, kelvins(301), kelvins(305)));
+        source_bounds.extend(SourceBound::pair(2, kelvins(280), kelvins(285)));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), kelvins(301));
+        assert_eq!(interval.upper_bound(), kelvins(302));
+        assert_eq!(interval.width(), kelvins(1));
+        assert_eq!(interval.sources_true(), 2);
+        assert_eq!(interval.sources_false(), 1);
+
+        // Bounds given in different units are compared in the base unit:
+        let mut source_bounds = Vec::new();
+        source_bounds.extend(SourceBound::pair(
+            0,
+            Length::new::<kilometer>(1),
+            Length::new::<kilometer>(2),
+        ));
+        source_bounds.extend(SourceBound::pair_optional(
+            1,
+            Some(Length::new::<meter>(1_500)),
+            None,
+        ));
+        let interval = Interval::try_from_source_bounds(source_bounds)
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.lower_bound(), Length::new::<meter>(1_500));
+        assert_eq!(interval.width(), Length::new::<meter>(500));
+    }
+}
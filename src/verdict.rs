@@ -0,0 +1,155 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{BoundType, Interval, SourceBound, Value};
+
+/// Whether a source agrees with the chosen interval, in NTP terms.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Verdict {
+    /// The source's interval contains the chosen interval.
+    Truechimer,
+    /// The source's interval does not contain the chosen interval.
+    Falseticker,
+}
+
+/// A source's interval and its verdict relative to the chosen interval.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceVerdict<T = i64> {
+    pub source: u8,
+    pub lower: T,
+    pub upper: T,
+    pub verdict: Verdict,
+    /// The signed distance of the source's interval from the chosen interval: negative below it,
+    /// positive above it, and zero if they overlap. A source that barely misses is then told apart
+    /// from one that is seconds away, and one that drifts away over successive selections is
+    /// spotted before it becomes a falseticker. `None` for value types without distances.
+    pub distance: Option<i64>,
+}
+
+/// Returns the signed distance of `[lower, upper]` from `interval`, as `SourceVerdict::distance`.
+pub(crate) fn distance<T: Value>(lower: &T, upper: &T, interval: &Interval<T>) -> Option<i64> {
+    if *upper < interval.lower_bound {
+        T::signed_distance(&interval.lower_bound, upper)
+    } else if *lower > interval.upper_bound {
+        T::signed_distance(&interval.upper_bound, lower)
+    } else {
+        Some(0)
+    }
+}
+
+/// Pairs up the lower and upper bound of every source, ordered by source. Sources missing either
+/// bound are skipped; use `validate_source_bounds` to reject such input.
+pub(crate) fn source_intervals<T: Value>(source_bounds: &[SourceBound<T>]) -> Vec<(u8, T, T)> {
+    let mut sources: BTreeMap<u8, (Option<&T>, Option<&T>)> = BTreeMap::new();
+    for bound in source_bounds {
+        let (lower, upper) = sources.entry(bound.source).or_default();
+        match bound.bound_type {
+            BoundType::Lower => *lower = Some(&bound.value),
+            BoundType::Upper => *upper = Some(&bound.value),
+        }
+    }
+
+    sources
+        .into_iter()
+        .filter_map(|(source, bounds)| match bounds {
+            (Some(lower), Some(upper)) => Some((source, lower.clone(), upper.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<T: Value> Interval<T> {
+    /// Whether `[lower, upper]` contains the interval, i.e. the source with that interval is
+    /// counted in `sources_true`.
+    pub fn is_contained_by(&self, lower: &T, upper: &T) -> bool {
+        *lower <= self.lower_bound && *upper >= self.upper_bound
+    }
+
+    /// Returns the verdict of every source in `source_bounds`, ordered by source. For the interval
+    /// chosen from the same bounds, the number of truechimers equals `sources_true`.
+    pub fn verdicts(&self, source_bounds: &[SourceBound<T>]) -> Vec<SourceVerdict<T>> {
+        source_intervals(source_bounds)
+            .into_iter()
+            .map(|(source, lower, upper)| SourceVerdict {
+                source,
+                verdict: if self.is_contained_by(&lower, &upper) {
+                    Verdict::Truechimer
+                } else {
+                    Verdict::Falseticker
+                },
+                distance: distance(&lower, &upper, self),
+                lower,
+                upper,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ordered;
+
+    #[test]
+    fn test_verdicts() {
+        let mut source_bounds: Vec<SourceBound> = Vec::new();
+        source_bounds.extend(SourceBound::pair(2, 8, 12));
+        source_bounds.extend(SourceBound::pair(0, 11, 13));
+        source_bounds.extend(SourceBound::pair(1, 14, 15));
+        let interval = Interval::try_from_source_bounds(source_bounds.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            interval.verdicts(&source_bounds),
+            vec![
+                SourceVerdict {
+                    source: 0,
+                    lower: 11,
+                    upper: 13,
+                    verdict: Verdict::Truechimer,
+                    distance: Some(0),
+                },
+                SourceVerdict {
+                    source: 1,
+                    lower: 14,
+                    upper: 15,
+                    verdict: Verdict::Falseticker,
+                    distance: Some(2),
+                },
+                SourceVerdict {
+                    source: 2,
+                    lower: 8,
+                    upper: 12,
+                    verdict: Verdict::Truechimer,
+                    distance: Some(0),
+                },
+            ]
+        );
+
+        let interval = Interval::try_from_source_bounds(Vec::from(SourceBound::pair(0, 5, 6)))
+            .unwrap()
+            .unwrap();
+        let mut source_bounds = Vec::from(SourceBound::pair(1, -10, -2));
+        source_bounds.extend(SourceBound::pair(2, i64::MIN, i64::MIN));
+        let distances: Vec<_> = interval
+            .verdicts(&source_bounds)
+            .iter()
+            .map(|verdict| verdict.distance)
+            .collect();
+        assert_eq!(distances, [Some(-7), Some(i64::MIN)]);
+
+        // Ordered keys have no distances:
+        let source_bounds = Vec::from(SourceBound::pair(0, Ordered("a"), Ordered("b")));
+        let interval = Interval::try_from_source_bounds(source_bounds.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval.verdicts(&source_bounds)[0].distance, Some(0));
+        let mut source_bounds = source_bounds;
+        source_bounds.extend(SourceBound::pair(1, Ordered("x"), Ordered("y")));
+        assert_eq!(interval.verdicts(&source_bounds)[1].distance, None);
+    }
+}
@@ -0,0 +1,117 @@
+//! The outcome of a `SendPipeline` as a tokio `watch` channel, so that application code awaits
+//! changes of the agreed time rather than polling for them. A receiver is wrapped into a `Stream`
+//! of outcomes by `tokio_stream::wrappers::WatchStream`.
+
+use ::tokio::sync::watch;
+
+use crate::source::{Root, Sample};
+use crate::{MarzulloError, SelectionOutcome, SendPipeline};
+
+/// Runs a `SendPipeline`, and publishes every outcome to the receivers of its channel. Receivers
+/// are only notified when the chosen interval or the estimate changed, rather than on every run,
+/// but always see the latest outcome. The publisher is `Send`, so that it runs on any worker of a
+/// multi-threaded runtime.
+pub struct Publisher {
+    pipeline: SendPipeline,
+    sender: watch::Sender<SelectionOutcome>,
+}
+
+impl Publisher {
+    /// Returns the publisher, and a receiver of its outcomes, the default outcome until the first
+    /// run.
+    pub fn new(pipeline: SendPipeline) -> (Publisher, watch::Receiver<SelectionOutcome>) {
+        let (sender, receiver) = watch::channel(SelectionOutcome::default());
+        (Publisher { pipeline, sender }, receiver)
+    }
+
+    /// Returns another receiver of the outcomes, which sees the latest one as seen already.
+    pub fn subscribe(&self) -> watch::Receiver<SelectionOutcome> {
+        self.sender.subscribe()
+    }
+
+    pub fn pipeline_mut(&mut self) -> &mut SendPipeline {
+        &mut self.pipeline
+    }
+
+    /// Runs the pipeline as `Pipeline::run`, and publishes the outcome. Errors are returned rather
+    /// than published, so that receivers keep the last outcome.
+    pub fn run(
+        &mut self,
+        samples: impl IntoIterator<Item = (u8, Sample)>,
+    ) -> Result<(), MarzulloError> {
+        let outcome = self.pipeline.run(samples)?;
+        self.publish(outcome);
+        Ok(())
+    }
+
+    /// Runs the pipeline as `Pipeline::run_with_root`, and publishes the outcome.
+    pub fn run_with_root(
+        &mut self,
+        samples: impl IntoIterator<Item = (u8, Sample, Root)>,
+    ) -> Result<(), MarzulloError> {
+        let outcome = self.pipeline.run_with_root(samples)?;
+        self.publish(outcome);
+        Ok(())
+    }
+
+    fn publish(&self, outcome: SelectionOutcome) {
+        self.sender.send_if_modified(|latest| {
+            let changed =
+                latest.interval != outcome.interval || latest.estimate != outcome.estimate;
+            *latest = outcome;
+            changed
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(offset: i64) -> (u8, Sample) {
+        (
+            0,
+            Sample {
+                local_time: 0,
+                offset,
+                uncertainty: 100,
+            },
+        )
+    }
+
+    #[::tokio::test]
+    async fn test_publisher() {
+        let (mut publisher, mut receiver) =
+            Publisher::new(SendPipeline::default().without_filter());
+        let waiter = ::tokio::spawn(async move {
+            receiver.changed().await.unwrap();
+            receiver.borrow_and_update().interval.clone()
+        });
+        publisher.run([sample(1_000)]).unwrap();
+        let interval = waiter.await.unwrap().unwrap();
+        assert_eq!(interval.lower_bound(), 900);
+
+        // The same outcome again does not notify:
+        let receiver = publisher.subscribe();
+        publisher.run([sample(1_000)]).unwrap();
+        assert!(!receiver.has_changed().unwrap());
+        publisher.run([sample(2_000)]).unwrap();
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(receiver.borrow().estimate.unwrap().offset, 2_000);
+    }
+
+    #[::tokio::test(flavor = "multi_thread")]
+    async fn test_publisher_is_send() {
+        let (mut publisher, mut receiver) =
+            Publisher::new(SendPipeline::default().without_filter());
+        // The publisher may run on another worker thread than the receiver:
+        let runner = ::tokio::spawn(async move {
+            publisher.run([sample(1_000)]).unwrap();
+            publisher
+        });
+        receiver.changed().await.unwrap();
+        assert_eq!(receiver.borrow().estimate.unwrap().offset, 1_000);
+        let publisher = runner.await.unwrap();
+        assert!(!publisher.subscribe().has_changed().unwrap());
+    }
+}
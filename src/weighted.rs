@@ -0,0 +1,191 @@
+//! Selection with fractional weights: every source counts with a confidence in `[0, 1]` rather
+//! than as one, and the chosen interval is the smallest one with the most total weight. With all
+//! weights 1, it is the interval of Marzullo's algorithm.
+//!
+//! Weights are summed in fixed point, with 32 bits below the point, so that equal sums compare
+//! equal however they were added up.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::verdict::source_intervals;
+use crate::{BoundType, Interval, SourceBound, Value};
+
+/// One in the fixed point of the weights.
+const ONE: f64 = (1_u64 << 32) as f64;
+
+/// A weight that is not within `[0, 1]`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvalidWeight {
+    pub source: u8,
+    pub weight: f64,
+}
+
+impl core::fmt::Display for InvalidWeight {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "weight {} of source {} is not within [0, 1]",
+            self.weight, self.source
+        )
+    }
+}
+
+impl core::error::Error for InvalidWeight {}
+
+/// Why a weighted selection failed.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WeightedError {
+    InvalidWeight(InvalidWeight),
+    /// There are more sources than the 255 that `sources_true` and `sources_false` can count.
+    TooManySources {
+        sources: usize,
+    },
+}
+
+impl core::fmt::Display for WeightedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            WeightedError::InvalidWeight(invalid) => write!(f, "{}", invalid),
+            WeightedError::TooManySources { sources } => {
+                write!(
+                    f,
+                    "{} sources are more than the 255 an interval can count",
+                    sources
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for WeightedError {}
+
+/// The chosen interval and its total weight.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeightedInterval<T = i64> {
+    /// Of the interval, `sources_true` counts the sources that contain it, whatever their weight.
+    pub interval: Interval<T>,
+    /// The sum of the weights of the sources that contain the interval.
+    pub weight: f64,
+}
+
+/// Returns the smallest interval with the most total weight, by the weight of every source, 1 for
+/// sources without one, or `None` if no source has a positive weight. Sources missing either
+/// bound are skipped.
+pub fn select_weighted<T: Value>(
+    source_bounds: &[SourceBound<T>],
+    weights: &BTreeMap<u8, f64>,
+) -> Result<Option<WeightedInterval<T>>, WeightedError> {
+    let sources = source_intervals(source_bounds);
+    let count = u8::try_from(sources.len()).map_err(|_| WeightedError::TooManySources {
+        sources: sources.len(),
+    })?;
+    let mut fixed = BTreeMap::new();
+    for &(source, _, _) in &sources {
+        let weight = weights.get(&source).copied().unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(WeightedError::InvalidWeight(InvalidWeight {
+                source,
+                weight,
+            }));
+        }
+        fixed.insert(source, (weight * ONE) as u64);
+    }
+    let mut bounds: Vec<SourceBound<T>> = sources
+        .iter()
+        .flat_map(|(source, lower, upper)| SourceBound::pair(*source, lower.clone(), upper.clone()))
+        .collect();
+    bounds.sort();
+
+    let (mut total, mut best) = (0_u64, 0_u64);
+    let mut chosen: Option<(T, T)> = None;
+    for (index, bound) in bounds.iter().enumerate() {
+        match bound.bound_type {
+            BoundType::Lower => total += fixed[&bound.source],
+            BoundType::Upper => total -= fixed[&bound.source],
+        }
+        let Some(next) = bounds.get(index + 1) else {
+            break;
+        };
+        if total > best {
+            best = total;
+            chosen = Some((bound.value.clone(), next.value.clone()));
+        } else if total == best && total > 0 && next.bound_type == BoundType::Upper {
+            if let Some((lower, upper)) = &chosen {
+                if T::width(&bound.value, &next.value) < T::width(lower, upper) {
+                    chosen = Some((bound.value.clone(), next.value.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(chosen.map(|(lower_bound, upper_bound)| {
+        let mut interval = Interval {
+            lower_bound,
+            upper_bound,
+            sources_true: 0,
+            sources_false: 0,
+        };
+        let support = sources
+            .iter()
+            .filter(|(_, lower, upper)| interval.is_contained_by(lower, upper))
+            .count();
+        interval.sources_true = support as u8;
+        interval.sources_false = count - support as u8;
+        WeightedInterval {
+            interval,
+            weight: best as f64 / ONE,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bounds;
+
+    #[test]
+    fn test_select_weighted() {
+        let source_bounds: Vec<SourceBound> = bounds(&[(0, 0, 10), (1, 2, 12), (2, 20, 30)]);
+        // Unweighted, the two agreeing sources win:
+        let unweighted = select_weighted(&source_bounds, &BTreeMap::new())
+            .unwrap()
+            .unwrap();
+        let interval = &unweighted.interval;
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (2, 10));
+        assert_eq!(unweighted.weight, 2.0);
+
+        // Unless they are far less confident than the third:
+        let weights = BTreeMap::from([(0, 0.25), (1, 0.5), (2, 0.9)]);
+        let weighted = select_weighted(&source_bounds, &weights).unwrap().unwrap();
+        let interval = &weighted.interval;
+        assert_eq!((interval.lower_bound(), interval.upper_bound()), (20, 30));
+        assert_eq!((interval.sources_true(), interval.sources_false()), (1, 2));
+        assert!((weighted.weight - 0.9).abs() < 1e-9);
+
+        let zero = BTreeMap::from([(0, 0.0), (1, 0.0), (2, 0.0)]);
+        assert_eq!(select_weighted(&source_bounds, &zero), Ok(None));
+        let invalid = BTreeMap::from([(1, 1.5)]);
+        assert_eq!(
+            select_weighted(&source_bounds, &invalid),
+            Err(WeightedError::InvalidWeight(InvalidWeight {
+                source: 1,
+                weight: 1.5,
+            }))
+        );
+
+        // Up to 255 sources, as many as the interval can count:
+        let source_bounds: Vec<SourceBound> = (0..=u8::MAX)
+            .flat_map(|source| SourceBound::pair(source, 0, 1))
+            .collect();
+        let weighted = select_weighted(&source_bounds[..510], &BTreeMap::new()).unwrap();
+        assert_eq!(weighted.unwrap().interval.sources_true(), 255);
+        assert_eq!(
+            select_weighted(&source_bounds, &BTreeMap::new()),
+            Err(WeightedError::TooManySources { sources: 256 })
+        );
+    }
+}